@@ -1,24 +1,21 @@
 import pathlib
 import sys
 
-from lib.cmcut import DurationSecUnits, FrameLoudness, ProgramScenes
+import get_loudness_from_wav
+from lib.cmcut import ProgramScenes
 
 
 if __name__ == '__main__':
-    frame_per_sec = 8000
-    duration_threshold = 5000
     if len(sys.argv) < 2:
         raise ValueError("need wav file.")
     wav_path = sys.argv[1]
-    duration_sec_units = DurationSecUnits([15, 30])
 
-    loudness = FrameLoudness.get_loudness_from_wav(wav_path)
-    program_scenes = ProgramScenes.construct_program_scenes_without_structure(
-        loudness, 
-        duration_threshold, 
-        frame_per_sec, 
-        duration_sec_units, 
-        )
+    try:
+        # Silences of digital zero longer than 0.625 sec divide CMs searched without structures.
+        program_scenes = ProgramScenes.detect_program_scenes(wav_path)
+    except (get_loudness_from_wav.WavError, ValueError) as e:
+        # Exit with an error so that the caller can fall back.
+        sys.exit(f"{type(e).__name__}: {e}")
     video_basename = pathlib.Path(wav_path).stem
     total_duration = 0
     for index, section in enumerate(program_scenes.scene_sections):
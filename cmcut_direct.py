@@ -1,52 +1,56 @@
-import json
 import pathlib
 import sys
 
-from lib.cmcut import DurationSecUnits, FrameLoudness, ProgramScenes, NominalCMStructure
+import get_loudness_from_wav
+from lib.cmcut import ProgramProperty, ProgramScenes
 
 
 if __name__ == '__main__':
-    frame_per_sec = 8000
     if len(sys.argv) < 3:
         raise ValueError("need both wav file and program property file.")
     wav_path = sys.argv[1]
-    default_cm_structures_dict = [{"cm": 60}] * 3 
-    default_end_scene_duration_sec = 15
-    default_has_monolithic_cm = False
-    default_margin_sec = 3.5
-    default_duration_threshold = 4000
-    duration_sec_units = DurationSecUnits([15, 30])
+    try:
+        # Defaults apply to the keys the file leaves out.
+        program_property = ProgramProperty.load(sys.argv[2])
+    except (OSError, ValueError) as e:
+        sys.exit(f"{type(e).__name__}: {e}")
+    cue_wav_path = program_property.cue_wav_path
 
-    program_property = {}
-    program_property_json_path = sys.argv[2]
-    with open(program_property_json_path, "r") as json_file:
-        program_property = json.load(json_file)
-    cm_structures_dict = program_property.get("cm_structures", default_cm_structures_dict)
-    end_scene_duration_sec = program_property.get("end_scene_duration", default_end_scene_duration_sec)
-    has_monolithic_cm = program_property.get("has_monolithic_cm", default_has_monolithic_cm)
-    margin_sec = program_property.get("margin_sec", default_margin_sec)
-    duration_threshold = program_property.get("duration_threshold", default_duration_threshold)
-    additional_duration_units = program_property.get("additional_duration_units", [])
-    cm_structures = [
-        NominalCMStructure(value, margin_sec) for value in cm_structures_dict
-        ]
-
-    for duration in additional_duration_units:
-        if not duration in duration_sec_units.durations_sec:
-            duration_sec_units = duration_sec_units.append_duration(duration)
-#    print (f"#{duration_sec_units.durations_sec}")
-
-    loudness = FrameLoudness.get_loudness_from_wav(wav_path)
-#    print (f"DURATION THRESHOLD: {duration_threshold}")
-    program_scenes = ProgramScenes.construct_program_scenes(
-        loudness, 
-        duration_threshold, 
-        frame_per_sec, 
-        duration_sec_units, 
-        cm_structures, 
-        end_scene_duration_sec,
-        has_monolithic_cm
-        )
+    try:
+        if program_property.compare_audio_streams:
+            comparison = ProgramScenes.compare_audio_streams(
+                wav_path, 
+                program_property.duration_threshold, 
+                program_property.channel_mode, 
+                program_property.silence_threshold_dbfs, 
+                analysis_rate=program_property.analysis_rate, 
+                duration_threshold_sec=program_property.duration_threshold_sec, 
+                )
+            for stream, ratio, sections in zip(
+                comparison.streams, comparison.silent_ratios, comparison.unmatched_sections
+                ):
+                print (f"#audio stream 0x{stream.pid:04x}: silent ratio {ratio}, unmatched {sections}")
+        # Silences and CM sections follow the settings and CM structures of the property,
+        # and the silences report what the single pass over the file found.
+        program_scenes = ProgramScenes.detect_program_scenes(wav_path, program_property)
+    except (get_loudness_from_wav.WavError, ValueError) as e:
+        # Exit with an error so that the caller can fall back.
+        sys.exit(f"{type(e).__name__}: {e}")
+    silences = program_scenes.silences
+    for stream in silences.info.audio_streams:
+        print (f"#{stream}")
+    if silences.info.audio_streams:
+        print (f"#audio_stream: {program_property.audio_stream}")
+    if silences.info.corruption is not None:
+        print (f"#corruption: {silences.info.corruption}")
+    if program_property.silence_threshold_dbfs == "auto":
+        print (f"#silence_threshold_dbfs: {silences.threshold_dbfs}")
+    if cue_wav_path is not None:
+        try:
+            program_scenes.write_cue_points(wav_path, cue_wav_path)
+        except get_loudness_from_wav.WavError as e:
+            sys.exit(f"{type(e).__name__}: {e}")
+        print (f"#cue_wav_path: {cue_wav_path}")
     video_basename = pathlib.Path(wav_path).stem
     total_duration = 0
     for index, section in enumerate(program_scenes.scene_sections):
@@ -6,9 +6,8 @@ eval "$(pyenv init --path)"
 eval "$(pyenv init -)"
 pyenv activate analysis
 cd $(dirname $0)
-if [ -e $2 ]
+if [ -e $2 ] && python3 cmcut_direct.py $1 $2 > tmp.sh
 then
-	python3 cmcut_direct.py $1 $2 > tmp.sh
 	RES=`tail -1 tmp.sh|awk -F '#' '{print int($2)}'`
 	DIFF=$((RES-EFFT)) 
 	if [ $DIFF -gt -$ALLOWED_MARGIN -a $DIFF -lt $ALLOWED_MARGIN ]
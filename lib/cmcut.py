@@ -1,265 +1,320 @@
 """Library to cut CM."""
 from __future__ import annotations
-import copy
-from typing import List, Tuple, Dict, Optional, Union
+import datetime
+from typing import BinaryIO, List, Tuple, Optional, Union
 
 import get_loudness_from_wav
 import numpy as np
 
+# Anything get_loudness_from_wav reads audio from.
+AudioSource = Union[str, bytes, BinaryIO, get_loudness_from_wav.DecodedAudio]
 
-class SilentSection:
-    """SilentSection
-    This class represents property of silent scene.
-    Attributes:
-        frame_per_sec (float): Factor to convert frame index to sec.
-        start_sec (float): Start timing of the scene in sec.
-        end_sec (float): End timing of the scene in sec.
-    """
-    def __init__(
-        self, 
-        start_frame_index: int, 
-        end_frame_index: int, 
-        frame_per_sec: float = 8000
-        ):
-        """Initialize a SilentSection object.
-        Args:
-            start_frame_index (int): Start timing of the scene in frame_index.
-            end_frame_index (int): End timing of the scene in frame_index.
-            frame_per_sec (float): Factor to convert frame index to sec.
-        """
-        if type(start_frame_index) is not int:
-            raise TypeError(f"Type of start_frame_index must be int: {type(start_frame_index)}.")
-        if start_frame_index < 0:
-            raise ValueError(f"start_frame_index must be non-negative: {start_frame_index}.")
-        if type(end_frame_index) is not int:
-            raise TypeError(f"Type of end_frame_index must be int: {type(end_frame_index)}.")
-        if end_frame_index <= 0:
-            raise ValueError(f"end_frame_index must be positive: {end_frame_index}.")
-        if start_frame_index >= end_frame_index:
-            raise ValueError(f"start_frame_index must be less than end_frame_index: {start_frame_index} {end_frame_index}.")
-        if frame_per_sec <= 0:
-            raise ValueError(f"frame_per_sec must be positive: {frame_per_sec}.")
-        self.frame_per_sec = frame_per_sec
-        self.start_sec = start_frame_index/frame_per_sec
-        self.end_sec = end_frame_index/frame_per_sec
 
-    def __repr__(self):
-        return repr([self.start_sec, self.end_sec, self.frame_per_sec])
+# Silent section kept in frame indices, with start_sec and end_sec derived from frame_per_sec.
+SilentSection = get_loudness_from_wav.SilentSection
 
-    def duration_sec(self) -> float:
-        """Duration of the section."""
-        return self.end_sec - self.start_sec
-
-    def is_cm_divider_candidate(
-        self, 
-        following_sections: List[SilentSection], 
-        duration_sec_units: DurationSecUnits, 
-        margin_sec: float
-        ) -> bool:
-        """Judge if the section is candidate of CM.
-        If Duration between the section and the last section 
-        is one of the duration units, the section could be a candidate of cm divider.
-        Args:
-            last_section (SilentSection): A section which is just before the section.
-            duration_sec_units (DurationSecUnits): Durations of CM.
-            margin_sec (float): Fluctuation of duration to be considered.
-        """
-        for section in following_sections:
-            duration_sec = section.end_sec - self.start_sec
-            largest_duration_unit_sec = duration_sec_units.durations_sec[-1] 
-            if largest_duration_unit_sec + margin_sec <= duration_sec:
-                break
-            for duration_sec_unit in duration_sec_units.durations_sec:
-#                print (f"#### {self}, {section}, {duration_sec}")
-                if duration_sec_unit < duration_sec <= duration_sec_unit + margin_sec:
-                    return True
-        return False
 
 class FrameLoudness:
     """FrameLoudness
     This class represents timeseries of sound loudness of a TV program.
     Attributes:
-        values (List[float]): Timeseries of loudness.
+        values (np.ndarray): Timeseries of loudness in float32.
+        frame_per_sec (Optional[float]): Factor to convert frame index to sec, if known.
+        info (Optional[get_loudness_from_wav.WavInfo]): Header metadata of the source wav file.
     """
-    def __init__(self, loudness_values: List[float]):
+    def __init__(
+        self,
+        loudness_values: Union[np.ndarray, List[float]],
+        frame_per_sec: Optional[float] = None,
+        info: Optional[get_loudness_from_wav.WavInfo] = None
+        ):
         """Initialize a FrameLoudness object.
         Args:
-            loudness_array (List[float]): Timeseries of loudness.
+            loudness_values (Union[np.ndarray, List[float]]): Timeseries of loudness.
+            frame_per_sec (Optional[float]): Factor to convert frame index to sec, if known.
+            info (Optional[get_loudness_from_wav.WavInfo]): Header metadata of the source wav file.
         """
-        type_error_message = "Loudness_array must be List[float]"
-        if type(loudness_values) is not list:
+        type_error_message = "Loudness_array must be 1-D float array"
+        if type(loudness_values) is list:
+            loudness_values = np.asarray(loudness_values)
+        if not isinstance(loudness_values, np.ndarray):
             raise TypeError(
                 f"{type_error_message}: {type(loudness_values)}."
                 )
+        if loudness_values.ndim != 1 or not np.issubdtype(loudness_values.dtype, np.floating):
+            raise TypeError(
+                f"{type_error_message}: {loudness_values.dtype} {loudness_values.shape}."
+                )
         if len(loudness_values) <= 0:
             raise TypeError(
                 f"{type_error_message}: length {len(loudness_values)}."
                 )
-        filtered_values = list(filter(lambda x: type(x) is float, loudness_values))
-        if len(filtered_values) != len(loudness_values):
-            raise TypeError(type_error_message)
-        if len(list(filter(lambda x: x<0, filtered_values))) > 0:
+        if (loudness_values < 0).any():
             raise TypeError("Loudness must be positive.")
+        if frame_per_sec is not None and frame_per_sec <= 0:
+            raise ValueError(f"frame_per_sec must be positive: {frame_per_sec}.")
 
         self.values = loudness_values
+        self.frame_per_sec = frame_per_sec
+        self.info = info
 
     @classmethod
-    def get_loudness_from_wav(cls, wav_path:str) -> FrameLoudness:
+    def get_loudness_from_wav(
+        cls,
+        wav_path: AudioSource,
+        channel_mode: Union[str, int] = "all",
+        audio_stream: Union[int, str] = 0,
+        lenient: bool = False,
+        analysis_rate: Optional[int] = None
+        ) -> FrameLoudness:
         """Get loudness timeseries from wav file.
         Args:
-            wav_path (str): A path of wav file.
+            wav_path (AudioSource): A path of wav file, or of the MPEG-2 transport stream to decode audio from.
+                The file in memory, a readable binary file or pipe, or decoded samples are read as well.
+            channel_mode (Union[str, int]): How channels drive the loudness,
+                either "all", "mid", "side", "interleaved" or a channel index.
+                "all" is silent only if all channels are silent.
+            audio_stream (Union[int, str]): Audio stream of the transport stream,
+                either an index, a PID like "0x0111" or a language like "jpn".
+            lenient (bool): Whether to analyse a truncated or corrupted wav file up to the corruption
+                instead of raising, which info.corruption reports.
+                Damaged audio of a transport stream is filled with silence.
+            analysis_rate (Optional[int]): Rate to decimate the samples to before taking their loudness,
+                so that frames mean the same duration whatever the sample rate of the file.
         """
-        loudness_values = get_loudness_from_wav.wav2loudness(wav_path)
-        return FrameLoudness(loudness_values)
+        loudness_values, info = get_loudness_from_wav.wav2loudness_with_info(
+            wav_path, channel_mode, audio_stream, lenient, analysis_rate
+            )
+        frame_per_sec = info.analysis_rate
+        if channel_mode == "interleaved":
+            # Values are interleaved samples, so each channel adds a frame.
+            frame_per_sec *= info.channels
+        return FrameLoudness(loudness_values, frame_per_sec, info)
 
-class NominalCMStructure:
-    """NominalCMStructure
-    This class represents nominal CM structure, 
-    which could consists both actual CM and indistinguishable program scene.
+    @classmethod
+    def get_envelope_from_wav(
+        cls,
+        wav_path: AudioSource,
+        window_sec: float = 0.01,
+        hop_sec: Optional[float] = None,
+        kind: str = "rms",
+        channel_mode: Union[str, int] = "all",
+        audio_stream: Union[int, str] = 0,
+        lenient: bool = False
+        ) -> FrameLoudness:
+        """Get downsampled loudness timeseries from wav file.
+        A frame of the timeseries is a hop, so frame_per_sec is the hop rate.
+        Args:
+            wav_path (AudioSource): A path of wav file, or of the MPEG-2 transport stream to decode audio from.
+                The file in memory, a readable binary file or pipe, or decoded samples are read as well.
+            window_sec (float): Window length to take the loudness over in sec.
+            hop_sec (Optional[float]): Interval between windows in sec, window_sec by default.
+            kind (str): Statistic over the window, either "rms", "peak" or "mean_abs".
+            channel_mode (Union[str, int]): How channels drive the loudness,
+                either "all", "mid", "side" or a channel index.
+            audio_stream (Union[int, str]): Audio stream of the transport stream,
+                either an index, a PID like "0x0111" or a language like "jpn".
+            lenient (bool): Whether to analyse a truncated or corrupted wav file up to the corruption
+                instead of raising, which info.corruption reports.
+                Damaged audio of a transport stream is filled with silence.
+        """
+        envelope = get_loudness_from_wav.wav2envelope(
+            wav_path, window_sec, hop_sec, kind, channel_mode, audio_stream, lenient
+            )
+        return FrameLoudness(envelope.values, envelope.frame_per_sec, envelope.info)
+
+class AudioStreamComparison:
+    """AudioStreamComparison
+    This class represents silent sections detected on each audio stream of a TV program
+    and where the streams disagree.
     Attributes:
-        composition (dict): Composition of the structure 
-            which includes at least a CM and possibly includes a program scene.
-            Ex.
-            {'cm': 60}
-            {'scene': 30, 'cm': 60}
-            {'cm': 60, 'scene': 30}
-            {'monolithic_cm': 120}
-        nominal_duration (Union[int, float]): Sum of durations in the structure.
+        streams (List[get_loudness_from_wav.AudioStream]): Audio streams compared.
+        silent_sections (List[List[SilentSection]]): Silent sections of each stream.
+        silent_ratios (List[float]): Ratio of silent frames of each stream,
+            near 1 for a stream silent for the whole program.
+        unmatched_sections (List[List[SilentSection]]): Sections of each stream
+            which some other stream has no section for within the tolerance.
     """
-    def __init__(self, cm_structure: Dict, margin_sec: float):
-        """Initialize a NominalCMStructure object.
+    def __init__(
+        self,
+        streams: List[get_loudness_from_wav.AudioStream],
+        silent_sections: List[List[SilentSection]],
+        silent_ratios: List[float],
+        tolerance_sec: float
+        ):
+        """Initialize an AudioStreamComparison object.
         Args:
-            cm_structure (Dict): Raw nominal CM structure.
+            streams (List[get_loudness_from_wav.AudioStream]): Audio streams compared.
+            silent_sections (List[List[SilentSection]]): Silent sections of each stream.
+            silent_ratios (List[float]): Ratio of silent frames of each stream.
+            tolerance_sec (float): Difference of start and end in sec within which sections agree.
         """
-        if type(cm_structure) is not dict:
-            raise TypeError(
-                f"Unexpected type for {self.__class__.__name__}: {type(cm_structure)}."
-                )
-        if len(cm_structure) > 2:
-            raise ValueError(
-                f"CM Structure length(must be less than 3) is too long: {len(cm_structure)}"
-                )
-        elif len(cm_structure) == 1 and "scene" in cm_structure:
-            raise ValueError(f"CM Structure must include 'cm' key.")
-        elif len(cm_structure) == 0:
-            raise ValueError("CM Structure is empty.")
-        self.nominal_duration = 0
-        for key in cm_structure:
-            if key != "cm" and key != "scene" and key != "monolithic_cm":
-                raise KeyError(f"Key must be either 'cm' or 'scene': {key}.")
-            if type(cm_structure[key]) is not int and type(cm_structure[key]) is not float:
-                raise TypeError(
-                    f"Type of value must be either integer or float: {type(cm_structure[key])}."
+        if tolerance_sec < 0:
+            raise ValueError(f"tolerance_sec must be non-negative: {tolerance_sec}.")
+        self.streams = streams
+        self.silent_sections = silent_sections
+        self.silent_ratios = silent_ratios
+        self.unmatched_sections = [
+            [
+                section for section in sections
+                if not all(
+                    any(
+                        abs(section.start_sec - other.start_sec) <= tolerance_sec
+                        and abs(section.end_sec - other.end_sec) <= tolerance_sec
+                        for other in other_sections
+                        )
+                    for other_index, other_sections in enumerate(silent_sections)
+                    if other_index != index
                     )
-            elif cm_structure[key] <= 0:
-                raise ValueError(f"Value must be positive value: {cm_structure[key]}.")
-            self.nominal_duration += cm_structure[key]
-        self.composition = cm_structure
-        if type(margin_sec) is not int and type(margin_sec) is not float: 
-            raise TypeError(f"Type of margin sec must be either integer or float: {type(margin_sec)}.")
-        if margin_sec <= 0:
-            raise ValueError(f"margin_sec must be positive value: {margin_sec}.")
-        self.margin_sec = margin_sec
-    def get_actual_cm_section(
-        self, 
-        last_cm_divider_end_sec: float, 
-        next_cm_divider_start_sec: float
-        ) -> Tuple[float]:
-        """Get actual CM section removing a TV program scene based on the structure.
-        Args:
-            last_cm_divider_end_sec (float): End timing of last cm divider. 
-            next_cm_divider_start_sec (float): Start timing of next cm divider.
-        """
-        if len(self.composition) == 1:
-            return last_cm_divider_end_sec, next_cm_divider_start_sec
-        counter = 0
-        for key in self.composition:
-            if key == "scene":
-                if counter == 0:
-                    return last_cm_divider_end_sec + self.composition[key], next_cm_divider_start_sec
-                else:
-                    return last_cm_divider_end_sec, next_cm_divider_start_sec - self.composition[key]
-            counter += 1
-
-class DurationSecUnits:
-    """DurationSecUnits
-    This class represents CM Duration units in sec.
+                ]
+            for index, sections in enumerate(silent_sections)
+            ]
+
+    def has_disagreement(self) -> bool:
+        """Whether a silent section is missing from some stream."""
+        return any(self.unmatched_sections)
+
+class BroadcastClock:
+    """BroadcastClock
+    This class converts times in the source wav file to wall-clock broadcast time
+    by the bext chunk of a Broadcast Wave Format file.
     Attributes:
-        durations_sec (List[Union[int, float]]): CM Duration units in sec.
+        start (datetime.datetime): Wall-clock time of the first frame.
     """
-    def __init__(self, durations_sec: List[Union[int, float]]):
-        """Initialize a DuratoinSecUnits object.
+    def __init__(self, start: datetime.datetime):
+        """Initialize a BroadcastClock object.
         Args:
-            durations_sec (List[Union[int, float]]): Duration units to distinguish CM.
+            start (datetime.datetime): Wall-clock time of the first frame.
         """
-        self.durations_sec = [15, 30]
-        if len(durations_sec) > 0:
-            for value in durations_sec:
-                if type(value) is not int and type(value) is not float:
-                    raise TypeError(
-                        f"Element of duration units must be integer or float: {type(value)}."
-                        )
-                if value <= 0:
-                    raise ValueError(f"CM durations must be positive value: {value}.")
-                if value not in self.durations_sec:
-                    self.durations_sec.append(value)
-            self.durations_sec = sorted(self.durations_sec)
-
-    def append_duration(self, duration_sec: Union[int, float]) -> DurationSecUnits:
-        """Append a duration to the units.
+        self.start = start
+
+    @classmethod
+    def from_info(cls, info: get_loudness_from_wav.WavInfo) -> Optional[BroadcastClock]:
+        """Get the clock of a wav file, None if it has no bext chunk with an origination date.
+        The time reference counts frames from midnight of the origination date.
+        A capture tool leaving it at 0 is taken to mean the origination time instead.
         Args:
-            duration_sec (Union[int, float]): Duration to be appended to the units.
+            info (get_loudness_from_wav.WavInfo): Header metadata of the source wav file.
         """
-        durations = self.durations_sec
-        durations.append(duration_sec)
-        durations_sorted = sorted(durations)
-        return DurationSecUnits(durations_sorted)
+        if info.bext is None:
+            return None
+        digits = "".join(c for c in info.bext.origination_date if c.isdigit())
+        try:
+            midnight = datetime.datetime.strptime(digits, "%Y%m%d")
+        except ValueError:
+            return None
+        offset_sec = info.bext.time_reference_sec
+        if info.bext.time_reference == 0:
+            time_digits = "".join(c for c in info.bext.origination_time if c.isdigit())
+            if len(time_digits) == 6:
+                hours, minutes, seconds = (int(time_digits[i:i + 2]) for i in (0, 2, 4))
+                offset_sec = hours * 3600 + minutes * 60 + seconds
+        return BroadcastClock(midnight + datetime.timedelta(seconds=offset_sec))
+
+    def at(self, sec: float) -> datetime.datetime:
+        """Wall-clock time of a timing in the wav file in sec."""
+        return self.start + datetime.timedelta(seconds=sec)
 
-    def remove_duration(self, duration_sec: Union[int, float]) -> DurationSecUnits:
-        """Remove a duration from the units.
+    @staticmethod
+    def unmatched_cue_points(
+        boundaries_sec: List[float],
+        cue_points: List[get_loudness_from_wav.CuePoint],
+        tolerance_sec: float = 0.5
+        ) -> List[get_loudness_from_wav.CuePoint]:
+        """Markers placed by an operator which no detected boundary is near.
         Args:
-            duration_sec (Union[int, float]): Duration to be removed from the units.
+            boundaries_sec (List[float]): Detected boundaries in sec.
+            cue_points (List[get_loudness_from_wav.CuePoint]): Markers of the wav file.
+            tolerance_sec (float): Difference in sec within which a boundary meets a marker.
         """
-        durations = self.durations_sec
-        if duration_sec in durations:
-            durations.remove(duration_sec)
-        return DurationSecUnits(durations)
+        if tolerance_sec < 0:
+            raise ValueError(f"tolerance_sec must be non-negative: {tolerance_sec}.")
+        return [
+            cue_point for cue_point in cue_points
+            if not any(abs(cue_point.position_sec - sec) <= tolerance_sec for sec in boundaries_sec)
+            ]
+
+# Nominal CM structure with the side of its program scene made explicit.
+NominalCMStructure = get_loudness_from_wav.NominalCMStructure
+
+# Property of a TV program read from a JSON file such as sample.json, defaults applied.
+ProgramProperty = get_loudness_from_wav.ProgramProperty
+
+
+# CM duration units in sec, immutable and always sorted.
+DurationSecUnits = get_loudness_from_wav.DurationSecUnits
+
 
 class ProgramScenes:
     """ProgramScenes
     This class defines TV program scenes using both start and end timing.
     Attributes:
         scene_sections (List[Tuple[float]]): List of start and end timing.
+        silences (Optional[get_loudness_from_wav.Silences]): Silences the scenes were divided by,
+            with the threshold chosen and the info of the file, when given by detect_program_scenes.
     """
     starting_range_sec = 5
     end_margin_sec = 1
 
     def __init__(self, scene_sections: List[Tuple[float]]):
         self.scene_sections = scene_sections
+        self.silences = None
     
     @classmethod
     def construct_program_scenes(
         cls, 
         loudness: FrameLoudness, 
-        duration_frame_threshold: int, 
-        frame_per_sec: float, 
+        duration_frame_threshold: Optional[int], 
+        frame_per_sec: Optional[float], 
         duration_sec_units: DurationSecUnits, 
         cm_structures: List[NominalCMStructure], 
         last_scene_duration: int,
-        has_monolithic_cm: bool
+        has_monolithic_cm: bool,
+        duration_threshold_sec: Optional[float] = None
         ) -> ProgramScenes:
         """Construct TV program scenes based on both its loudness timeseries and program property.
         The property consists of structures of CMs and duration of last scene.
         Args:
             loudness (FrameLoudness): Loudness timeseries of a TV program.
-            duration_frame_threshold (int): Duration threshold in frame number to distinguish silent section.
-            frame_per_sec (float): Factor to convert frame index to sec.
+            duration_frame_threshold (Optional[int]): Duration threshold in frame number to distinguish silent section,
+                None to give it in sec.
+            frame_per_sec (Optional[float]): Factor to convert frame index to sec.
+                None uses the rate read from the wav file.
+            duration_sec_units (DurationSecUnits): CM Duration units in sec.
+            cm_structures (List[NominalCMStructure]): List of nominal CM structure, 
+                which could consists both actual CM and indistinguishable program scene.
+            last_scene_duration (int): Duration of last scene in the program.
+            duration_threshold_sec (Optional[float]): Duration threshold in sec instead of frame number.
+        """
+        frame_per_sec = cls.resolve_frame_per_sec(loudness, frame_per_sec)
+        silent_sections = cls.extract_silent_sections(
+            loudness, duration_frame_threshold, frame_per_sec, duration_threshold_sec
+            )
+        return cls.construct_program_scenes_from_silent_sections(
+            silent_sections,
+            duration_sec_units,
+            cm_structures,
+            last_scene_duration,
+            has_monolithic_cm
+            )
+
+    @classmethod
+    def construct_program_scenes_from_silent_sections(
+        cls,
+        silent_sections: List[SilentSection],
+        duration_sec_units: DurationSecUnits,
+        cm_structures: List[NominalCMStructure],
+        last_scene_duration: int,
+        has_monolithic_cm: bool
+        ) -> ProgramScenes:
+        """Construct TV program scenes based on both its silent sections and program property.
+        Args:
+            silent_sections (List[SilentSection]): Silent sections which could divide program scenes and CMs
             duration_sec_units (DurationSecUnits): CM Duration units in sec.
             cm_structures (List[NominalCMStructure]): List of nominal CM structure, 
                 which could consists both actual CM and indistinguishable program scene.
             last_scene_duration (int): Duration of last scene in the program.
         """
-        silent_sections = cls.extract_silent_sections(loudness, duration_frame_threshold, frame_per_sec)
 #        print (f"SILENT_SECTIONS: {silent_sections}")
         cm_sections = cls.construct_cm_sections(
             silent_sections, 
@@ -282,19 +337,41 @@ class ProgramScenes:
     def construct_program_scenes_without_structure(
         cls, 
         loudness: FrameLoudness, 
-        duration_frame_threshold: int, 
-        frame_per_sec: float, 
+        duration_frame_threshold: Optional[int], 
+        frame_per_sec: Optional[float], 
         duration_sec_units: DurationSecUnits, 
+        duration_threshold_sec: Optional[float] = None
         ) -> ProgramScenes:
         """Construct TV program scenes based on its loudness.
         Countinuous series made of multi CM is searched and then is cut.
         Args:
             loudness (FrameLoudness): Loudness timeseries of a TV program.
-            duration_frame_threshold (int): Duration threshold in frame number to distinguish silent section.
-            frame_per_sec (float): Factor to convert frame index to sec.
+            duration_frame_threshold (Optional[int]): Duration threshold in frame number to distinguish silent section,
+                None to give it in sec.
+            frame_per_sec (Optional[float]): Factor to convert frame index to sec.
+                None uses the rate read from the wav file.
+            duration_sec_units (DurationSecUnits): CM Duration units in sec.
+            duration_threshold_sec (Optional[float]): Duration threshold in sec instead of frame number.
+        """
+        frame_per_sec = cls.resolve_frame_per_sec(loudness, frame_per_sec)
+        silent_sections = cls.extract_silent_sections(
+            loudness, duration_frame_threshold, frame_per_sec, duration_threshold_sec
+            )
+        return cls.construct_program_scenes_without_structure_from_silent_sections(
+            silent_sections, duration_sec_units
+            )
+
+    @classmethod
+    def construct_program_scenes_without_structure_from_silent_sections(
+        cls,
+        silent_sections: List[SilentSection],
+        duration_sec_units: DurationSecUnits,
+        ) -> ProgramScenes:
+        """Construct TV program scenes based on its silent sections.
+        Args:
+            silent_sections (List[SilentSection]): Silent sections which could divide program scenes and CMs
             duration_sec_units (DurationSecUnits): CM Duration units in sec.
         """
-        silent_sections = cls.extract_silent_sections(loudness, duration_frame_threshold, frame_per_sec)
         cm_sections = cls.search_cm_sections(silent_sections, duration_sec_units)
         first_start_sec_canditate = silent_sections[0].start_sec
         last_end_sec_canditate = silent_sections[-1].end_sec
@@ -307,16 +384,82 @@ class ProgramScenes:
                 )
                 )
 
+    @classmethod
+    def detect_program_scenes(
+        cls,
+        wav_path: AudioSource,
+        program_property: Optional[ProgramProperty] = None
+        ) -> ProgramScenes:
+        """Detect TV program scenes from a wav file end to end, without its loudness timeseries.
+        Args:
+            wav_path (AudioSource): A path of wav file, or of the MPEG-2 transport stream to decode audio from.
+            program_property (Optional[ProgramProperty]): Property whose silence settings and CM structures
+                the scenes follow, as cmcut_direct.py uses. None searches CMs without structures,
+                as cmcut_default.py does.
+        Scenes follow starting_range_sec and end_margin_sec of the class, as generate_scenes does.
+        The silences dividing them are kept as silences, so that the file is decoded only once.
+        """
+        scene_sections, silences = get_loudness_from_wav.detect_program_scenes(
+            wav_path, program_property, cls.starting_range_sec, cls.end_margin_sec
+            )
+        program_scenes = cls(scene_sections)
+        program_scenes.silences = silences
+        return program_scenes
+
+    @staticmethod
+    def resolve_frame_per_sec(loudness: FrameLoudness, frame_per_sec: Optional[float]) -> float:
+        """Resolve the factor to convert frame index to sec.
+        Args:
+            loudness (FrameLoudness): Loudness timeseries of a TV program.
+            frame_per_sec (Optional[float]): Explicit factor, which takes priority over the wav file.
+        """
+        if frame_per_sec is not None:
+            return frame_per_sec
+        if loudness.frame_per_sec is None:
+            raise ValueError("frame_per_sec is unknown for the loudness.")
+        return loudness.frame_per_sec
+
+    @staticmethod
+    def resolve_duration_frame_threshold(
+        duration_frame_threshold: Optional[int],
+        duration_threshold_sec: Optional[float],
+        frame_per_sec: float
+        ) -> int:
+        """Resolve the duration threshold in frame number from either of its forms.
+        Args:
+            duration_frame_threshold (Optional[int]): Duration threshold in frame number.
+            duration_threshold_sec (Optional[float]): Duration threshold in sec.
+            frame_per_sec (float): Factor to convert frame index to sec.
+        """
+        if (duration_frame_threshold is None) == (duration_threshold_sec is None):
+            raise ValueError(
+                "Give either duration_frame_threshold or duration_threshold_sec: "
+                f"{duration_frame_threshold} {duration_threshold_sec}."
+                )
+        if duration_threshold_sec is None:
+            return duration_frame_threshold
+        if duration_threshold_sec < 0:
+            raise ValueError(f"duration_threshold_sec must be non-negative: {duration_threshold_sec}.")
+        return round(duration_threshold_sec * frame_per_sec)
+
     @staticmethod
     def extract_silent_sections(
-        loudness: FrameLoudness, duration_frame_threshold: int, frame_per_sec: int
+        loudness: FrameLoudness,
+        duration_frame_threshold: Optional[int],
+        frame_per_sec: int,
+        duration_threshold_sec: Optional[float] = None
         ) -> List[SilentSection]:
         """Extract silent sections based on its loudness.
         Args:
             loudness (FrameLoudness): Loudness timeseries of a TV program.
-            duration_frame_threshold (int): Duration threshold in frame number to distinguish silent section.
+            duration_frame_threshold (Optional[int]): Duration threshold in frame number to distinguish silent section,
+                None to give it in sec.
             frame_per_sec (float): Factor to convert frame index to sec.
+            duration_threshold_sec (Optional[float]): Duration threshold in sec instead of frame number.
         """
+        duration_frame_threshold = ProgramScenes.resolve_duration_frame_threshold(
+            duration_frame_threshold, duration_threshold_sec, frame_per_sec
+            )
         silent_sections = []
         silent_duration = 0
         last_loudness = -1
@@ -334,6 +477,134 @@ class ProgramScenes:
             last_loudness = loudness
         return silent_sections
 
+    @staticmethod
+    def extract_silent_sections_from_wav(
+        wav_path: AudioSource,
+        duration_frame_threshold: Optional[int],
+        channel_mode: Union[str, int] = "all",
+        threshold_dbfs: Union[float, str, None] = None,
+        exit_threshold_dbfs: Optional[float] = None,
+        max_blip_frame: int = 0,
+        audio_stream: Union[int, str] = 0,
+        lenient: bool = False,
+        analysis_rate: Optional[int] = None,
+        duration_threshold_sec: Optional[float] = None,
+        max_blip_sec: Optional[float] = None
+        ) -> List[SilentSection]:
+        """Extract silent sections while streaming a wav file, without its loudness timeseries.
+        With the default thresholds the result is the same as extract_silent_sections
+        on the loudness of the file, where only digital zero is silent.
+        Args:
+            wav_path (AudioSource): A path of wav file, or of the MPEG-2 transport stream to decode audio from.
+                The file in memory, a readable binary file or pipe, or decoded samples are read as well.
+            duration_frame_threshold (Optional[int]): Duration threshold in frame number to distinguish silent section,
+                None to give it in sec.
+            channel_mode (Union[str, int]): How channels drive the loudness,
+                either "all", "mid", "side", "interleaved" or a channel index.
+            threshold_dbfs (Union[float, str, None]): Loudness in dBFS at or below which a silence starts,
+                or "auto" to choose it from the noise floor of the file.
+            exit_threshold_dbfs (Optional[float]): Loudness in dBFS above which a silence ends,
+                threshold_dbfs by default.
+            max_blip_frame (int): Number of louder frames in a row tolerated inside a silence.
+            audio_stream (Union[int, str]): Audio stream of the transport stream,
+                either an index, a PID like "0x0111" or a language like "jpn".
+            lenient (bool): Whether to detect on the part of a truncated or corrupted wav file
+                up to the corruption instead of raising.
+                Damaged audio of a transport stream is filled with silence.
+            analysis_rate (Optional[int]): Rate to decimate the samples to before detection,
+                so that frame numbers mean the same duration whatever the sample rate of the file.
+            duration_threshold_sec (Optional[float]): Duration threshold in sec instead of frame number.
+            max_blip_sec (Optional[float]): Duration of louder frames tolerated in sec instead of frame number.
+        """
+        if (duration_frame_threshold is None) == (duration_threshold_sec is None):
+            raise ValueError(
+                "Give either duration_frame_threshold or duration_threshold_sec: "
+                f"{duration_frame_threshold} {duration_threshold_sec}."
+                )
+        silences = get_loudness_from_wav.detect_silences(
+            wav_path,
+            threshold_dbfs=threshold_dbfs,
+            min_duration=duration_frame_threshold or 0,
+            channel_mode=channel_mode,
+            exit_threshold_dbfs=exit_threshold_dbfs,
+            max_blip=max_blip_frame,
+            audio_stream=audio_stream,
+            lenient=lenient,
+            analysis_rate=analysis_rate,
+            min_duration_sec=duration_threshold_sec,
+            max_blip_sec=max_blip_sec,
+            )
+        return [
+            SilentSection(start_frame_index, end_frame_index, silences.frame_per_sec)
+            for start_frame_index, end_frame_index in silences.sections
+            ]
+
+    @staticmethod
+    def compare_audio_streams(
+        ts_path: str,
+        duration_frame_threshold: Optional[int],
+        channel_mode: Union[str, int] = "all",
+        threshold_dbfs: Union[float, str, None] = None,
+        tolerance_sec: float = 0.5,
+        analysis_rate: Optional[int] = None,
+        duration_threshold_sec: Optional[float] = None
+        ) -> AudioStreamComparison:
+        """Detect silent sections on every audio stream of a transport stream and compare them.
+        Args:
+            ts_path (str): A path of MPEG-2 transport stream.
+            duration_frame_threshold (Optional[int]): Duration threshold in frame number to distinguish silent section,
+                None to give it in sec.
+            channel_mode (Union[str, int]): How channels drive the loudness,
+                either "all", "mid", "side", "interleaved" or a channel index.
+            threshold_dbfs (Union[float, str, None]): Loudness in dBFS at or below which a silence starts,
+                or "auto" to choose it per stream from its noise floor.
+            tolerance_sec (float): Difference of start and end in sec within which sections agree.
+            analysis_rate (Optional[int]): Rate to decimate every stream to before detection,
+                which lets streams of different sample rates share frame thresholds.
+            duration_threshold_sec (Optional[float]): Duration threshold in sec instead of frame number.
+        """
+        streams = get_loudness_from_wav.list_audio_streams(ts_path)
+        if not streams:
+            raise ValueError(f"Audio streams can only be compared in a transport stream: {ts_path}.")
+        silent_sections = []
+        silent_ratios = []
+        for stream in streams:
+            silences = get_loudness_from_wav.detect_silences(
+                ts_path,
+                threshold_dbfs=threshold_dbfs,
+                min_duration=duration_frame_threshold or 0,
+                channel_mode=channel_mode,
+                audio_stream=stream.index,
+                analysis_rate=analysis_rate,
+                min_duration_sec=duration_threshold_sec,
+                )
+            silent_sections.append([
+                SilentSection(start_frame_index, end_frame_index, silences.frame_per_sec)
+                for start_frame_index, end_frame_index in silences.sections
+                ])
+            silent_ratios.append(silences.silent_ratio)
+        return AudioStreamComparison(streams, silent_sections, silent_ratios, tolerance_sec)
+
+    def write_cue_points(self, wav_path: str, output_path: str) -> None:
+        """Copy the wav file with each program scene and each CM between them as a labelled region,
+        for editors to show the detection result on the waveform.
+        Cue points of the wav file are kept before them and its samples are copied untouched.
+        Args:
+            wav_path (str): A path of the wav file the scenes were detected in.
+            output_path (str): A path to write the copy to, replaced atomically.
+        """
+        info = get_loudness_from_wav.wav_info(wav_path)
+        sections = [
+            (min(round(start_sec * info.sample_rate), info.frames), min(round(end_sec * info.sample_rate), info.frames))
+            for start_sec, end_sec in self.scene_sections
+            ]
+        markers = []
+        for index, (start, end) in enumerate(sections):
+            if index > 0 and start > sections[index - 1][1]:
+                markers.append((sections[index - 1][1], "CM", start - sections[index - 1][1]))
+            markers.append((start, f"program {index}", end - start))
+        get_loudness_from_wav.write_cue_points(wav_path, output_path, markers)
+
     @staticmethod
     def construct_cm_sections(
         silent_sections: List[SilentSection], 
@@ -348,51 +619,12 @@ class ProgramScenes:
             duration_sec_units (DurationSecUnits): CM Duration units in sec.
             cm_structures (List[NominalCMStructure]): List of nominal CM structure, 
                 which could consists both actual CM and indistinguishable program scene.
+            has_monolithic_cm (bool): Whether a CM could have no silence inside.
+        Silent sections must be sorted, as extract_silent_sections gives them.
         """
-        if len(cm_structures) == 0:
-            return []
-        cm_num_threshold = 1
-        if has_monolithic_cm:
-            cm_num_threshold = 0
-
-        cm_divider_candidates = []
-        cm_sections = []
-        structure_reference = 0
-        target_cm_structure = cm_structures[structure_reference]
-        margin_sec = target_cm_structure.margin_sec
-        nominal_cm_duration = target_cm_structure.nominal_duration
-
-        for index, section in enumerate(silent_sections):
-#            print (section)
-            tmp_duration_sec_units = copy.deepcopy(duration_sec_units)
-            if "monolithic_cm" in target_cm_structure.composition:
-                tmp_duration_sec_units = duration_sec_units.append_duration(target_cm_structure.composition["monolithic_cm"])
-            if section.is_cm_divider_candidate(silent_sections[index+1:], tmp_duration_sec_units, margin_sec):
-#                print (f"CANDIDATE: {section}")
-                cm_divider_candidates.append(section)
-            candidates_could_be_cm = len(cm_divider_candidates) > cm_num_threshold
-            if len(target_cm_structure.composition) and "monolithic_cm" in target_cm_structure.composition:
-                candidates_could_be_cm = len(cm_divider_candidates) == 1
-            if candidates_could_be_cm:
-#                print (section, cm_divider_candidates)
-                combined_duration_sec = section.end_sec - cm_divider_candidates[0].start_sec
-#                print (f"COMB_DURA_SEC: {combined_duration_sec}")
-                if combined_duration_sec <= nominal_cm_duration:
-                    continue
-                if nominal_cm_duration < combined_duration_sec < nominal_cm_duration + margin_sec:
-                    actual_cm_section = target_cm_structure.get_actual_cm_section(
-                        cm_divider_candidates[0].end_sec, section.start_sec
-                        )
-                    cm_sections.append(actual_cm_section)
-                    structure_reference += 1
-                    if structure_reference < len(cm_structures):
-                        target_cm_structure = cm_structures[structure_reference]
-                        nominal_cm_duration = target_cm_structure.nominal_duration
-                    else:
-                        break
-                cm_divider_candidates = [cm_divider_candidates[-1]]
-        return cm_sections
-
+        return get_loudness_from_wav.construct_cm_sections(
+            silent_sections, duration_sec_units, cm_structures, has_monolithic_cm
+            )
 
     @classmethod
     def search_cm_sections(
@@ -405,23 +637,9 @@ class ProgramScenes:
         Args:
             silent_sections (List[SilentSection]): Silent sections which could divide program scenes and CMs
             duration_sec_units (DurationSecUnits): CM Duration units in sec.
+        Silent sections must be sorted, as extract_silent_sections gives them.
         """
-        margin_sec = 1
-        cm_divider_candidates = []
-        cm_sections = []
-        continuity = False
-        for index, section in enumerate(silent_sections):
-            if section.is_cm_divider_candidate(silent_sections[index+1:], duration_sec_units, margin_sec):
-                cm_divider_candidates.append(section)
-                continuity = True
-            else:
-                if continuity and len(cm_divider_candidates) >= 2:
-                    cm_sections.append((cm_divider_candidates[0].end_sec, cm_divider_candidates[-1].start_sec))
-                    cm_divider_candidates = []
-                    continuity = False
-        if len(cm_divider_candidates) >= 2:
-            cm_sections.append((cm_divider_candidates[0].end_sec, cm_divider_candidates[-1].start_sec))
-        return cm_sections
+        return get_loudness_from_wav.search_cm_sections(silent_sections, duration_sec_units)
 
     @classmethod
     def generate_scenes(
@@ -438,15 +656,11 @@ class ProgramScenes:
             cm_sections (List[Tuple[float]]): List of start-end timings of CMs. 
             last_scene_duration (int): The duration of last scene in the program.
         """
-        scenes = []
-        start_sec = 0
-        if first_start_sec_candidate < cls.starting_range_sec:
-            start_sec = first_start_sec_candidate
-        for section in cm_sections:
-            scenes.append((start_sec, section[0]))
-            start_sec = section[1]
-        if last_scene_duration != 0:
-            scenes.append((start_sec, start_sec + last_scene_duration + cls.end_margin_sec))
-        else:
-            scenes.append((start_sec, last_end_sec_candidate))
-        return scenes
\ No newline at end of file
+        return get_loudness_from_wav.generate_scenes(
+            first_start_sec_candidate,
+            last_end_sec_candidate,
+            cm_sections,
+            last_scene_duration,
+            cls.starting_range_sec,
+            cls.end_margin_sec,
+            )
\ No newline at end of file
@@ -0,0 +1,74 @@
+use std::ffi::c_void;
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+use pyo3::exceptions::PyBufferError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::{ffi, AsPyPointer};
+
+const FLOAT32_FORMAT: &[u8] = b"f\0";
+
+/// Read-only float32 buffer owning a Rust vector.
+/// NumPy arrays created from it keep it alive, so the samples are never copied.
+#[pyclass]
+pub struct LoudnessBuffer {
+    values: Vec<f32>,
+    shape: [ffi::Py_ssize_t; 1],
+}
+
+#[pymethods]
+impl LoudnessBuffer {
+    fn __len__(&self) -> usize {
+        self.values.len()
+    }
+
+    unsafe fn __getbuffer__(
+        mut slf: PyRefMut<'_, Self>,
+        view: *mut ffi::Py_buffer,
+        flags: c_int,
+    ) -> PyResult<()> {
+        if view.is_null() {
+            return Err(PyBufferError::new_err("View is null"));
+        }
+        if (flags & ffi::PyBUF_WRITABLE) == ffi::PyBUF_WRITABLE {
+            return Err(PyBufferError::new_err("Object is not writable"));
+        }
+        (*view).obj = ffi::_Py_NewRef(slf.as_ptr());
+        (*view).buf = slf.values.as_ptr() as *mut c_void;
+        (*view).len = (slf.values.len() * std::mem::size_of::<f32>()) as ffi::Py_ssize_t;
+        (*view).readonly = 1;
+        (*view).itemsize = std::mem::size_of::<f32>() as ffi::Py_ssize_t;
+        (*view).format = if (flags & ffi::PyBUF_FORMAT) == ffi::PyBUF_FORMAT {
+            FLOAT32_FORMAT.as_ptr() as *mut c_char
+        } else {
+            ptr::null_mut()
+        };
+        (*view).ndim = 1;
+        (*view).shape = if (flags & ffi::PyBUF_ND) == ffi::PyBUF_ND {
+            slf.shape.as_mut_ptr()
+        } else {
+            ptr::null_mut()
+        };
+        (*view).strides = if (flags & ffi::PyBUF_STRIDES) == ffi::PyBUF_STRIDES {
+            &mut (*view).itemsize
+        } else {
+            ptr::null_mut()
+        };
+        (*view).suboffsets = ptr::null_mut();
+        (*view).internal = ptr::null_mut();
+        Ok(())
+    }
+}
+
+/// Hand `values` to Python as a `numpy.ndarray[float32]` backed by the Rust allocation.
+pub fn into_ndarray(py: Python, values: Vec<f32>) -> PyResult<PyObject> {
+    let shape = [values.len() as ffi::Py_ssize_t];
+    let buffer = Py::new(py, LoudnessBuffer { values, shape })?;
+    let numpy = py.import("numpy")?;
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("dtype", numpy.getattr("float32")?)?;
+    Ok(numpy
+        .call_method("frombuffer", (buffer,), Some(kwargs))?
+        .into())
+}
@@ -0,0 +1,167 @@
+use std::fmt;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use serde::de::{self, Deserialize, Deserializer, Unexpected, Visitor};
+
+use crate::wav::Analyzer;
+
+/// How the channels of a frame are reduced to a single loudness value.
+///
+/// From Python it is given as one of:
+/// - `"all"`: largest magnitude among channels, silent only if all channels are silent.
+/// - `"mid"`: magnitude of the mean of all channels.
+/// - `"side"`: magnitude of half the difference of a stereo pair.
+/// - `"interleaved"`: magnitude of every sample, one value per channel per frame.
+/// - an `int`: magnitude of that channel only, e.g. to pick a language of dual-mono audio.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelMode {
+    All,
+    Mid,
+    Side,
+    Interleaved,
+    Channel(usize),
+}
+
+impl ChannelMode {
+    /// Check the mode can be applied to a recording with `channels` channels.
+    pub fn validate(&self, channels: usize) -> PyResult<()> {
+        match self {
+            ChannelMode::Side if channels != 2 => Err(PyValueError::new_err(format!(
+                "Side downmix needs 2 channels: {}.",
+                channels
+            ))),
+            ChannelMode::Channel(index) if *index >= channels => Err(PyValueError::new_err(
+                format!("Channel index out of range: {} of {}.", index, channels),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Magnitude of one frame of interleaved samples.
+    pub fn reduce(&self, frame: &[f32]) -> f32 {
+        match self {
+            ChannelMode::All => frame.iter().fold(0.0, |max, x| max.max(x.abs())),
+            ChannelMode::Mid => (frame.iter().sum::<f32>() / frame.len() as f32).abs(),
+            ChannelMode::Side => ((frame[0] - frame[1]) / 2.0).abs(),
+            ChannelMode::Channel(index) => frame[*index].abs(),
+            ChannelMode::Interleaved => unreachable!("interleaved samples are not reduced"),
+        }
+    }
+}
+
+impl ChannelMode {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "all" => Some(ChannelMode::All),
+            "mid" => Some(ChannelMode::Mid),
+            "side" => Some(ChannelMode::Side),
+            "interleaved" => Some(ChannelMode::Interleaved),
+            _ => None,
+        }
+    }
+}
+
+impl<'source> FromPyObject<'source> for ChannelMode {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        if let Ok(index) = ob.extract::<usize>() {
+            return Ok(ChannelMode::Channel(index));
+        }
+        let name = ob.extract::<&str>()?;
+        ChannelMode::from_name(name).ok_or_else(|| {
+            PyValueError::new_err(format!(
+                "Channel mode must be 'all', 'mid', 'side', 'interleaved' or a channel index: {}.",
+                name
+            ))
+        })
+    }
+}
+
+impl IntoPy<PyObject> for ChannelMode {
+    fn into_py(self, py: Python) -> PyObject {
+        match self {
+            ChannelMode::All => "all".into_py(py),
+            ChannelMode::Mid => "mid".into_py(py),
+            ChannelMode::Side => "side".into_py(py),
+            ChannelMode::Interleaved => "interleaved".into_py(py),
+            ChannelMode::Channel(index) => index.into_py(py),
+        }
+    }
+}
+
+/// Same forms as from Python, for program property files.
+impl<'de> Deserialize<'de> for ChannelMode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ModeVisitor;
+
+        impl Visitor<'_> for ModeVisitor {
+            type Value = ChannelMode;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("'all', 'mid', 'side', 'interleaved' or a channel index")
+            }
+
+            fn visit_u64<E: de::Error>(self, index: u64) -> Result<ChannelMode, E> {
+                Ok(ChannelMode::Channel(index as usize))
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<ChannelMode, E> {
+                ChannelMode::from_name(name)
+                    .ok_or_else(|| E::invalid_value(Unexpected::Str(name), &self))
+            }
+        }
+
+        deserializer.deserialize_any(ModeVisitor)
+    }
+}
+
+/// Collects one loudness value per frame, reducing channels with a `ChannelMode`.
+pub struct ChannelLoudness {
+    mode: ChannelMode,
+    pub values: Vec<f32>,
+}
+
+impl ChannelLoudness {
+    pub fn new(mode: ChannelMode) -> Self {
+        ChannelLoudness {
+            mode,
+            values: Vec::new(),
+        }
+    }
+}
+
+impl Analyzer for ChannelLoudness {
+    fn process(&mut self, samples: &[f32], channels: usize) {
+        match self.mode {
+            ChannelMode::Interleaved => self.values.extend(samples.iter().map(|x| x.abs())),
+            mode => self.values.extend(
+                samples
+                    .chunks_exact(channels)
+                    .map(|frame| mode.reduce(frame)),
+            ),
+        }
+    }
+}
+
+/// Collects the magnitude envelope of each channel separately.
+pub struct PerChannelLoudness {
+    pub values: Vec<Vec<f32>>,
+}
+
+impl PerChannelLoudness {
+    pub fn new(channels: usize) -> Self {
+        PerChannelLoudness {
+            values: vec![Vec::new(); channels],
+        }
+    }
+}
+
+impl Analyzer for PerChannelLoudness {
+    fn process(&mut self, samples: &[f32], channels: usize) {
+        for frame in samples.chunks_exact(channels) {
+            for (values, x) in self.values.iter_mut().zip(frame) {
+                values.push(x.abs());
+            }
+        }
+    }
+}
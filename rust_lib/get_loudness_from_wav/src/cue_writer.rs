@@ -0,0 +1,309 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::error::{ReadError, ReadErrorKind};
+use crate::info::WavInfo;
+use crate::metadata::CuePoint;
+use crate::wav::WavReader;
+
+/// Marker to write as a cue point, given from Python as `(position, label)` or
+/// `(position, label, length)` in frames. A length makes it a region.
+pub struct Marker {
+    pub position: u64,
+    pub label: String,
+    pub length: Option<u64>,
+}
+
+impl<'source> FromPyObject<'source> for Marker {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        let (position, label, length) = match ob.extract::<(u64, String)>() {
+            Ok((position, label)) => (position, label, None),
+            Err(_) => ob.extract::<(u64, String, Option<u64>)>()?,
+        };
+        Ok(Marker {
+            position,
+            label,
+            length,
+        })
+    }
+}
+
+/// Chunk of the source copied with its body, `size` bytes after its header at `offset`.
+struct CopiedChunk {
+    offset: u64,
+    id: [u8; 4],
+    size: u64,
+}
+
+/// Copy the WAV file at `path` to `output_path` with a `cue ` chunk and a `LIST` `adtl`
+/// chunk labelling the cue points of the source followed by `markers`.
+///
+/// Cue points of the source keep their IDs, labels, notes and lengths, and markers take
+/// the IDs after them. A marker the source already has, at the same position with the
+/// same label and length, is not repeated, so writing the same markers again changes
+/// nothing.
+///
+/// Every other chunk, the data chunk first of all, is copied byte for byte, and the new
+/// chunks go at the end of the file. A data chunk without a size, left by an interrupted
+/// recorder, runs to the end of the file: its whole frames are copied and their size is
+/// written to its header. Bytes after the last whole chunk, such as garbage left by a
+/// writer, are dropped. The copy is written to a temporary file next to
+/// `output_path` and renamed over it once complete, so `output_path` is never seen half
+/// written, even when it is `path` itself.
+pub fn write_cue_points(path: &str, output_path: &str, markers: &[Marker]) -> PyResult<()> {
+    let mut file = File::open(path).map_err(|e| ReadError::from_io(e, path, 0))?;
+    let len = file
+        .metadata()
+        .map_err(|e| ReadError::from_io(e, path, 0))?
+        .len();
+    let (frames, data_size, cue_points) = {
+        let mut reader = WavReader::new(BufReader::new(&mut file), path, Some(len), false)?;
+        if reader.frames_unknown() {
+            let kind = ReadErrorKind::Unsupported("data chunk of unknown size".into());
+            return Err(ReadError::new(kind, path, 0).into());
+        }
+        let data_size = reader
+            .data_runs_to_eof()
+            .then(|| reader.frames() * reader.format().block_align as u64);
+        reader.skip_data()?;
+        let mut info = WavInfo::new(reader.format(), reader.frames());
+        reader.metadata().fill_info(&mut info);
+        (reader.frames(), data_size, info.cue_points)
+    };
+    for marker in markers {
+        let end = marker.position + marker.length.unwrap_or(0);
+        if end > frames || end > u32::MAX as u64 {
+            return Err(PyValueError::new_err(format!(
+                "Marker out of the data: {} {} of {}.",
+                marker.position, marker.label, frames
+            )));
+        }
+    }
+    let chunks = list_copied_chunks(&mut file, path, len, data_size)?;
+    let new_chunks = cue_chunks(&merge_markers(cue_points, markers));
+    let riff_size = 4
+        + chunks
+            .iter()
+            .map(|chunk| 8 + padded(chunk.size))
+            .sum::<u64>()
+        + new_chunks.len() as u64;
+    if riff_size > u32::MAX as u64 {
+        let kind = ReadErrorKind::Unsupported("too large for a RIFF file with cue points".into());
+        return Err(ReadError::new(kind, path, 0).into());
+    }
+
+    let temp_path = temp_path(output_path);
+    let result = write_copy(
+        &mut file,
+        path,
+        &temp_path,
+        riff_size as u32,
+        &chunks,
+        &new_chunks,
+    )
+    .and_then(|_| fs::rename(&temp_path, output_path).map_err(|e| write_error(e, output_path)));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Walk the chunks of a RIFF/WAVE file, leaving out its cue points and their labels.
+/// `data_size` is the size of a data chunk running to the end of the file, which ends
+/// the walk.
+fn list_copied_chunks(
+    file: &mut File,
+    path: &str,
+    len: u64,
+    data_size: Option<u64>,
+) -> Result<Vec<CopiedChunk>, ReadError> {
+    let mut reader = BufReader::new(file);
+    let mut riff = [0u8; 12];
+    reader
+        .seek(SeekFrom::Start(0))
+        .and_then(|_| reader.read_exact(&mut riff))
+        .map_err(|e| ReadError::from_io(e, path, 0))?;
+    if &riff[0..4] != b"RIFF" {
+        let kind = ReadErrorKind::Unsupported("cue points are only written to RIFF files".into());
+        return Err(ReadError::new(kind, path, 0));
+    }
+    let mut chunks = Vec::new();
+    let mut offset = 12;
+    let mut header = [0u8; 12];
+    while offset + 8 <= len {
+        let peek = (len - offset).min(12) as usize;
+        reader
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| reader.read_exact(&mut header[..peek]))
+            .map_err(|e| ReadError::from_io(e, path, offset))?;
+        let id = [header[0], header[1], header[2], header[3]];
+        if let (b"data", Some(size)) = (&id, data_size) {
+            chunks.push(CopiedChunk { offset, id, size });
+            break;
+        }
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as u64;
+        if offset + 8 + size > len {
+            if &id == b"data" {
+                return Err(ReadError::new(ReadErrorKind::Truncated, path, len));
+            }
+            break;
+        }
+        let is_cue = &id == b"cue " || (&id == b"LIST" && peek == 12 && &header[8..12] == b"adtl");
+        if !is_cue {
+            chunks.push(CopiedChunk { offset, id, size });
+        }
+        offset += 8 + padded(size);
+    }
+    Ok(chunks)
+}
+
+fn write_copy(
+    file: &mut File,
+    path: &str,
+    temp_path: &Path,
+    riff_size: u32,
+    chunks: &[CopiedChunk],
+    new_chunks: &[u8],
+) -> PyResult<()> {
+    let temp_name = temp_path.to_string_lossy();
+    // A temp file of the same name is left by a crashed run which had the same PID, as
+    // this process writes one copy at a time.
+    match fs::remove_file(temp_path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(write_error(e, &temp_name)),
+        _ => {}
+    }
+    let output = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(temp_path)
+        .map_err(|e| write_error(e, &temp_name))?;
+    let mut writer = BufWriter::new(output);
+    let mut riff = b"RIFF".to_vec();
+    riff.extend_from_slice(&riff_size.to_le_bytes());
+    riff.extend_from_slice(b"WAVE");
+    writer
+        .write_all(&riff)
+        .map_err(|e| write_error(e, &temp_name))?;
+    for chunk in chunks {
+        // The header is written from the size copied, and the pad byte rather than copied,
+        // as the last chunk may lack it.
+        let mut header = chunk.id.to_vec();
+        header.extend_from_slice(&(chunk.size as u32).to_le_bytes());
+        writer
+            .write_all(&header)
+            .map_err(|e| write_error(e, &temp_name))?;
+        let body_offset = chunk.offset + 8;
+        file.seek(SeekFrom::Start(body_offset))
+            .map_err(|e| ReadError::from_io(e, path, body_offset))?;
+        let mut body = (&mut *file).take(chunk.size);
+        let copied = io::copy(&mut body, &mut writer).map_err(|e| write_error(e, &temp_name))?;
+        if copied < chunk.size {
+            return Err(
+                ReadError::new(ReadErrorKind::Truncated, path, body_offset + copied).into(),
+            );
+        }
+        if chunk.size & 1 == 1 {
+            writer
+                .write_all(&[0])
+                .map_err(|e| write_error(e, &temp_name))?;
+        }
+    }
+    writer
+        .write_all(new_chunks)
+        .and_then(|_| writer.into_inner().map_err(|e| e.into_error()))
+        .and_then(|output| output.sync_all())
+        .map_err(|e| write_error(e, &temp_name))
+}
+
+/// Cue points of the source followed by the markers it lacks, numbered from the ID after
+/// the largest of the source.
+fn merge_markers(mut cue_points: Vec<CuePoint>, markers: &[Marker]) -> Vec<CuePoint> {
+    let mut next_id = cue_points.iter().map(|cue| cue.id).max().unwrap_or(0) + 1;
+    for marker in markers {
+        let known = cue_points.iter().any(|cue| {
+            cue.position == marker.position
+                && cue.label.as_deref() == Some(marker.label.as_str())
+                && cue.length == marker.length
+        });
+        if !known {
+            cue_points.push(CuePoint {
+                id: next_id,
+                position: marker.position,
+                position_sec: 0.0,
+                label: Some(marker.label.clone()),
+                note: None,
+                length: marker.length,
+            });
+            next_id += 1;
+        }
+    }
+    cue_points
+}
+
+/// `cue ` chunk of the cue points followed by a `LIST` `adtl` chunk of their labels, notes
+/// and the lengths of regions.
+fn cue_chunks(cue_points: &[CuePoint]) -> Vec<u8> {
+    let mut cue = (cue_points.len() as u32).to_le_bytes().to_vec();
+    let mut adtl = b"adtl".to_vec();
+    for cue_point in cue_points {
+        let id = cue_point.id;
+        let position = (cue_point.position as u32).to_le_bytes();
+        cue.extend_from_slice(&id.to_le_bytes());
+        cue.extend_from_slice(&position);
+        cue.extend_from_slice(b"data");
+        cue.extend_from_slice(&[0; 8]);
+        cue.extend_from_slice(&position);
+        for (chunk_id, text) in [(b"labl", &cue_point.label), (b"note", &cue_point.note)] {
+            if let Some(text) = text {
+                let mut body = id.to_le_bytes().to_vec();
+                body.extend_from_slice(text.as_bytes());
+                body.push(0);
+                push_chunk(&mut adtl, chunk_id, &body);
+            }
+        }
+        if let Some(length) = cue_point.length {
+            let mut ltxt = id.to_le_bytes().to_vec();
+            ltxt.extend_from_slice(&(length as u32).to_le_bytes());
+            // Purpose, then country, language, dialect and code page left unspecified.
+            ltxt.extend_from_slice(b"rgn ");
+            ltxt.extend_from_slice(&[0; 8]);
+            push_chunk(&mut adtl, b"ltxt", &ltxt);
+        }
+    }
+    let mut chunks = Vec::new();
+    push_chunk(&mut chunks, b"cue ", &cue);
+    push_chunk(&mut chunks, b"LIST", &adtl);
+    chunks
+}
+
+fn push_chunk(out: &mut Vec<u8>, id: &[u8; 4], body: &[u8]) {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    out.extend_from_slice(body);
+    if body.len() & 1 == 1 {
+        out.push(0);
+    }
+}
+
+fn padded(size: u64) -> u64 {
+    size + (size & 1)
+}
+
+/// Hidden file in the directory of `output_path`, so that renaming it stays on one file system.
+fn temp_path(output_path: &str) -> std::path::PathBuf {
+    let output = Path::new(output_path);
+    let name = output
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    output.with_file_name(format!(".{}.{}.tmp", name, std::process::id()))
+}
+
+/// OSError of the matching subclass, naming the file being written.
+fn write_error(error: io::Error, path: &str) -> PyErr {
+    io::Error::new(error.kind(), format!("Failed to write {}: {}", path, error)).into()
+}
@@ -0,0 +1,167 @@
+use pyo3::buffer::{Element, PyBuffer};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::error::ReadError;
+use crate::wav::{SampleFormat, WavFormat, BLOCK_FRAMES};
+
+/// Channels beyond which samples are rather taken for an array of shape `(channels, frames)`.
+const MAX_CHANNELS: usize = 64;
+
+/// Samples already decoded in Python, to be analysed wherever a WAV file is.
+///
+/// `samples` is a buffer of `float32`, `float64`, `int16` or `int32`, such as a NumPy array,
+/// or a list of floats. It is either 1-D, holding interleaved frames of `channels` channels,
+/// or 2-D of shape `(frames, channels)`, with at most 64 channels. Integers are scaled so
+/// that full scale is 1.0. The samples are copied once on construction.
+#[pyclass]
+pub struct DecodedAudio {
+    samples: Vec<f32>,
+    format: WavFormat,
+}
+
+#[pymethods]
+impl DecodedAudio {
+    #[new]
+    #[args(channels = "None")]
+    fn new(py: Python, samples: &PyAny, sample_rate: u32, channels: Option<u16>) -> PyResult<Self> {
+        if sample_rate == 0 {
+            return Err(PyValueError::new_err("sample_rate must be positive: 0."));
+        }
+        let (samples, shape, sample_format, bits) = if let Ok(buffer) = PyBuffer::get(samples) {
+            (
+                buffer.to_vec(py)?,
+                buffer.shape().to_vec(),
+                SampleFormat::Float,
+                32,
+            )
+        } else if let Ok(buffer) = PyBuffer::<f64>::get(samples) {
+            let samples = buffer.to_vec(py)?.into_iter().map(|x| x as f32).collect();
+            (samples, buffer.shape().to_vec(), SampleFormat::Float, 64)
+        } else if let Ok(buffer) = PyBuffer::<i16>::get(samples) {
+            (
+                scale(py, &buffer, 16)?,
+                buffer.shape().to_vec(),
+                SampleFormat::Int,
+                16,
+            )
+        } else if let Ok(buffer) = PyBuffer::<i32>::get(samples) {
+            (
+                scale(py, &buffer, 32)?,
+                buffer.shape().to_vec(),
+                SampleFormat::Int,
+                32,
+            )
+        } else {
+            let samples: Vec<f32> = samples.extract()?;
+            let shape = vec![samples.len()];
+            (samples, shape, SampleFormat::Float, 32)
+        };
+        let channels = match (shape.as_slice(), channels) {
+            ([_], channels) => channels.map_or(1, usize::from),
+            ([_, columns], None) => *columns,
+            ([_, columns], Some(channels)) if *columns == channels as usize => *columns,
+            (shape, _) => {
+                return Err(PyValueError::new_err(format!(
+                    "Samples must be 1-D or of shape (frames, channels): {:?}.",
+                    shape
+                )))
+            }
+        };
+        let channels = u16::try_from(channels)
+            .ok()
+            .filter(|channels| *channels as usize <= MAX_CHANNELS)
+            .ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "Samples must have at most {} channels: {}. Transpose samples of shape \
+                     (channels, frames) to (frames, channels).",
+                    MAX_CHANNELS, channels
+                ))
+            })?;
+        if channels == 0 || samples.len() % channels as usize != 0 {
+            return Err(PyValueError::new_err(format!(
+                "Samples must hold whole frames of {} channels: {}.",
+                channels,
+                samples.len()
+            )));
+        }
+        let block_align = channels
+            .checked_mul(bits)
+            .map(|bits| bits / 8)
+            .ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "Frames of {} channels of {} bits are too large.",
+                    channels, bits
+                ))
+            })?;
+        Ok(DecodedAudio {
+            samples,
+            format: WavFormat {
+                sample_format,
+                channels,
+                sample_rate,
+                bits_per_sample: bits,
+                valid_bits_per_sample: bits,
+                channel_mask: 0,
+                block_align,
+            },
+        })
+    }
+
+    #[getter]
+    fn sample_rate(&self) -> u32 {
+        self.format.sample_rate
+    }
+
+    #[getter]
+    fn channels(&self) -> u16 {
+        self.format.channels
+    }
+
+    #[getter]
+    fn frames(&self) -> usize {
+        self.samples.len() / self.format.channels as usize
+    }
+}
+
+fn scale<T: Element + Into<f64>>(
+    py: Python,
+    buffer: &PyBuffer<T>,
+    bits: i32,
+) -> PyResult<Vec<f32>> {
+    let full_scale = 2f64.powi(bits - 1);
+    Ok(buffer
+        .to_vec(py)?
+        .into_iter()
+        .map(|x| (x.into() / full_scale) as f32)
+        .collect())
+}
+
+/// Hands out the samples of a `DecodedAudio` block by block as `WavReader::read_block` does.
+pub struct DecodedReader<'a> {
+    audio: &'a DecodedAudio,
+    position: usize,
+}
+
+impl<'a> DecodedReader<'a> {
+    pub fn new(audio: &'a DecodedAudio) -> Self {
+        DecodedReader { audio, position: 0 }
+    }
+
+    pub fn format(&self) -> &WavFormat {
+        &self.audio.format
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.audio.frames() as u64
+    }
+
+    pub fn read_block(&mut self, out: &mut Vec<f32>) -> Result<usize, ReadError> {
+        let channels = self.audio.format.channels as usize;
+        let end = (self.position + BLOCK_FRAMES * channels).min(self.audio.samples.len());
+        out.clear();
+        out.extend_from_slice(&self.audio.samples[self.position..end]);
+        self.position = end;
+        Ok(out.len() / channels)
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use pyo3::basic::CompareOp;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyFloat, PyLong};
+use pyo3::PyTypeInfo;
+
+/// Durations of CM in sec that every set of units holds.
+const DEFAULT_DURATIONS_SEC: [f64; 2] = [15.0, 30.0];
+
+/// CM duration units in sec, sorted and without duplicates.
+///
+/// Units never change once built: `append_duration` and `remove_duration` return new units,
+/// so units shared by callers, such as those of each silent section matched against the CM
+/// structures, stay as they were.
+#[pyclass(module = "get_loudness_from_wav")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DurationSecUnits {
+    durations_sec: Vec<f64>,
+}
+
+impl DurationSecUnits {
+    /// Units of `durations_sec` besides the 15 and 30 sec defaults.
+    pub fn with_defaults(durations_sec: &[f64]) -> Self {
+        DurationSecUnits::from_durations(DEFAULT_DURATIONS_SEC.iter().chain(durations_sec).copied())
+    }
+
+    fn from_durations(durations_sec: impl IntoIterator<Item = f64>) -> Self {
+        let mut durations_sec: Vec<f64> = durations_sec.into_iter().collect();
+        durations_sec.sort_by(f64::total_cmp);
+        durations_sec.dedup();
+        DurationSecUnits { durations_sec }
+    }
+
+    pub fn durations_sec(&self) -> &[f64] {
+        &self.durations_sec
+    }
+
+    /// Units with `duration_sec` as well.
+    pub fn with_duration(&self, duration_sec: f64) -> Self {
+        DurationSecUnits::from_durations(self.durations_sec.iter().copied().chain([duration_sec]))
+    }
+
+    /// Units without `duration_sec`, the same units if it is not one of them or is one of
+    /// the defaults, which every set of units keeps.
+    pub fn without_duration(&self, duration_sec: f64) -> Self {
+        if DEFAULT_DURATIONS_SEC.contains(&duration_sec) {
+            return self.clone();
+        }
+        DurationSecUnits {
+            durations_sec: self
+                .durations_sec
+                .iter()
+                .copied()
+                .filter(|unit| *unit != duration_sec)
+                .collect(),
+        }
+    }
+}
+
+/// Duration given from Python, an `int` or a `float` itself as in `DurationSecUnits`
+/// of lib/cmcut.py before it.
+fn duration_sec(ob: &PyAny) -> PyResult<f64> {
+    let py = ob.py();
+    let ty = ob.get_type();
+    if !ty.is(PyLong::type_object(py)) && !ty.is(PyFloat::type_object(py)) {
+        return Err(PyTypeError::new_err(format!(
+            "Element of duration units must be integer or float: {}.",
+            ty
+        )));
+    }
+    let value: f64 = ob.extract()?;
+    if value.is_nan() || value <= 0.0 {
+        return Err(PyValueError::new_err(format!(
+            "CM durations must be positive value: {}.",
+            ob
+        )));
+    }
+    Ok(value)
+}
+
+#[pymethods]
+impl DurationSecUnits {
+    #[new]
+    fn py_new(durations_sec: Vec<&PyAny>) -> PyResult<Self> {
+        let durations_sec = durations_sec
+            .into_iter()
+            .map(duration_sec)
+            .collect::<PyResult<Vec<f64>>>()?;
+        Ok(DurationSecUnits::with_defaults(&durations_sec))
+    }
+
+    /// Duration units in sec in ascending order, as a new list.
+    #[getter(durations_sec)]
+    fn py_durations_sec(&self) -> Vec<f64> {
+        self.durations_sec.clone()
+    }
+
+    /// New units with `duration_sec` appended.
+    fn append_duration(&self, duration_sec: &PyAny) -> PyResult<Self> {
+        Ok(self.with_duration(self::duration_sec(duration_sec)?))
+    }
+
+    /// New units with `duration_sec` removed, unless it is one of the 15 and 30 sec defaults.
+    fn remove_duration(&self, duration_sec: f64) -> Self {
+        self.without_duration(duration_sec)
+    }
+
+    fn __contains__(&self, duration_sec: f64) -> bool {
+        self.durations_sec.contains(&duration_sec)
+    }
+
+    fn __len__(&self) -> usize {
+        self.durations_sec.len()
+    }
+
+    fn __richcmp__(&self, other: &PyAny, op: CompareOp) -> PyObject {
+        let py = other.py();
+        match (other.extract::<PyRef<DurationSecUnits>>(), op) {
+            (Ok(other), CompareOp::Eq) => (*self == *other).into_py(py),
+            (Ok(other), CompareOp::Ne) => (*self != *other).into_py(py),
+            _ => py.NotImplemented(),
+        }
+    }
+
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for unit in &self.durations_sec {
+            unit.to_bits().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Pickled as the constructor call, which the units always hold the defaults of.
+    fn __reduce__(slf: &PyCell<Self>) -> (PyObject, (Vec<f64>,)) {
+        (slf.get_type().into(), (slf.borrow().durations_sec.clone(),))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("DurationSecUnits({:?})", self.durations_sec)
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::VecDeque;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::channels::ChannelMode;
+use crate::info::WavInfo;
+use crate::wav::Analyzer;
+
+/// Statistic taken over each window of frame magnitudes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnvelopeKind {
+    Rms,
+    Peak,
+    MeanAbs,
+}
+
+impl EnvelopeKind {
+    fn measure<'a, I: ExactSizeIterator<Item = &'a f32>>(&self, window: I) -> f32 {
+        let len = window.len() as f32;
+        match self {
+            EnvelopeKind::Rms => (window.map(|x| x * x).sum::<f32>() / len).sqrt(),
+            EnvelopeKind::Peak => window.fold(0.0, |max, x| max.max(*x)),
+            EnvelopeKind::MeanAbs => window.sum::<f32>() / len,
+        }
+    }
+}
+
+impl<'source> FromPyObject<'source> for EnvelopeKind {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        match ob.extract::<&str>()? {
+            "rms" => Ok(EnvelopeKind::Rms),
+            "peak" => Ok(EnvelopeKind::Peak),
+            "mean_abs" => Ok(EnvelopeKind::MeanAbs),
+            other => Err(PyValueError::new_err(format!(
+                "Envelope kind must be 'rms', 'peak' or 'mean_abs': {}.",
+                other
+            ))),
+        }
+    }
+}
+
+/// Downsampled loudness envelope of a WAV file.
+/// `values[i]` covers frames `[i * hop_frames, i * hop_frames + window_frames)`.
+#[pyclass]
+pub struct Envelope {
+    #[pyo3(get)]
+    pub values: PyObject,
+    /// Factor to convert an envelope index to sec.
+    #[pyo3(get)]
+    pub frame_per_sec: f64,
+    #[pyo3(get)]
+    pub window_frames: usize,
+    #[pyo3(get)]
+    pub hop_frames: usize,
+    #[pyo3(get)]
+    pub info: WavInfo,
+}
+
+/// Convert a duration in sec to a positive number of frames.
+pub fn sec_to_frames(sec: f64, sample_rate: u32, name: &str) -> PyResult<usize> {
+    let frames = (sec * sample_rate as f64).round();
+    if !frames.is_finite() || frames < 1.0 {
+        return Err(PyValueError::new_err(format!(
+            "{} must be at least one frame: {}.",
+            name, sec
+        )));
+    }
+    Ok(frames as usize)
+}
+
+/// Streams frame magnitudes through a sliding window, keeping at most one window in memory.
+pub struct EnvelopeAnalyzer {
+    kind: EnvelopeKind,
+    mode: ChannelMode,
+    window: usize,
+    hop: usize,
+    /// Magnitudes of frames from `pending_start` onwards.
+    pending: VecDeque<f32>,
+    pending_start: u64,
+    next_start: u64,
+    pub values: Vec<f32>,
+}
+
+impl EnvelopeAnalyzer {
+    pub fn new(kind: EnvelopeKind, mode: ChannelMode, window: usize, hop: usize) -> Self {
+        EnvelopeAnalyzer {
+            kind,
+            mode,
+            window,
+            hop,
+            pending: VecDeque::with_capacity(window),
+            pending_start: 0,
+            next_start: 0,
+            values: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, magnitude: f32) {
+        let frame = self.pending_start + self.pending.len() as u64;
+        if frame < self.next_start {
+            // Gap between windows when the hop is longer than the window.
+            self.pending_start += 1;
+            return;
+        }
+        self.pending.push_back(magnitude);
+        if self.pending.len() == self.window {
+            self.emit(self.window);
+        }
+    }
+
+    /// Emit the window starting at `next_start` from its first `len` frames and slide by a hop.
+    fn emit(&mut self, len: usize) {
+        self.values
+            .push(self.kind.measure(self.pending.iter().take(len)));
+        self.next_start += self.hop as u64;
+        let drop = self.hop.min(self.pending.len());
+        self.pending.drain(..drop);
+        self.pending_start += drop as u64;
+    }
+}
+
+impl Analyzer for EnvelopeAnalyzer {
+    fn process(&mut self, samples: &[f32], channels: usize) {
+        for frame in samples.chunks_exact(channels) {
+            self.push(self.mode.reduce(frame));
+        }
+    }
+
+    /// Emit the trailing windows which are cut short by the end of the data.
+    fn finish(&mut self) {
+        while !self.pending.is_empty() {
+            self.emit(self.pending.len());
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::fmt;
+use std::io;
+
+use pyo3::exceptions::PyOSError;
+use pyo3::prelude::*;
+use pyo3::{create_exception, PyErr};
+
+create_exception!(
+    get_loudness_from_wav,
+    WavError,
+    PyOSError,
+    "Failure to read a WAV file."
+);
+create_exception!(
+    get_loudness_from_wav,
+    WavNotFoundError,
+    WavError,
+    "The WAV file does not exist."
+);
+create_exception!(
+    get_loudness_from_wav,
+    WavPermissionError,
+    WavError,
+    "The WAV file cannot be opened."
+);
+create_exception!(
+    get_loudness_from_wav,
+    MalformedWavError,
+    WavError,
+    "The RIFF/WAVE structure is broken."
+);
+create_exception!(
+    get_loudness_from_wav,
+    UnsupportedWavFormatError,
+    WavError,
+    "The sample layout is not supported."
+);
+create_exception!(
+    get_loudness_from_wav,
+    TruncatedWavError,
+    WavError,
+    "The file ends before the declared data."
+);
+create_exception!(
+    get_loudness_from_wav,
+    AudioStreamNotFoundError,
+    WavError,
+    "No audio stream of the file matches the selection."
+);
+
+/// Reason a WAV file could not be read.
+#[derive(Debug)]
+pub enum ReadErrorKind {
+    NotFound,
+    PermissionDenied,
+    Malformed(String),
+    Unsupported(String),
+    Truncated,
+    StreamNotFound(String),
+    Io(io::Error),
+}
+
+/// Error raised while reading a WAV file, with the path and the byte offset where it happened.
+#[derive(Debug)]
+pub struct ReadError {
+    pub kind: ReadErrorKind,
+    pub path: String,
+    pub offset: u64,
+}
+
+impl ReadError {
+    pub fn new(kind: ReadErrorKind, path: &str, offset: u64) -> Self {
+        ReadError {
+            kind,
+            path: path.to_string(),
+            offset,
+        }
+    }
+
+    /// Classify an I/O error raised at `offset`.
+    pub fn from_io(error: io::Error, path: &str, offset: u64) -> Self {
+        let kind = match error.kind() {
+            io::ErrorKind::NotFound => ReadErrorKind::NotFound,
+            io::ErrorKind::PermissionDenied => ReadErrorKind::PermissionDenied,
+            io::ErrorKind::UnexpectedEof => ReadErrorKind::Truncated,
+            _ => ReadErrorKind::Io(error),
+        };
+        ReadError::new(kind, path, offset)
+    }
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ReadErrorKind::NotFound => write!(f, "WAV file not found: {}", self.path),
+            ReadErrorKind::PermissionDenied => write!(f, "Permission denied: {}", self.path),
+            ReadErrorKind::Malformed(reason) => {
+                write!(
+                    f,
+                    "Malformed WAV at byte {} of {}: {}",
+                    self.offset, self.path, reason
+                )
+            }
+            ReadErrorKind::Unsupported(reason) => {
+                write!(
+                    f,
+                    "Unsupported WAV format at byte {} of {}: {}",
+                    self.offset, self.path, reason
+                )
+            }
+            ReadErrorKind::Truncated => {
+                write!(
+                    f,
+                    "WAV file is truncated at byte {}: {}",
+                    self.offset, self.path
+                )
+            }
+            ReadErrorKind::StreamNotFound(reason) => {
+                write!(f, "Audio stream not found in {}: {}", self.path, reason)
+            }
+            ReadErrorKind::Io(error) => {
+                write!(
+                    f,
+                    "Failed to read byte {} of {}: {}",
+                    self.offset, self.path, error
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReadError {}
+
+impl From<ReadError> for PyErr {
+    fn from(error: ReadError) -> PyErr {
+        let message = error.to_string();
+        let err = match error.kind {
+            ReadErrorKind::NotFound => WavNotFoundError::new_err(message),
+            ReadErrorKind::PermissionDenied => WavPermissionError::new_err(message),
+            ReadErrorKind::Malformed(_) => MalformedWavError::new_err(message),
+            ReadErrorKind::Unsupported(_) => UnsupportedWavFormatError::new_err(message),
+            ReadErrorKind::Truncated => TruncatedWavError::new_err(message),
+            ReadErrorKind::StreamNotFound(_) => AudioStreamNotFoundError::new_err(message),
+            ReadErrorKind::Io(_) => WavError::new_err(message),
+        };
+        Python::with_gil(|py| {
+            let value = err.value(py);
+            value
+                .setattr("path", error.path)
+                .and_then(|_| value.setattr("offset", error.offset))
+                .map_or_else(|e| e, |_| err)
+        })
+    }
+}
+
+/// Register the exception classes in the module.
+pub fn register(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add("WavError", py.get_type::<WavError>())?;
+    m.add("WavNotFoundError", py.get_type::<WavNotFoundError>())?;
+    m.add("WavPermissionError", py.get_type::<WavPermissionError>())?;
+    m.add("MalformedWavError", py.get_type::<MalformedWavError>())?;
+    m.add(
+        "UnsupportedWavFormatError",
+        py.get_type::<UnsupportedWavFormatError>(),
+    )?;
+    m.add("TruncatedWavError", py.get_type::<TruncatedWavError>())?;
+    m.add(
+        "AudioStreamNotFoundError",
+        py.get_type::<AudioStreamNotFoundError>(),
+    )?;
+    Ok(())
+}
@@ -0,0 +1,499 @@
+use symphonia_bundle_mp3::MpaDecoder;
+use symphonia_codec_aac::AacDecoder;
+use symphonia_core::audio::{Channels, SampleBuffer};
+use symphonia_core::codecs::{
+    CodecParameters, Decoder, DecoderOptions, CODEC_TYPE_AAC, CODEC_TYPE_MP1, CODEC_TYPE_MP2,
+    CODEC_TYPE_MP3,
+};
+use symphonia_core::errors::Error as SymphoniaError;
+use symphonia_core::formats::Packet;
+
+use crate::error::ReadErrorKind;
+
+/// MPEG-1 sample rates, halved for MPEG-2 and quartered for MPEG-2.5.
+const MPEG_SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+/// Bit rates in kbps by bit rate index, for MPEG-1 layers I, II and III
+/// and for MPEG-2 layer I and layers II and III.
+const MPEG_BIT_RATES: [[u32; 15]; 5] = [
+    [
+        0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+    ],
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+    ],
+    [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ],
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+    ],
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+];
+/// Gain of a channel folded into one side of a stereo downmix, as ITU-R BS.775 does.
+const DOWNMIX_GAIN: f32 = std::f32::consts::FRAC_1_SQRT_2;
+const AAC_SAMPLE_RATES: [u32; 13] = [
+    96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+];
+
+/// Audio coding of an elementary stream, by its PMT stream type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioCodec {
+    /// MPEG-1/2 audio layers I to III.
+    Mpeg,
+    /// AAC in ADTS frames.
+    Adts,
+    /// AAC in LATM frames of a LOAS stream.
+    Latm,
+}
+
+impl AudioCodec {
+    pub fn from_stream_type(stream_type: u8) -> Option<Self> {
+        match stream_type {
+            0x03 | 0x04 => Some(AudioCodec::Mpeg),
+            0x0F => Some(AudioCodec::Adts),
+            0x11 => Some(AudioCodec::Latm),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AudioCodec::Mpeg => "mpeg",
+            AudioCodec::Adts => "adts",
+            AudioCodec::Latm => "latm",
+        }
+    }
+}
+
+/// What a decoder has to be set up with for a frame, a new one being needed when it changes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DecoderConfig {
+    /// AudioSpecificConfig of AAC.
+    Aac([u8; 2]),
+    /// MPEG audio layer, sample rate and whether it is single channel.
+    Mpeg {
+        layer: u8,
+        sample_rate: u32,
+        mono: bool,
+    },
+}
+
+/// Outcome of looking for a frame at the start of the buffered stream.
+enum Framing {
+    NeedMore,
+    /// Bytes to drop to get back in sync.
+    Skip(usize),
+    /// A frame of `len` bytes whose payload is the decoder input.
+    Frame {
+        len: usize,
+        config: DecoderConfig,
+        payload: Vec<u8>,
+    },
+}
+
+/// Splits an audio elementary stream into frames and decodes them to interleaved samples
+/// with full scale being 1.0.
+///
+/// Broadcasts switch between stereo, dual mono and 5.1 within a stream, typically at CM
+/// boundaries. A new decoder is set up for such frames, and their samples are remapped to
+/// the channel layout of the first frame decoded, which the stream keeps throughout.
+pub struct EsDecoder {
+    codec: AudioCodec,
+    buffer: Vec<u8>,
+    /// AudioSpecificConfig of the last LATM StreamMuxConfig.
+    latm_config: Option<[u8; 2]>,
+    decoder: Option<(DecoderConfig, Box<dyn Decoder>)>,
+    /// Channel layout of the first frame decoded.
+    layout: Option<Channels>,
+    sample_rate: u32,
+    /// Interleaved samples of a frame in another layout, before they are remapped.
+    remapped: Vec<f32>,
+}
+
+impl EsDecoder {
+    pub fn new(codec: AudioCodec) -> Self {
+        EsDecoder {
+            codec,
+            buffer: Vec::new(),
+            latm_config: None,
+            decoder: None,
+            layout: None,
+            sample_rate: 0,
+            remapped: Vec::new(),
+        }
+    }
+
+    /// Channels and sample rate, known once a frame is decoded.
+    pub fn spec(&self) -> Option<(usize, u32)> {
+        self.layout.map(|layout| (layout.count(), self.sample_rate))
+    }
+
+    /// Drop the bytes buffered, for decoding to start again at the next PES.
+    pub fn resync(&mut self) {
+        self.buffer.clear();
+    }
+
+    /// Buffer `data` and decode every frame it completes into `out`.
+    pub fn push(&mut self, data: &[u8], out: &mut Vec<f32>) -> Result<(), ReadErrorKind> {
+        let mut buffer = std::mem::take(&mut self.buffer);
+        buffer.extend_from_slice(data);
+        let mut start = 0;
+        let result = loop {
+            let framing = match self.next_frame(&buffer[start..]) {
+                Ok(framing) => framing,
+                Err(e) => break Err(e),
+            };
+            match framing {
+                Framing::NeedMore => break Ok(()),
+                Framing::Skip(len) => start += len,
+                Framing::Frame {
+                    len,
+                    config,
+                    payload,
+                } => {
+                    start += len;
+                    if let Err(e) = self.decode(config, payload, out) {
+                        break Err(e);
+                    }
+                }
+            }
+        };
+        buffer.drain(..start);
+        self.buffer = buffer;
+        result
+    }
+
+    fn next_frame(&mut self, data: &[u8]) -> Result<Framing, ReadErrorKind> {
+        match self.codec {
+            AudioCodec::Mpeg => Ok(mpeg_frame(data)),
+            AudioCodec::Adts => adts_frame(data),
+            AudioCodec::Latm => loas_frame(data, &mut self.latm_config),
+        }
+    }
+
+    fn decode(
+        &mut self,
+        config: DecoderConfig,
+        payload: Vec<u8>,
+        out: &mut Vec<f32>,
+    ) -> Result<(), ReadErrorKind> {
+        let decoder = match &mut self.decoder {
+            Some((current, decoder)) if *current == config => decoder,
+            _ => {
+                let decoder = new_decoder(config).map_err(symphonia_error)?;
+                &mut self.decoder.insert((config, decoder)).1
+            }
+        };
+        let packet = Packet::new_from_boxed_slice(0, 0, 0, payload.into_boxed_slice());
+        let decoded = decoder.decode(&packet).map_err(symphonia_error)?;
+        let spec = *decoded.spec();
+        if decoded.frames() == 0 {
+            return Ok(());
+        }
+        let layout = *self.layout.get_or_insert(spec.channels);
+        if self.sample_rate == 0 {
+            self.sample_rate = spec.rate;
+        } else if spec.rate != self.sample_rate {
+            return Err(ReadErrorKind::Unsupported(format!(
+                "audio sample rate changing within the stream from {} to {} Hz",
+                self.sample_rate, spec.rate
+            )));
+        }
+        let mut samples = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        samples.copy_interleaved_ref(decoded);
+        if spec.channels == layout {
+            out.extend_from_slice(samples.samples());
+        } else {
+            remap(samples.samples(), spec.channels, layout, &mut self.remapped);
+            out.append(&mut self.remapped);
+        }
+        Ok(())
+    }
+}
+
+fn new_decoder(config: DecoderConfig) -> Result<Box<dyn Decoder>, SymphoniaError> {
+    let mut params = CodecParameters::new();
+    let options = DecoderOptions::default();
+    Ok(match config {
+        DecoderConfig::Aac(asc) => {
+            params
+                .for_codec(CODEC_TYPE_AAC)
+                .with_extra_data(Box::new(asc));
+            Box::new(AacDecoder::try_new(&params, &options)?)
+        }
+        DecoderConfig::Mpeg { layer, .. } => {
+            params.for_codec(match layer {
+                1 => CODEC_TYPE_MP1,
+                2 => CODEC_TYPE_MP2,
+                _ => CODEC_TYPE_MP3,
+            });
+            Box::new(MpaDecoder::try_new(&params, &options)?)
+        }
+    })
+}
+
+/// Remap interleaved samples of the `from` layout to the `to` one into `out`.
+///
+/// Samples are folded into a stereo downmix, a single channel being mono and the others
+/// adding to the side they are on, centre ones to both and LFE to neither. A mono or stereo
+/// layout takes the downmix, and a wider one takes the channels the two layouts share, or
+/// the downmix at the front left and right when `from` is mono or stereo, the rest silent.
+fn remap(samples: &[f32], from: Channels, to: Channels, out: &mut Vec<f32>) {
+    let gains: Vec<(f32, f32)> = if from.count() == 1 {
+        vec![(1.0, 1.0)]
+    } else {
+        from.iter().map(stereo_gains).collect()
+    };
+    let narrow = Channels::FRONT_LEFT | Channels::FRONT_RIGHT;
+    let from_narrow = from.count() == 1 || narrow.contains(from);
+    out.clear();
+    for frame in samples.chunks_exact(gains.len()) {
+        let (left, right) = frame.iter().zip(&gains).fold(
+            (0.0, 0.0),
+            |(left, right), (sample, (to_left, to_right))| {
+                (left + sample * to_left, right + sample * to_right)
+            },
+        );
+        if to.count() == 1 {
+            out.push((left + right) / 2.0);
+            continue;
+        }
+        for position in to.iter() {
+            out.push(match position {
+                Channels::FRONT_LEFT if from_narrow || narrow.contains(to) => left,
+                Channels::FRONT_RIGHT if from_narrow || narrow.contains(to) => right,
+                position => match from.iter().position(|channel| channel == position) {
+                    Some(index) if !from_narrow => frame[index],
+                    _ => 0.0,
+                },
+            });
+        }
+    }
+}
+
+/// Gains of a channel at `position` to the left and right of a stereo downmix.
+fn stereo_gains(position: Channels) -> (f32, f32) {
+    let left = Channels::FRONT_LEFT_CENTRE
+        | Channels::REAR_LEFT
+        | Channels::REAR_LEFT_CENTRE
+        | Channels::SIDE_LEFT
+        | Channels::TOP_FRONT_LEFT
+        | Channels::TOP_REAR_LEFT
+        | Channels::FRONT_LEFT_WIDE
+        | Channels::FRONT_LEFT_HIGH;
+    let right = Channels::FRONT_RIGHT_CENTRE
+        | Channels::REAR_RIGHT
+        | Channels::REAR_RIGHT_CENTRE
+        | Channels::SIDE_RIGHT
+        | Channels::TOP_FRONT_RIGHT
+        | Channels::TOP_REAR_RIGHT
+        | Channels::FRONT_RIGHT_WIDE
+        | Channels::FRONT_RIGHT_HIGH;
+    match position {
+        Channels::FRONT_LEFT => (1.0, 0.0),
+        Channels::FRONT_RIGHT => (0.0, 1.0),
+        Channels::LFE1 | Channels::LFE2 => (0.0, 0.0),
+        position if left.contains(position) => (DOWNMIX_GAIN, 0.0),
+        position if right.contains(position) => (0.0, DOWNMIX_GAIN),
+        _ => (DOWNMIX_GAIN, DOWNMIX_GAIN),
+    }
+}
+
+fn symphonia_error(e: SymphoniaError) -> ReadErrorKind {
+    match e {
+        SymphoniaError::Unsupported(what) => ReadErrorKind::Unsupported(what.into()),
+        e => ReadErrorKind::Malformed(e.to_string()),
+    }
+}
+
+/// AudioSpecificConfig of an AAC object type, sampling frequency index and channels.
+fn audio_specific_config(object_type: u8, frequency_index: u8, channels: u8) -> [u8; 2] {
+    let asc = (object_type as u16) << 11 | (frequency_index as u16) << 7 | (channels as u16) << 3;
+    asc.to_be_bytes()
+}
+
+fn mpeg_frame(data: &[u8]) -> Framing {
+    if data.len() < 4 {
+        return Framing::NeedMore;
+    }
+    let header = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+    let version = (header >> 19) & 3;
+    let layer = 4 - ((header >> 17) & 3);
+    let bit_rate_index = ((header >> 12) & 0xF) as usize;
+    let sample_rate_index = ((header >> 10) & 3) as usize;
+    // Free format bit rates are not supported.
+    if header >> 21 != 0x7FF
+        || version == 1
+        || layer == 4
+        || bit_rate_index == 0
+        || bit_rate_index == 15
+        || sample_rate_index == 3
+    {
+        return Framing::Skip(1);
+    }
+    let mpeg1 = version == 3;
+    let sample_rate = MPEG_SAMPLE_RATES[sample_rate_index] >> (3 - version).min(2);
+    let table = match (mpeg1, layer) {
+        (true, layer) => layer as usize - 1,
+        (false, 1) => 3,
+        (false, _) => 4,
+    };
+    let bit_rate = MPEG_BIT_RATES[table][bit_rate_index] * 1000;
+    let padding = (header >> 9) & 1;
+    let mono = (header >> 6) & 3 == 3;
+    let len = match layer {
+        1 => (12 * bit_rate / sample_rate + padding) * 4,
+        3 if !mpeg1 => 72 * bit_rate / sample_rate + padding,
+        _ => 144 * bit_rate / sample_rate + padding,
+    } as usize;
+    if data.len() < len {
+        return Framing::NeedMore;
+    }
+    Framing::Frame {
+        len,
+        config: DecoderConfig::Mpeg {
+            layer: layer as u8,
+            sample_rate,
+            mono,
+        },
+        payload: data[..len].to_vec(),
+    }
+}
+
+fn adts_frame(data: &[u8]) -> Result<Framing, ReadErrorKind> {
+    if data.len() < 7 {
+        return Ok(Framing::NeedMore);
+    }
+    if data[0] != 0xFF || data[1] & 0xF6 != 0xF0 {
+        return Ok(Framing::Skip(1));
+    }
+    let header_len = if data[1] & 1 == 1 { 7 } else { 9 };
+    let profile = data[2] >> 6;
+    let frequency_index = (data[2] >> 2) & 0xF;
+    let channels = (data[2] & 1) << 2 | data[3] >> 6;
+    let len = ((data[3] as usize & 3) << 11) | (data[4] as usize) << 3 | data[5] as usize >> 5;
+    if len < header_len || frequency_index as usize >= AAC_SAMPLE_RATES.len() {
+        return Ok(Framing::Skip(1));
+    }
+    if data.len() < len {
+        return Ok(Framing::NeedMore);
+    }
+    if data[6] & 3 != 0 {
+        return Err(ReadErrorKind::Unsupported(
+            "ADTS frame of several raw data blocks".into(),
+        ));
+    }
+    Ok(Framing::Frame {
+        len,
+        config: DecoderConfig::Aac(audio_specific_config(
+            profile + 1,
+            frequency_index,
+            channels,
+        )),
+        payload: data[header_len..len].to_vec(),
+    })
+}
+
+/// Reads big-endian bit fields.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn read(&mut self, bits: u32) -> Result<u32, ReadErrorKind> {
+        let mut value = 0;
+        for _ in 0..bits {
+            let byte = self
+                .data
+                .get(self.pos / 8)
+                .ok_or_else(|| ReadErrorKind::Malformed("LATM frame is too short".into()))?;
+            value = value << 1 | (byte >> (7 - self.pos % 8)) as u32 & 1;
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+}
+
+/// Parse the frame of a LOAS AudioSyncStream carrying an AudioMuxElement.
+/// Only a single AAC program and layer, as broadcasters use, is supported.
+fn loas_frame(data: &[u8], config: &mut Option<[u8; 2]>) -> Result<Framing, ReadErrorKind> {
+    if data.len() < 3 {
+        return Ok(Framing::NeedMore);
+    }
+    if data[0] != 0x56 || data[1] & 0xE0 != 0xE0 {
+        return Ok(Framing::Skip(1));
+    }
+    let len = 3 + ((data[1] as usize & 0x1F) << 8 | data[2] as usize);
+    if data.len() < len {
+        return Ok(Framing::NeedMore);
+    }
+    let unsupported = |what: &str| ReadErrorKind::Unsupported(format!("LATM {}", what));
+    let mut bits = BitReader {
+        data: &data[3..len],
+        pos: 0,
+    };
+    // useSameStreamMux
+    if bits.read(1)? == 0 {
+        if bits.read(1)? != 0 {
+            return Err(unsupported("audioMuxVersion 1"));
+        }
+        // allStreamsSameTimeFraming, numSubFrames, numProgram, numLayer
+        if bits.read(1)? != 1 || bits.read(6)? != 0 || bits.read(4)? != 0 || bits.read(3)? != 0 {
+            return Err(unsupported("with several sub frames, programs or layers"));
+        }
+        let object_type = match bits.read(5)? {
+            31 => 32 + bits.read(6)?,
+            object_type => object_type,
+        };
+        let frequency_index = bits.read(4)?;
+        if frequency_index == 15 {
+            return Err(unsupported("explicit sampling frequency"));
+        }
+        let channels = bits.read(4)?;
+        // frameLengthFlag, then dependsOnCoreCoder with its coreCoderDelay.
+        bits.read(1)?;
+        if bits.read(1)? == 1 {
+            bits.read(14)?;
+        }
+        if bits.read(1)? != 0 || channels == 0 {
+            return Err(unsupported("AudioSpecificConfig extension"));
+        }
+        // frameLengthType, then latmBufferFullness.
+        if bits.read(3)? != 0 {
+            return Err(unsupported("fixed frame length"));
+        }
+        bits.read(8)?;
+        // otherDataPresent
+        if bits.read(1)? == 1 {
+            return Err(unsupported("other data"));
+        }
+        // crcCheckPresent with crcCheckSum.
+        if bits.read(1)? == 1 {
+            bits.read(8)?;
+        }
+        *config = Some(audio_specific_config(
+            object_type as u8,
+            frequency_index as u8,
+            channels as u8,
+        ));
+    }
+    let config =
+        config.ok_or_else(|| ReadErrorKind::Malformed("LATM frame before its config".into()))?;
+    let mut payload_len = 0;
+    loop {
+        let tmp = bits.read(8)?;
+        payload_len += tmp as usize;
+        if tmp != 255 {
+            break;
+        }
+    }
+    let payload = (0..payload_len)
+        .map(|_| bits.read(8).map(|byte| byte as u8))
+        .collect::<Result<Vec<u8>, ReadErrorKind>>()?;
+    Ok(Framing::Frame {
+        len,
+        config: DecoderConfig::Aac(config),
+        payload,
+    })
+}
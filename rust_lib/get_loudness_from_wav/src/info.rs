@@ -0,0 +1,125 @@
+use pyo3::prelude::*;
+
+use crate::metadata::{Bext, CuePoint};
+use crate::ts::AudioStream;
+use crate::wav::WavFormat;
+
+/// Header metadata of a WAV file, or format of the audio decoded from a transport stream.
+#[pyclass]
+#[derive(Clone)]
+pub struct WavInfo {
+    #[pyo3(get)]
+    pub sample_rate: u32,
+    #[pyo3(get)]
+    pub channels: u16,
+    /// Size of the sample container.
+    #[pyo3(get)]
+    pub bits_per_sample: u16,
+    #[pyo3(get)]
+    pub valid_bits_per_sample: u16,
+    #[pyo3(get)]
+    pub sample_format: &'static str,
+    /// Speaker position bits of WAVE_FORMAT_EXTENSIBLE, None for other formats.
+    #[pyo3(get)]
+    pub channel_mask: Option<u32>,
+    #[pyo3(get)]
+    pub frames: u64,
+    /// Rate of the frames analysed, below `sample_rate` when they were decimated.
+    #[pyo3(get)]
+    pub analysis_rate: u32,
+    /// PTS of the first frame in sec for a transport stream, None for a WAV file.
+    #[pyo3(get)]
+    pub start_time_sec: Option<f64>,
+    /// PID of the audio stream decoded from a transport stream, None for a WAV file.
+    #[pyo3(get)]
+    pub audio_pid: Option<u16>,
+    /// Audio streams of the program of a transport stream, as `list_audio_streams` gives
+    /// them, empty for a WAV file.
+    #[pyo3(get)]
+    pub audio_streams: Vec<AudioStream>,
+    /// Broadcast audio extension of a BWF file.
+    #[pyo3(get)]
+    pub bext: Option<Bext>,
+    /// Markers of the `cue ` chunk, with their labels.
+    #[pyo3(get)]
+    pub cue_points: Vec<CuePoint>,
+    /// Where the samples stopped being readable, when read leniently. `frames` is then the
+    /// number of frames recovered. For a transport stream it is the first damage, past which
+    /// decoding went on.
+    #[pyo3(get)]
+    pub corruption: Option<Corruption>,
+}
+
+impl WavInfo {
+    pub fn new(format: &WavFormat, frames: u64) -> Self {
+        WavInfo {
+            sample_rate: format.sample_rate,
+            channels: format.channels,
+            bits_per_sample: format.bits_per_sample,
+            valid_bits_per_sample: format.valid_bits_per_sample,
+            sample_format: format.sample_format.name(),
+            channel_mask: match format.channel_mask {
+                0 => None,
+                mask => Some(mask),
+            },
+            frames,
+            analysis_rate: format.sample_rate,
+            start_time_sec: None,
+            audio_pid: None,
+            audio_streams: Vec::new(),
+            bext: None,
+            cue_points: Vec::new(),
+            corruption: None,
+        }
+    }
+}
+
+#[pymethods]
+impl WavInfo {
+    /// Duration of the recording in sec.
+    #[getter]
+    fn duration_sec(&self) -> f64 {
+        self.frames as f64 / self.sample_rate as f64
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "WavInfo(sample_rate={}, channels={}, bits_per_sample={}, sample_format='{}', frames={})",
+            self.sample_rate, self.channels, self.bits_per_sample, self.sample_format, self.frames
+        )
+    }
+}
+
+/// Part of the samples of a truncated or corrupted WAV file that a lenient read recovered,
+/// or the first damage to the audio of a transport stream.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct Corruption {
+    /// Byte offset where the corruption starts, right after the last whole frame recovered.
+    #[pyo3(get)]
+    pub offset: u64,
+    #[pyo3(get)]
+    pub reason: String,
+    /// Frames declared by the data chunk, None when its size is unknown.
+    #[pyo3(get)]
+    pub declared_frames: Option<u64>,
+    #[pyo3(get)]
+    pub recovered_frames: u64,
+    /// Bytes of the data chunk recovered, whole frames only.
+    #[pyo3(get)]
+    pub recovered_bytes: u64,
+    /// Frames of silence put in place of the audio dropped from a transport stream,
+    /// 0 for a WAV file.
+    #[pyo3(get)]
+    pub filled_frames: u64,
+}
+
+#[pymethods]
+impl Corruption {
+    fn __repr__(&self) -> String {
+        format!(
+            "Corruption(offset={}, recovered_frames={}, declared_frames={:?}, reason='{}')",
+            self.offset, self.recovered_frames, self.declared_frames, self.reason
+        )
+    }
+}
@@ -1,18 +1,548 @@
-use std::fs::File;
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 
-/// Formats the sum of two numbers as string.
+mod buffer;
+mod channels;
+mod cue_writer;
+mod decoded;
+mod duration_units;
+mod envelope;
+mod error;
+mod es;
+mod info;
+mod lufs;
+mod metadata;
+mod noise_floor;
+mod property;
+mod resample;
+mod scenes;
+mod section;
+mod silence;
+mod source;
+mod ts;
+mod view;
+mod wav;
+
+use channels::{ChannelLoudness, ChannelMode, PerChannelLoudness};
+use cue_writer::Marker;
+use decoded::DecodedAudio;
+use duration_units::DurationSecUnits;
+use envelope::{Envelope, EnvelopeAnalyzer, EnvelopeKind};
+use info::{Corruption, WavInfo};
+use lufs::{Loudness, LoudnessMeter};
+use metadata::{Bext, CuePoint};
+use noise_floor::{NoiseFloor, NoiseFloorEstimator, SilenceThreshold};
+use property::{NominalCMStructure, ProgramProperty};
+use section::SilentSection;
+use silence::{SilenceAnalyzer, SilenceDetector, Silences};
+use source::{AudioReader, AudioSource};
+use ts::{AudioStream, AudioStreamSelector};
+use view::{RangeStats, WavView};
+
+/// Get the loudness of every frame of a WAV file, streaming the data chunk.
+/// Samples of every integer and float layout are scaled so that full scale is 1.0.
+/// Channels are reduced according to `channel_mode`, see `ChannelMode`. By default every
+/// interleaved sample is kept, as before channels could be reduced, so that a stereo file
+/// gives two values per frame; `FrameLoudness` asks for `"all"` instead.
+/// `audio_stream` picks the audio of a transport stream, see `AudioStreamSelector`.
+/// Besides a path, `file_path` takes the file in memory, a readable binary file or pipe,
+/// or samples already decoded, see `AudioSource`.
+/// `lenient` analyses the whole frames of a truncated or corrupted WAV file up to where the
+/// corruption starts instead of raising, which `WavInfo.corruption` of the other functions
+/// reports. Audio of a transport stream which cannot be decoded is filled with silence.
+/// `analysis_rate` decimates the samples to that rate with an anti-alias filter before
+/// taking their loudness, so that frame counts mean the same duration whatever the sample
+/// rate of the file. It is reported as `WavInfo.analysis_rate`.
+/// The result is a `numpy.ndarray[float32]` sharing the Rust buffer.
+#[pyfunction(
+    channel_mode = "ChannelMode::Interleaved",
+    audio_stream = "AudioStreamSelector::default()",
+    lenient = "false",
+    analysis_rate = "None"
+)]
+fn wav2loudness(
+    py: Python,
+    file_path: AudioSource,
+    channel_mode: ChannelMode,
+    audio_stream: AudioStreamSelector,
+    lenient: bool,
+    analysis_rate: Option<u32>,
+) -> PyResult<PyObject> {
+    Ok(wav2loudness_with_info(
+        py,
+        file_path,
+        channel_mode,
+        audio_stream,
+        lenient,
+        analysis_rate,
+    )?
+    .0)
+}
+
+/// Same as `wav2loudness`, also returning the header metadata of the file.
+#[pyfunction(
+    channel_mode = "ChannelMode::Interleaved",
+    audio_stream = "AudioStreamSelector::default()",
+    lenient = "false",
+    analysis_rate = "None"
+)]
+fn wav2loudness_with_info(
+    py: Python,
+    file_path: AudioSource,
+    channel_mode: ChannelMode,
+    audio_stream: AudioStreamSelector,
+    lenient: bool,
+    analysis_rate: Option<u32>,
+) -> PyResult<(PyObject, WavInfo)> {
+    let mut reader = AudioReader::open(&file_path, &audio_stream, lenient)?;
+    let format = *reader.format();
+    channel_mode.validate(format.channels as usize)?;
+    let analysis_rate = resample::analysis_rate(analysis_rate, format.sample_rate)?;
+    let mut loudness = ChannelLoudness::new(channel_mode);
+    reader.analyze_at(analysis_rate, &mut loudness)?;
+    let mut info = reader.info();
+    info.analysis_rate = analysis_rate;
+    Ok((buffer::into_ndarray(py, loudness.values)?, info))
+}
+
+/// Get the loudness envelope of each channel of a WAV file as a list of arrays.
+#[pyfunction(
+    audio_stream = "AudioStreamSelector::default()",
+    lenient = "false",
+    analysis_rate = "None"
+)]
+fn wav2channel_loudness(
+    py: Python,
+    file_path: AudioSource,
+    audio_stream: AudioStreamSelector,
+    lenient: bool,
+    analysis_rate: Option<u32>,
+) -> PyResult<Vec<PyObject>> {
+    let mut reader = AudioReader::open(&file_path, &audio_stream, lenient)?;
+    let format = *reader.format();
+    let analysis_rate = resample::analysis_rate(analysis_rate, format.sample_rate)?;
+    let mut loudness = PerChannelLoudness::new(format.channels as usize);
+    reader.analyze_at(analysis_rate, &mut loudness)?;
+    loudness
+        .values
+        .into_iter()
+        .map(|values| buffer::into_ndarray(py, values))
+        .collect()
+}
+
+/// Get a downsampled loudness envelope of a WAV file.
+/// Each value is the `kind` statistic ("rms", "peak" or "mean_abs") of frame magnitudes
+/// over `window_sec`, taken every `hop_sec` (defaults to `window_sec`).
+#[pyfunction(
+    window_sec = "0.01",
+    hop_sec = "None",
+    kind = "EnvelopeKind::Rms",
+    channel_mode = "ChannelMode::All",
+    audio_stream = "AudioStreamSelector::default()",
+    lenient = "false"
+)]
+#[allow(clippy::too_many_arguments)]
+fn wav2envelope(
+    py: Python,
+    file_path: AudioSource,
+    window_sec: f64,
+    hop_sec: Option<f64>,
+    kind: EnvelopeKind,
+    channel_mode: ChannelMode,
+    audio_stream: AudioStreamSelector,
+    lenient: bool,
+) -> PyResult<Envelope> {
+    let mut reader = AudioReader::open(&file_path, &audio_stream, lenient)?;
+    let format = *reader.format();
+    if channel_mode == ChannelMode::Interleaved {
+        return Err(PyValueError::new_err(
+            "Envelope needs channels reduced per frame: interleaved.",
+        ));
+    }
+    channel_mode.validate(format.channels as usize)?;
+    let window_frames = envelope::sec_to_frames(window_sec, format.sample_rate, "window_sec")?;
+    let hop_frames = match hop_sec {
+        Some(hop_sec) => envelope::sec_to_frames(hop_sec, format.sample_rate, "hop_sec")?,
+        None => window_frames,
+    };
+    let mut analyzer = EnvelopeAnalyzer::new(kind, channel_mode, window_frames, hop_frames);
+    reader.analyze(&mut analyzer)?;
+    Ok(Envelope {
+        values: buffer::into_ndarray(py, analyzer.values)?,
+        frame_per_sec: format.sample_rate as f64 / hop_frames as f64,
+        window_frames,
+        hop_frames,
+        info: reader.info(),
+    })
+}
+
+/// Measure the K-weighted loudness of a WAV file following ITU-R BS.1770 and EBU R128:
+/// momentary and short-term series, integrated loudness and loudness range.
+#[pyfunction(audio_stream = "AudioStreamSelector::default()", lenient = "false")]
+fn wav2lufs(
+    py: Python,
+    file_path: AudioSource,
+    audio_stream: AudioStreamSelector,
+    lenient: bool,
+) -> PyResult<Loudness> {
+    let mut reader = AudioReader::open(&file_path, &audio_stream, lenient)?;
+    let format = *reader.format();
+    let mut meter = LoudnessMeter::new(
+        format.sample_rate,
+        format.channels as usize,
+        format.channel_mask,
+    );
+    reader.analyze(&mut meter)?;
+    let measurement = meter.measure();
+    Ok(Loudness {
+        momentary: buffer::into_ndarray(py, measurement.momentary)?,
+        short_term: buffer::into_ndarray(py, measurement.short_term)?,
+        integrated: measurement.integrated,
+        loudness_range: measurement.loudness_range,
+        frame_per_sec: 1.0 / lufs::SUB_BLOCK_SEC,
+        info: reader.info(),
+    })
+}
+
+/// Estimate the noise floor of a WAV file from a histogram of the peak level of each
+/// `window_sec` window, and choose a silence threshold `margin_db` above it.
+/// Files with digital silence keep digital zero as their threshold.
+#[pyfunction(
+    channel_mode = "ChannelMode::All",
+    window_sec = "0.01",
+    margin_db = "6.0",
+    audio_stream = "AudioStreamSelector::default()",
+    lenient = "false",
+    analysis_rate = "None"
+)]
+#[allow(clippy::too_many_arguments)]
+fn estimate_noise_floor(
+    file_path: AudioSource,
+    channel_mode: ChannelMode,
+    window_sec: f64,
+    margin_db: f64,
+    audio_stream: AudioStreamSelector,
+    lenient: bool,
+    analysis_rate: Option<u32>,
+) -> PyResult<NoiseFloor> {
+    noise_floor(
+        &file_path,
+        channel_mode,
+        window_sec,
+        margin_db,
+        &audio_stream,
+        lenient,
+        analysis_rate,
+    )
+}
+
+fn noise_floor(
+    source: &AudioSource,
+    channel_mode: ChannelMode,
+    window_sec: f64,
+    margin_db: f64,
+    audio_stream: &AudioStreamSelector,
+    lenient: bool,
+    analysis_rate: Option<u32>,
+) -> PyResult<NoiseFloor> {
+    let mut reader = AudioReader::open(source, audio_stream, lenient)?;
+    let format = *reader.format();
+    channel_mode.validate(format.channels as usize)?;
+    let analysis_rate = resample::analysis_rate(analysis_rate, format.sample_rate)?;
+    let window_frames = envelope::sec_to_frames(window_sec, analysis_rate, "window_sec")?;
+    let mut estimator = NoiseFloorEstimator::new(channel_mode, window_frames);
+    reader.analyze_at(analysis_rate, &mut estimator)?;
+    Ok(estimator.estimate(margin_db))
+}
+
+/// Detect silent sections of a WAV file while streaming it, without keeping the loudness.
+///
+/// A silence starts at a frame whose loudness is at most `threshold_dbfs` and lasts while
+/// frames stay at most `exit_threshold_dbfs` (defaults to `threshold_dbfs`), tolerating up to
+/// `max_blip` louder frames in a row. Without a threshold only digital zero is silent, and
+/// with "auto" it is chosen by `estimate_noise_floor` and reported in the result, which
+/// reads the audio twice and so raises ValueError for a file object that is not seekable.
+/// Sections longer than `min_duration` frames are reported as `ProgramScenes.extract_silent_sections`
+/// does, so a silence running at the end of the file is not.
+/// Frames are decimated to `analysis_rate` if given, see `wav2loudness`, and
+/// `min_duration_sec` and `max_blip_sec` give `min_duration` and `max_blip` in sec instead,
+/// so that they do not depend on the rate.
+#[pyfunction(
+    threshold_dbfs = "SilenceThreshold::DigitalZero",
+    min_duration = "0",
+    channel_mode = "ChannelMode::All",
+    exit_threshold_dbfs = "None",
+    max_blip = "0",
+    audio_stream = "AudioStreamSelector::default()",
+    lenient = "false",
+    analysis_rate = "None",
+    min_duration_sec = "None",
+    max_blip_sec = "None"
+)]
+#[allow(clippy::too_many_arguments)]
+fn detect_silences(
+    file_path: AudioSource,
+    threshold_dbfs: SilenceThreshold,
+    min_duration: u64,
+    channel_mode: ChannelMode,
+    exit_threshold_dbfs: Option<f64>,
+    max_blip: u64,
+    audio_stream: AudioStreamSelector,
+    lenient: bool,
+    analysis_rate: Option<u32>,
+    min_duration_sec: Option<f64>,
+    max_blip_sec: Option<f64>,
+) -> PyResult<Silences> {
+    let threshold_dbfs = match threshold_dbfs {
+        SilenceThreshold::DigitalZero => None,
+        SilenceThreshold::Dbfs(dbfs) => Some(dbfs),
+        SilenceThreshold::Auto => {
+            file_path.check_rewindable()?;
+            let threshold_dbfs = noise_floor(
+                &file_path,
+                channel_mode,
+                0.01,
+                6.0,
+                &audio_stream,
+                lenient,
+                analysis_rate,
+            )?
+            .threshold_dbfs;
+            file_path.rewind()?;
+            threshold_dbfs
+        }
+    };
+    let mut reader = AudioReader::open(&file_path, &audio_stream, lenient)?;
+    let format = *reader.format();
+    channel_mode.validate(format.channels as usize)?;
+    if let (Some(enter), Some(exit)) = (threshold_dbfs, exit_threshold_dbfs) {
+        if exit < enter {
+            return Err(PyValueError::new_err(format!(
+                "exit_threshold_dbfs must not be lower than threshold_dbfs: {} {}.",
+                exit, enter
+            )));
+        }
+    }
+    let analysis_rate = resample::analysis_rate(analysis_rate, format.sample_rate)?;
+    let mut frame_per_sec = analysis_rate as f64;
+    if channel_mode == ChannelMode::Interleaved {
+        frame_per_sec *= format.channels as f64;
+    }
+    let min_duration = match min_duration_sec {
+        Some(sec) => silence::sec_to_frame_count(sec, frame_per_sec, "min_duration_sec")?,
+        None => min_duration,
+    };
+    let max_blip = match max_blip_sec {
+        Some(sec) => silence::sec_to_frame_count(sec, frame_per_sec, "max_blip_sec")?,
+        None => max_blip,
+    };
+    let enter_threshold = threshold_dbfs.map_or(0.0, silence::dbfs_to_linear);
+    let exit_threshold = exit_threshold_dbfs.map_or(enter_threshold, silence::dbfs_to_linear);
+    let detector = SilenceDetector::new(enter_threshold, exit_threshold, min_duration, max_blip);
+    let mut analyzer = SilenceAnalyzer::new(channel_mode, detector);
+    reader.analyze_at(analysis_rate, &mut analyzer)?;
+    let mut info = reader.info();
+    info.analysis_rate = analysis_rate;
+    Ok(Silences {
+        silent_ratio: analyzer.detector.silent_ratio(),
+        sections: analyzer.detector.sections,
+        threshold_dbfs,
+        frame_per_sec,
+        info,
+    })
+}
+
+/// Read the header metadata of a WAV file without decoding its samples, along with the
+/// `bext` and `cue ` chunks before or after them. The audio of a transport stream is
+/// decoded to count its frames.
+#[pyfunction(audio_stream = "AudioStreamSelector::default()", lenient = "false")]
+fn wav_info(
+    file_path: AudioSource,
+    audio_stream: AudioStreamSelector,
+    lenient: bool,
+) -> PyResult<WavInfo> {
+    let mut reader = AudioReader::open(&file_path, &audio_stream, lenient)?;
+    reader.scan()?;
+    Ok(reader.info())
+}
+
+/// List the audio streams of the first program of a transport stream, in PMT order,
+/// to choose the `audio_stream` of the other functions. A WAV file gives an empty list.
+#[pyfunction]
+fn list_audio_streams(file_path: AudioSource) -> PyResult<Vec<AudioStream>> {
+    Ok(AudioReader::list_streams(&file_path)?)
+}
+
+/// Copy a WAV file to `output_path` with `markers` as cue points labelled in a `LIST`
+/// `adtl` chunk, for editors to show them on the waveform. A marker is a tuple of
+/// `(position, label)` or `(position, label, length)` in frames, a length making it a region.
+/// Cue points of the file are kept before the markers, the samples are copied untouched
+/// and `output_path` is replaced atomically.
+#[pyfunction]
+fn write_cue_points(file_path: &str, output_path: &str, markers: Vec<Marker>) -> PyResult<()> {
+    cue_writer::write_cue_points(file_path, output_path, &markers)
+}
+
+/// Construct CM sections from silent sections sorted by their ends, following the nominal
+/// CM structures in order, as `ProgramScenes.construct_cm_sections` does.
+/// Returns `(start_sec, end_sec)` of each CM section found.
 #[pyfunction]
-fn wav2loudness(file_path: &str) -> Vec<f32> {
-    let file_in = File::open(file_path).unwrap();
-    let (_head, samples) = wav_io::read_from_file(file_in).unwrap();
-    samples.iter().map(|x| x.abs()).collect()
+fn construct_cm_sections(
+    silent_sections: Vec<SilentSection>,
+    duration_sec_units: DurationSecUnits,
+    cm_structures: Vec<NominalCMStructure>,
+    has_monolithic_cm: bool,
+) -> PyResult<Vec<(f64, f64)>> {
+    scenes::check_sorted(&silent_sections)?;
+    Ok(scenes::construct_cm_sections(
+        &silent_sections,
+        &duration_sec_units,
+        &cm_structures,
+        has_monolithic_cm,
+    ))
+}
+
+/// Search CM sections from silent sections sorted by their ends, where CMs follow one
+/// another without structures, as `ProgramScenes.search_cm_sections` does.
+/// Returns `(start_sec, end_sec)` of each CM section found.
+#[pyfunction]
+fn search_cm_sections(
+    silent_sections: Vec<SilentSection>,
+    duration_sec_units: DurationSecUnits,
+) -> PyResult<Vec<(f64, f64)>> {
+    scenes::check_sorted(&silent_sections)?;
+    Ok(scenes::search_cm_sections(
+        &silent_sections,
+        &duration_sec_units,
+    ))
+}
+
+/// Generate `(start_sec, end_sec)` of scenes around CM sections, as
+/// `ProgramScenes.generate_scenes` does. A `last_scene_duration` of 0 lets the last scene
+/// run up to `last_end_sec_candidate`.
+#[pyfunction(
+    starting_range_sec = "scenes::STARTING_RANGE_SEC",
+    end_margin_sec = "scenes::END_MARGIN_SEC"
+)]
+fn generate_scenes(
+    first_start_sec_candidate: f64,
+    last_end_sec_candidate: f64,
+    cm_sections: Vec<(f64, f64)>,
+    last_scene_duration: f64,
+    starting_range_sec: f64,
+    end_margin_sec: f64,
+) -> Vec<(f64, f64)> {
+    scenes::generate_scenes(
+        first_start_sec_candidate,
+        last_end_sec_candidate,
+        &cm_sections,
+        last_scene_duration,
+        starting_range_sec,
+        end_margin_sec,
+    )
+}
+
+/// Detect the scenes of a TV program from a WAV file or transport stream end to end,
+/// returning `(start_sec, end_sec)` of each scene along with the `Silences` dividing them,
+/// which tell the threshold chosen, the audio streams and any corruption read past.
+///
+/// With a `ProgramProperty`, silences are detected with its settings and CM sections follow
+/// its CM structures, as cmcut_direct.py does. Without one, silences of digital zero longer
+/// than 0.625 sec divide CMs searched without structures, as cmcut_default.py does.
+/// `starting_range_sec` and `end_margin_sec` are given to `generate_scenes`.
+/// Raises ValueError if the file has no silent section.
+#[pyfunction(
+    config = "None",
+    starting_range_sec = "scenes::STARTING_RANGE_SEC",
+    end_margin_sec = "scenes::END_MARGIN_SEC"
+)]
+fn detect_program_scenes(
+    file_path: AudioSource,
+    config: Option<PyRef<ProgramProperty>>,
+    starting_range_sec: f64,
+    end_margin_sec: f64,
+) -> PyResult<(Vec<(f64, f64)>, Silences)> {
+    let silences = match config.as_deref() {
+        Some(property) => detect_silences(
+            file_path,
+            property.silence_threshold_dbfs,
+            property.duration_frame_threshold(),
+            property.channel_mode,
+            property.silence_exit_threshold_dbfs,
+            property.silence_max_blip_frame,
+            property.audio_stream.clone(),
+            property.lenient,
+            property.analysis_rate,
+            property.duration_threshold_sec,
+            property.silence_max_blip_sec,
+        )?,
+        None => detect_silences(
+            file_path,
+            SilenceThreshold::DigitalZero,
+            0,
+            ChannelMode::All,
+            None,
+            0,
+            AudioStreamSelector::default(),
+            false,
+            None,
+            Some(scenes::UNSTRUCTURED_DURATION_THRESHOLD_SEC),
+            None,
+        )?,
+    };
+    let sections: Vec<SilentSection> = silences
+        .sections
+        .iter()
+        .map(|&(start, end)| SilentSection::new(start, end, silences.frame_per_sec))
+        .collect();
+    if sections.is_empty() {
+        return Err(PyValueError::new_err(
+            "No silent section is found to divide the program into scenes.",
+        ));
+    }
+    let scenes = scenes::program_scenes(
+        &sections,
+        config.as_deref(),
+        starting_range_sec,
+        end_margin_sec,
+    );
+    Ok((scenes, silences))
 }
 
 /// A Python module implemented in Rust.
 #[pymodule]
-fn get_loudness_from_wav(_py: Python, m: &PyModule) -> PyResult<()> {
+fn get_loudness_from_wav(py: Python, m: &PyModule) -> PyResult<()> {
+    error::register(py, m)?;
+    m.add_class::<buffer::LoudnessBuffer>()?;
+    m.add_class::<WavInfo>()?;
+    m.add_class::<Envelope>()?;
+    m.add_class::<Loudness>()?;
+    m.add_class::<Silences>()?;
+    m.add_class::<NoiseFloor>()?;
+    m.add_class::<AudioStream>()?;
+    m.add_class::<DecodedAudio>()?;
+    m.add_class::<WavView>()?;
+    m.add_class::<RangeStats>()?;
+    m.add_class::<Bext>()?;
+    m.add_class::<CuePoint>()?;
+    m.add_class::<Corruption>()?;
+    m.add_class::<SilentSection>()?;
+    m.add_class::<DurationSecUnits>()?;
+    m.add_class::<NominalCMStructure>()?;
+    m.add_class::<ProgramProperty>()?;
     m.add_function(wrap_pyfunction!(wav2loudness, m)?)?;
+    m.add_function(wrap_pyfunction!(wav2loudness_with_info, m)?)?;
+    m.add_function(wrap_pyfunction!(wav2channel_loudness, m)?)?;
+    m.add_function(wrap_pyfunction!(wav2envelope, m)?)?;
+    m.add_function(wrap_pyfunction!(wav2lufs, m)?)?;
+    m.add_function(wrap_pyfunction!(estimate_noise_floor, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_silences, m)?)?;
+    m.add_function(wrap_pyfunction!(wav_info, m)?)?;
+    m.add_function(wrap_pyfunction!(list_audio_streams, m)?)?;
+    m.add_function(wrap_pyfunction!(write_cue_points, m)?)?;
+    m.add_function(wrap_pyfunction!(construct_cm_sections, m)?)?;
+    m.add_function(wrap_pyfunction!(search_cm_sections, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_scenes, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_program_scenes, m)?)?;
     Ok(())
 }
-
@@ -0,0 +1,256 @@
+use std::f64::consts::PI;
+
+use pyo3::prelude::*;
+
+use crate::info::WavInfo;
+use crate::wav::Analyzer;
+
+/// Length of the sub-blocks that the gating blocks are built from, in sec.
+pub const SUB_BLOCK_SEC: f64 = 0.1;
+/// Sub-blocks per 400 ms momentary block.
+const MOMENTARY_SUB_BLOCKS: usize = 4;
+/// Sub-blocks per 3 s short-term block.
+const SHORT_TERM_SUB_BLOCKS: usize = 30;
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const INTEGRATED_RELATIVE_GATE_LU: f64 = -10.0;
+const RANGE_RELATIVE_GATE_LU: f64 = -20.0;
+
+/// Second order IIR section in direct form I.
+#[derive(Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Biquad {
+            b,
+            a,
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+/// K-weighting filter of ITU-R BS.1770: a high shelf followed by the RLB high pass,
+/// derived for any sample rate.
+#[derive(Clone, Copy)]
+struct KWeighting {
+    shelf: Biquad,
+    high_pass: Biquad,
+}
+
+impl KWeighting {
+    fn new(sample_rate: u32) -> Self {
+        let fs = sample_rate as f64;
+
+        let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+        let k = (PI * f0 / fs).tan();
+        let vh = 10f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad::new(
+            [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        let (f0, q) = (38.13547087602444, 0.5003270373238773);
+        let k = (PI * f0 / fs).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let high_pass = Biquad::new(
+            [1.0, -2.0, 1.0],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        KWeighting { shelf, high_pass }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        self.high_pass.process(self.shelf.process(x))
+    }
+}
+
+const SPEAKER_LOW_FREQUENCY: u32 = 0x8;
+/// Back and side speakers, boosted as surround channels.
+const SPEAKER_SURROUND: u32 = 0x10 | 0x20 | 0x200 | 0x400;
+
+/// Weight of each channel in the sum. The LFE channel is excluded and surround channels
+/// are boosted by 1.5 dB. Without a channel mask for every channel the L, R, C, LFE, Ls, Rs
+/// order is assumed.
+fn channel_weights(channels: usize, channel_mask: u32) -> Vec<f64> {
+    if channel_mask.count_ones() as usize == channels {
+        return (0..32)
+            .map(|bit| 1u32 << bit)
+            .filter(|speaker| channel_mask & speaker != 0)
+            .map(|speaker| match speaker {
+                SPEAKER_LOW_FREQUENCY => 0.0,
+                speaker if speaker & SPEAKER_SURROUND != 0 => 1.41,
+                _ => 1.0,
+            })
+            .collect();
+    }
+    match channels {
+        5 => vec![1.0, 1.0, 1.0, 1.41, 1.41],
+        6 => vec![1.0, 1.0, 1.0, 0.0, 1.41, 1.41],
+        _ => vec![1.0; channels],
+    }
+}
+
+fn to_lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+/// Streams K-weighted power into 100 ms sub-blocks.
+/// Only one weighted power value per sub-block is kept, so memory stays small for long files.
+pub struct LoudnessMeter {
+    filters: Vec<KWeighting>,
+    weights: Vec<f64>,
+    sub_block_frames: usize,
+    frames_in_sub_block: usize,
+    sums: Vec<f64>,
+    sub_block_powers: Vec<f64>,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: u32, channels: usize, channel_mask: u32) -> Self {
+        LoudnessMeter {
+            filters: vec![KWeighting::new(sample_rate); channels],
+            weights: channel_weights(channels, channel_mask),
+            sub_block_frames: ((sample_rate as f64 * SUB_BLOCK_SEC).round() as usize).max(1),
+            frames_in_sub_block: 0,
+            sums: vec![0.0; channels],
+            sub_block_powers: Vec::new(),
+        }
+    }
+
+    /// Weighted mean square of every block of `len` sub-blocks, stepping by one sub-block.
+    fn block_powers(&self, len: usize) -> Vec<f64> {
+        if self.sub_block_powers.len() < len {
+            return Vec::new();
+        }
+        let mut sum: f64 = self.sub_block_powers[..len].iter().sum();
+        let mut powers = vec![sum / len as f64];
+        for i in len..self.sub_block_powers.len() {
+            sum += self.sub_block_powers[i] - self.sub_block_powers[i - len];
+            powers.push(sum.max(0.0) / len as f64);
+        }
+        powers
+    }
+
+    /// Blocks passing the absolute gate and the gate relative to their mean loudness.
+    fn gated_blocks(powers: &[f64], relative_gate_lu: f64) -> Vec<f64> {
+        let absolute: Vec<f64> = powers
+            .iter()
+            .copied()
+            .filter(|p| to_lufs(*p) > ABSOLUTE_GATE_LUFS)
+            .collect();
+        if absolute.is_empty() {
+            return absolute;
+        }
+        let gate = to_lufs(absolute.iter().sum::<f64>() / absolute.len() as f64) + relative_gate_lu;
+        absolute
+            .into_iter()
+            .filter(|p| to_lufs(*p) > gate)
+            .collect()
+    }
+
+    /// Summarise the measurement once all samples are processed.
+    pub fn measure(&self) -> Measurement {
+        let momentary = self.block_powers(MOMENTARY_SUB_BLOCKS);
+        let short_term = self.block_powers(SHORT_TERM_SUB_BLOCKS);
+
+        let gated = Self::gated_blocks(&momentary, INTEGRATED_RELATIVE_GATE_LU);
+        let integrated = if gated.is_empty() {
+            f64::NEG_INFINITY
+        } else {
+            to_lufs(gated.iter().sum::<f64>() / gated.len() as f64)
+        };
+
+        let mut gated: Vec<f64> = Self::gated_blocks(&short_term, RANGE_RELATIVE_GATE_LU)
+            .into_iter()
+            .map(to_lufs)
+            .collect();
+        gated.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let loudness_range = if gated.len() < 2 {
+            0.0
+        } else {
+            let percentile = |p: f64| gated[((gated.len() - 1) as f64 * p).round() as usize];
+            percentile(0.95) - percentile(0.10)
+        };
+
+        Measurement {
+            momentary: momentary.into_iter().map(|p| to_lufs(p) as f32).collect(),
+            short_term: short_term.into_iter().map(|p| to_lufs(p) as f32).collect(),
+            integrated,
+            loudness_range,
+        }
+    }
+}
+
+impl Analyzer for LoudnessMeter {
+    fn process(&mut self, samples: &[f32], channels: usize) {
+        for frame in samples.chunks_exact(channels) {
+            for (channel, x) in frame.iter().enumerate() {
+                let y = self.filters[channel].process(*x as f64);
+                self.sums[channel] += y * y;
+            }
+            self.frames_in_sub_block += 1;
+            if self.frames_in_sub_block == self.sub_block_frames {
+                let power = self
+                    .sums
+                    .iter()
+                    .zip(&self.weights)
+                    .map(|(sum, weight)| weight * sum / self.sub_block_frames as f64)
+                    .sum();
+                self.sub_block_powers.push(power);
+                self.sums.iter_mut().for_each(|sum| *sum = 0.0);
+                self.frames_in_sub_block = 0;
+            }
+        }
+    }
+}
+
+/// Loudness figures of a finished measurement.
+pub struct Measurement {
+    pub momentary: Vec<f32>,
+    pub short_term: Vec<f32>,
+    pub integrated: f64,
+    pub loudness_range: f64,
+}
+
+/// ITU-R BS.1770 / EBU R128 loudness of a WAV file.
+/// `momentary[i]` and `short_term[i]` are the loudness in LUFS of the 400 ms and 3 s
+/// windows starting at `i / frame_per_sec` sec; silence is `-inf`.
+#[pyclass]
+pub struct Loudness {
+    #[pyo3(get)]
+    pub momentary: PyObject,
+    #[pyo3(get)]
+    pub short_term: PyObject,
+    /// Gated integrated loudness in LUFS.
+    #[pyo3(get)]
+    pub integrated: f64,
+    /// Loudness range in LU.
+    #[pyo3(get)]
+    pub loudness_range: f64,
+    #[pyo3(get)]
+    pub frame_per_sec: f64,
+    #[pyo3(get)]
+    pub info: WavInfo,
+}
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use pyo3::prelude::*;
+
+use crate::info::WavInfo;
+
+/// Chunks larger than this are skipped instead of being read as metadata.
+pub const MAX_METADATA_CHUNK: u64 = 1 << 20;
+/// Size of the fields of a `bext` chunk up to its version.
+const BEXT_MIN_SIZE: usize = 348;
+const CUE_POINT_SIZE: usize = 24;
+
+/// Broadcast audio extension chunk of a Broadcast Wave Format file, EBU Tech 3285.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct Bext {
+    #[pyo3(get)]
+    pub description: String,
+    #[pyo3(get)]
+    pub originator: String,
+    #[pyo3(get)]
+    pub originator_reference: String,
+    /// Date the recording was made, `yyyy-mm-dd`.
+    #[pyo3(get)]
+    pub origination_date: String,
+    /// Time of day the recording was made, `hh:mm:ss`.
+    #[pyo3(get)]
+    pub origination_time: String,
+    /// Frames from midnight to the first frame of the recording.
+    #[pyo3(get)]
+    pub time_reference: u64,
+    /// `time_reference` in sec.
+    #[pyo3(get)]
+    pub time_reference_sec: f64,
+    #[pyo3(get)]
+    pub version: u16,
+    #[pyo3(get)]
+    pub coding_history: String,
+}
+
+#[pymethods]
+impl Bext {
+    fn __repr__(&self) -> String {
+        format!(
+            "Bext(origination_date='{}', origination_time='{}', time_reference={})",
+            self.origination_date, self.origination_time, self.time_reference
+        )
+    }
+}
+
+/// Marker of a `cue ` chunk with the texts the `LIST` `adtl` chunk attaches to it.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct CuePoint {
+    #[pyo3(get)]
+    pub id: u32,
+    /// Frame the marker points at.
+    #[pyo3(get)]
+    pub position: u64,
+    #[pyo3(get)]
+    pub position_sec: f64,
+    /// Text of the `labl` chunk.
+    #[pyo3(get)]
+    pub label: Option<String>,
+    /// Text of the `note` chunk.
+    #[pyo3(get)]
+    pub note: Option<String>,
+    /// Frames covered by the marker according to its `ltxt` chunk, which makes it a region.
+    #[pyo3(get)]
+    pub length: Option<u64>,
+}
+
+#[pymethods]
+impl CuePoint {
+    fn __repr__(&self) -> String {
+        format!(
+            "CuePoint(id={}, position={}, label={:?}, length={:?})",
+            self.id, self.position, self.label, self.length
+        )
+    }
+}
+
+/// Metadata chunks met while walking a WAV file. Parsing is lenient: a chunk too short
+/// for its fields is ignored and texts are decoded as UTF-8, replacing invalid bytes.
+#[derive(Default)]
+pub struct WavMetadata {
+    bext: Option<Bext>,
+    /// ID and frame of each cue point, in the order of the `cue ` chunk.
+    cues: Vec<(u32, u64)>,
+    labels: HashMap<u32, String>,
+    notes: HashMap<u32, String>,
+    lengths: HashMap<u32, u64>,
+}
+
+impl WavMetadata {
+    /// Whether the chunk `id` is read by `read_chunk`.
+    pub fn is_metadata(id: &[u8; 4]) -> bool {
+        matches!(id, b"bext" | b"cue " | b"LIST")
+    }
+
+    pub fn read_chunk(&mut self, id: &[u8; 4], body: &[u8]) {
+        match id {
+            b"bext" => self.bext = read_bext(body).or(self.bext.take()),
+            b"cue " => self.read_cue(body),
+            b"LIST" if body.starts_with(b"adtl") => self.read_adtl(&body[4..]),
+            _ => {}
+        }
+    }
+
+    fn read_cue(&mut self, body: &[u8]) {
+        let Some(count) = body.get(0..4).map(|count| u32_at(count, 0) as usize) else {
+            return;
+        };
+        self.cues = body[4..]
+            .chunks_exact(CUE_POINT_SIZE)
+            .take(count)
+            // dwName, then dwSampleOffset of the point in the data chunk.
+            .map(|point| (u32_at(point, 0), u32_at(point, 20) as u64))
+            .collect();
+    }
+
+    fn read_adtl(&mut self, mut body: &[u8]) {
+        while body.len() >= 12 {
+            let size = u32_at(body, 4) as usize;
+            let Some(sub) = body.get(8..8 + size) else {
+                break;
+            };
+            match &body[0..4] {
+                b"labl" if size >= 4 => {
+                    self.labels.insert(u32_at(sub, 0), text(&sub[4..]));
+                }
+                b"note" if size >= 4 => {
+                    self.notes.insert(u32_at(sub, 0), text(&sub[4..]));
+                }
+                b"ltxt" if size >= 8 => {
+                    self.lengths.insert(u32_at(sub, 0), u32_at(sub, 4) as u64);
+                }
+                _ => {}
+            }
+            body = body.get(8 + size + (size & 1)..).unwrap_or_default();
+        }
+    }
+
+    /// Put the metadata in `info`, with times in sec by its sample rate.
+    pub fn fill_info(&self, info: &mut WavInfo) {
+        let sample_rate = info.sample_rate as f64;
+        info.bext = self.bext.clone().map(|mut bext| {
+            bext.time_reference_sec = bext.time_reference as f64 / sample_rate;
+            bext
+        });
+        info.cue_points = self
+            .cues
+            .iter()
+            .map(|&(id, position)| CuePoint {
+                id,
+                position,
+                position_sec: position as f64 / sample_rate,
+                label: self.labels.get(&id).cloned(),
+                note: self.notes.get(&id).cloned(),
+                length: self.lengths.get(&id).copied(),
+            })
+            .collect();
+    }
+}
+
+fn read_bext(body: &[u8]) -> Option<Bext> {
+    if body.len() < BEXT_MIN_SIZE {
+        return None;
+    }
+    Some(Bext {
+        description: text(&body[0..256]),
+        originator: text(&body[256..288]),
+        originator_reference: text(&body[288..320]),
+        origination_date: text(&body[320..330]),
+        origination_time: text(&body[330..338]),
+        time_reference: u32_at(body, 338) as u64 | (u32_at(body, 342) as u64) << 32,
+        time_reference_sec: 0.0,
+        version: u16::from_le_bytes([body[346], body[347]]),
+        // Coding history follows the UMID, loudness fields and reserved bytes.
+        coding_history: body.get(602..).map(text).unwrap_or_default(),
+    })
+}
+
+fn u32_at(bytes: &[u8], i: usize) -> u32 {
+    u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]])
+}
+
+/// Text of a fixed size or NUL terminated field.
+fn text(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end])
+        .trim_end()
+        .to_string()
+}
@@ -0,0 +1,189 @@
+use std::fmt;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use serde::de::{self, Deserialize, Deserializer, Unexpected, Visitor};
+
+use crate::channels::ChannelMode;
+use crate::envelope::{EnvelopeAnalyzer, EnvelopeKind};
+use crate::wav::Analyzer;
+
+/// Lowest level in the histogram, in dBFS. Quieter windows fall into the first bin.
+const HISTOGRAM_MIN_DBFS: f64 = -150.0;
+/// Share of windows a histogram peak needs to count as the noise floor.
+const MIN_PEAK_RATIO: f64 = 0.005;
+
+/// Silence threshold given to the detector.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SilenceThreshold {
+    /// Only digital zero is silent.
+    DigitalZero,
+    Dbfs(f64),
+    /// Estimated from the noise floor of the file.
+    Auto,
+}
+
+impl<'source> FromPyObject<'source> for SilenceThreshold {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        if ob.is_none() {
+            return Ok(SilenceThreshold::DigitalZero);
+        }
+        if let Ok(dbfs) = ob.extract::<f64>() {
+            return Ok(SilenceThreshold::Dbfs(dbfs));
+        }
+        match ob.extract::<&str>() {
+            Ok("auto") => Ok(SilenceThreshold::Auto),
+            _ => Err(PyValueError::new_err(format!(
+                "Silence threshold must be None, a level in dBFS or 'auto': {}.",
+                ob
+            ))),
+        }
+    }
+}
+
+impl IntoPy<PyObject> for SilenceThreshold {
+    fn into_py(self, py: Python) -> PyObject {
+        match self {
+            SilenceThreshold::DigitalZero => py.None(),
+            SilenceThreshold::Dbfs(dbfs) => dbfs.into_py(py),
+            SilenceThreshold::Auto => "auto".into_py(py),
+        }
+    }
+}
+
+/// Same forms as from Python, `null` for digital zero, for program property files.
+impl<'de> Deserialize<'de> for SilenceThreshold {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ThresholdVisitor;
+
+        impl Visitor<'_> for ThresholdVisitor {
+            type Value = SilenceThreshold;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("null, a level in dBFS or 'auto'")
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<SilenceThreshold, E> {
+                Ok(SilenceThreshold::DigitalZero)
+            }
+
+            fn visit_f64<E: de::Error>(self, dbfs: f64) -> Result<SilenceThreshold, E> {
+                Ok(SilenceThreshold::Dbfs(dbfs))
+            }
+
+            fn visit_i64<E: de::Error>(self, dbfs: i64) -> Result<SilenceThreshold, E> {
+                Ok(SilenceThreshold::Dbfs(dbfs as f64))
+            }
+
+            fn visit_u64<E: de::Error>(self, dbfs: u64) -> Result<SilenceThreshold, E> {
+                Ok(SilenceThreshold::Dbfs(dbfs as f64))
+            }
+
+            fn visit_str<E: de::Error>(self, text: &str) -> Result<SilenceThreshold, E> {
+                match text {
+                    "auto" => Ok(SilenceThreshold::Auto),
+                    _ => Err(E::invalid_value(Unexpected::Str(text), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(ThresholdVisitor)
+    }
+}
+
+/// Builds a 1 dB histogram of the window peak levels of a recording.
+/// Window peaks are used since the detector compares the magnitude of each frame.
+pub struct NoiseFloorEstimator {
+    envelope: EnvelopeAnalyzer,
+    zero_windows: u64,
+    histogram: Vec<u64>,
+}
+
+impl NoiseFloorEstimator {
+    /// Interleaved samples are estimated on the loudest channel of each frame.
+    pub fn new(mode: ChannelMode, window_frames: usize) -> Self {
+        let mode = match mode {
+            ChannelMode::Interleaved => ChannelMode::All,
+            mode => mode,
+        };
+        NoiseFloorEstimator {
+            envelope: EnvelopeAnalyzer::new(EnvelopeKind::Peak, mode, window_frames, window_frames),
+            zero_windows: 0,
+            histogram: vec![0; -HISTOGRAM_MIN_DBFS as usize + 1],
+        }
+    }
+
+    fn drain(&mut self) {
+        let last_bin = self.histogram.len() - 1;
+        for peak in self.envelope.values.drain(..) {
+            if peak == 0.0 {
+                self.zero_windows += 1;
+                continue;
+            }
+            let dbfs = 20.0 * (peak as f64).log10();
+            let bin = (dbfs - HISTOGRAM_MIN_DBFS).floor().max(0.0) as usize;
+            self.histogram[bin.min(last_bin)] += 1;
+        }
+    }
+
+    /// Pick the noise floor as the quietest prominent peak of the histogram
+    /// and set the threshold `margin_db` above it.
+    /// Recordings with digital silence keep exact zero as their threshold.
+    pub fn estimate(&self, margin_db: f64) -> NoiseFloor {
+        let windows = self.zero_windows + self.histogram.iter().sum::<u64>();
+        let min_count = ((windows as f64 * MIN_PEAK_RATIO).ceil() as u64).max(1);
+        let mut noise_floor = NoiseFloor {
+            noise_floor_dbfs: None,
+            threshold_dbfs: None,
+            digital_silence_ratio: self.zero_windows as f64 / windows.max(1) as f64,
+        };
+        if self.zero_windows >= min_count {
+            return noise_floor;
+        }
+        // Peaks are searched on counts summed with the neighbouring bins, so a floor
+        // straddling two bins is not missed, then placed on the fullest of the three.
+        let neighbours = |i: usize| i.saturating_sub(1)..(i + 2).min(self.histogram.len());
+        let smoothed: Vec<u64> = (0..self.histogram.len())
+            .map(|i| self.histogram[neighbours(i)].iter().sum())
+            .collect();
+        let peak = (0..smoothed.len()).find(|&i| {
+            smoothed[i] >= min_count
+                && (i == 0 || smoothed[i] >= smoothed[i - 1])
+                && (i + 1 == smoothed.len() || smoothed[i] >= smoothed[i + 1])
+        });
+        if let Some(peak) = peak {
+            let bin = neighbours(peak).max_by_key(|&i| self.histogram[i]).unwrap();
+            let dbfs = HISTOGRAM_MIN_DBFS + bin as f64 + 0.5;
+            noise_floor.noise_floor_dbfs = Some(dbfs);
+            noise_floor.threshold_dbfs = Some(dbfs + margin_db);
+        }
+        noise_floor
+    }
+}
+
+impl Analyzer for NoiseFloorEstimator {
+    fn process(&mut self, samples: &[f32], channels: usize) {
+        self.envelope.process(samples, channels);
+        self.drain();
+    }
+
+    fn finish(&mut self) {
+        self.envelope.finish();
+        self.drain();
+    }
+}
+
+/// Noise floor of a recording and the silence threshold chosen from it.
+#[pyclass]
+#[derive(Clone)]
+pub struct NoiseFloor {
+    /// Level of the quietest prominent histogram peak, None with digital silence.
+    #[pyo3(get)]
+    pub noise_floor_dbfs: Option<f64>,
+    /// Chosen silence threshold, None when only digital zero is silent.
+    #[pyo3(get)]
+    pub threshold_dbfs: Option<f64>,
+    /// Share of windows which are digital zero.
+    #[pyo3(get)]
+    pub digital_silence_ratio: f64,
+}
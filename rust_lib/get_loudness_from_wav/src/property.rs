@@ -0,0 +1,508 @@
+use std::fmt;
+use std::fs;
+use std::io;
+
+use pyo3::exceptions::{PyKeyError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyFloat, PyLong, PyString};
+use pyo3::PyTypeInfo;
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use serde::Deserialize;
+
+use crate::channels::ChannelMode;
+use crate::duration_units::DurationSecUnits;
+use crate::noise_floor::SilenceThreshold;
+use crate::ts::AudioStreamSelector;
+
+/// Which side of the CM a program scene of a CM structure is on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScenePosition {
+    /// The scene ends where the CM starts.
+    Before,
+    /// The scene starts where the CM ends.
+    After,
+}
+
+/// CMs of a nominal CM structure, with a program scene too short to be told apart from them.
+///
+/// Given as a map of durations in sec: `"cm"` for CMs divided by silences, `"monolithic_cm"`
+/// for a CM without silences inside, and `"scene_before"` or `"scene_after"` for the scene.
+/// `"scene"` is a scene before the CM when it is the first key and after it otherwise,
+/// as in files written for `NominalCMStructure` of lib/cmcut.py.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CMComposition {
+    pub cm: Option<f64>,
+    pub monolithic_cm: Option<f64>,
+    pub scene: Option<(f64, ScenePosition)>,
+}
+
+/// Why a CM composition is rejected, raised from Python as the exception of the same name.
+pub enum CompositionError {
+    Key(String),
+    Value(String),
+}
+
+impl fmt::Display for CompositionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompositionError::Key(message) | CompositionError::Value(message) => {
+                f.write_str(message)
+            }
+        }
+    }
+}
+
+impl From<CompositionError> for PyErr {
+    fn from(error: CompositionError) -> Self {
+        match error {
+            CompositionError::Key(message) => PyKeyError::new_err(message),
+            CompositionError::Value(message) => PyValueError::new_err(message),
+        }
+    }
+}
+
+fn positive_sec(value: f64) -> Result<f64, String> {
+    if value.is_nan() || value <= 0.0 {
+        return Err(format!("Value must be positive value: {}.", value));
+    }
+    Ok(value)
+}
+
+impl CMComposition {
+    /// Composition of durations in the order they are given.
+    pub fn from_entries(entries: &[(String, f64)]) -> Result<Self, CompositionError> {
+        if entries.is_empty() {
+            return Err(CompositionError::Value("CM Structure is empty.".into()));
+        }
+        if entries.len() > 2 {
+            return Err(CompositionError::Value(format!(
+                "CM Structure length(must be less than 3) is too long: {}",
+                entries.len()
+            )));
+        }
+        let mut composition = CMComposition {
+            cm: None,
+            monolithic_cm: None,
+            scene: None,
+        };
+        for (index, (key, value)) in entries.iter().enumerate() {
+            let value = positive_sec(*value).map_err(CompositionError::Value)?;
+            let position = match key.as_str() {
+                "cm" | "monolithic_cm" => None,
+                "scene" if index == 0 => Some(ScenePosition::Before),
+                "scene_before" => Some(ScenePosition::Before),
+                "scene" | "scene_after" => Some(ScenePosition::After),
+                _ => {
+                    return Err(CompositionError::Key(format!(
+                        "Key must be 'cm', 'monolithic_cm', 'scene', 'scene_before' or 'scene_after': {}.",
+                        key
+                    )))
+                }
+            };
+            let replaced = match position {
+                None if key == "cm" => composition.cm.replace(value).is_some(),
+                None => composition.monolithic_cm.replace(value).is_some(),
+                Some(position) => composition.scene.replace((value, position)).is_some(),
+            };
+            if replaced {
+                return Err(CompositionError::Value(format!(
+                    "CM Structure has more than one of the key: {}.",
+                    key
+                )));
+            }
+        }
+        if composition.cm.is_none() && composition.monolithic_cm.is_none() {
+            return Err(CompositionError::Value(
+                "CM Structure must include 'cm' key.".into(),
+            ));
+        }
+        Ok(composition)
+    }
+
+    /// Sum of durations in the structure.
+    pub fn nominal_duration(&self) -> f64 {
+        self.cm.unwrap_or(0.0)
+            + self.monolithic_cm.unwrap_or(0.0)
+            + self.scene.map_or(0.0, |(scene, _)| scene)
+    }
+
+    /// CM section between two CM dividers, leaving out the scene.
+    pub fn actual_cm_section(&self, last_end_sec: f64, next_start_sec: f64) -> (f64, f64) {
+        match self.scene {
+            Some((scene, ScenePosition::Before)) => (last_end_sec + scene, next_start_sec),
+            Some((scene, ScenePosition::After)) => (last_end_sec, next_start_sec - scene),
+            None => (last_end_sec, next_start_sec),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CMComposition {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CompositionVisitor;
+
+        impl<'de> Visitor<'de> for CompositionVisitor {
+            type Value = CMComposition;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of durations of CMs and a scene in sec")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<CMComposition, A::Error> {
+                // Entries are read in the order of the file, which places a plain "scene".
+                let mut entries = Vec::new();
+                while let Some((key, value)) = map.next_entry::<String, f64>()? {
+                    entries.push((key, value));
+                }
+                CMComposition::from_entries(&entries).map_err(de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_map(CompositionVisitor)
+    }
+}
+
+/// Nominal CM structure, which could consist of both actual CMs and an indistinguishable
+/// program scene, see `CMComposition`, with the fluctuation of durations it is matched with.
+#[pyclass(module = "get_loudness_from_wav")]
+#[derive(Clone, Debug)]
+pub struct NominalCMStructure {
+    pub composition: CMComposition,
+    #[pyo3(get)]
+    pub margin_sec: f64,
+}
+
+/// Duration given from Python, an `int` or a `float` itself.
+fn sec_value(ob: &PyAny, what: &str) -> PyResult<f64> {
+    let py = ob.py();
+    let ty = ob.get_type();
+    if !ty.is(PyLong::type_object(py)) && !ty.is(PyFloat::type_object(py)) {
+        return Err(PyTypeError::new_err(format!(
+            "Type of {} must be either integer or float: {}.",
+            what, ty
+        )));
+    }
+    ob.extract()
+}
+
+#[pymethods]
+impl NominalCMStructure {
+    /// Structure of `cm_structure`, a dict such as `{"scene": 30, "cm": 60}`.
+    #[new]
+    fn py_new(cm_structure: &PyAny, margin_sec: &PyAny) -> PyResult<Self> {
+        let dict = cm_structure.downcast::<PyDict>().map_err(|_| {
+            PyTypeError::new_err(format!(
+                "Unexpected type for NominalCMStructure: {}.",
+                cm_structure.get_type()
+            ))
+        })?;
+        let entries = dict
+            .iter()
+            .map(|(key, value)| {
+                let key = key.downcast::<PyString>()?.to_str()?.to_string();
+                Ok((key, sec_value(value, "value")?))
+            })
+            .collect::<PyResult<Vec<(String, f64)>>>()?;
+        let composition = CMComposition::from_entries(&entries)?;
+        let margin_sec = sec_value(margin_sec, "margin sec")?;
+        if margin_sec.is_nan() || margin_sec <= 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "margin_sec must be positive value: {}.",
+                margin_sec
+            )));
+        }
+        Ok(NominalCMStructure {
+            composition,
+            margin_sec,
+        })
+    }
+
+    /// Durations in sec keyed by "scene", "cm" and "monolithic_cm", the scene first if
+    /// it is before the CM.
+    #[getter]
+    fn composition<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        let composition = &self.composition;
+        if let Some((scene, ScenePosition::Before)) = composition.scene {
+            dict.set_item("scene", scene)?;
+        }
+        if let Some(cm) = composition.cm {
+            dict.set_item("cm", cm)?;
+        }
+        if let Some(monolithic_cm) = composition.monolithic_cm {
+            dict.set_item("monolithic_cm", monolithic_cm)?;
+        }
+        if let Some((scene, ScenePosition::After)) = composition.scene {
+            dict.set_item("scene", scene)?;
+        }
+        Ok(dict)
+    }
+
+    #[getter]
+    fn cm(&self) -> Option<f64> {
+        self.composition.cm
+    }
+
+    #[getter]
+    fn monolithic_cm(&self) -> Option<f64> {
+        self.composition.monolithic_cm
+    }
+
+    #[getter]
+    fn scene(&self) -> Option<f64> {
+        self.composition.scene.map(|(scene, _)| scene)
+    }
+
+    /// "before" or "after" the CM, None without a scene.
+    #[getter]
+    fn scene_position(&self) -> Option<&'static str> {
+        self.composition.scene.map(|(_, position)| match position {
+            ScenePosition::Before => "before",
+            ScenePosition::After => "after",
+        })
+    }
+
+    /// Sum of durations in the structure.
+    #[getter]
+    fn nominal_duration(&self) -> f64 {
+        self.composition.nominal_duration()
+    }
+
+    /// Get actual CM section removing a TV program scene based on the structure.
+    fn get_actual_cm_section(
+        &self,
+        last_cm_divider_end_sec: f64,
+        next_cm_divider_start_sec: f64,
+    ) -> (f64, f64) {
+        self.composition
+            .actual_cm_section(last_cm_divider_end_sec, next_cm_divider_start_sec)
+    }
+
+    fn __repr__(&self) -> String {
+        let composition = &self.composition;
+        let mut entries = Vec::new();
+        if let Some((scene, ScenePosition::Before)) = composition.scene {
+            entries.push(format!("'scene_before': {}", scene));
+        }
+        if let Some(cm) = composition.cm {
+            entries.push(format!("'cm': {}", cm));
+        }
+        if let Some(monolithic_cm) = composition.monolithic_cm {
+            entries.push(format!("'monolithic_cm': {}", monolithic_cm));
+        }
+        if let Some((scene, ScenePosition::After)) = composition.scene {
+            entries.push(format!("'scene_after': {}", scene));
+        }
+        format!(
+            "NominalCMStructure({{{}}}, {})",
+            entries.join(", "),
+            self.margin_sec
+        )
+    }
+}
+
+/// Duration in sec that must be positive.
+#[derive(Clone, Copy, Debug)]
+struct PositiveSec(f64);
+
+impl<'de> Deserialize<'de> for PositiveSec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = f64::deserialize(deserializer)?;
+        positive_sec(value)
+            .map(PositiveSec)
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Property of a TV program guiding CM detection, read from a JSON file such as sample.json.
+///
+/// Every key is optional and falls back to the defaults cmcut_direct.py has used: three
+/// 60 sec CMs, a 15 sec end scene, `margin_sec` 3.5 and `duration_threshold` 4000 frames.
+/// `duration_threshold_sec` replaces `duration_threshold` when given. Unknown keys are
+/// ignored. Errors name the JSON path of the offending value, e.g. `cm_structures[1].cm`.
+#[pyclass(module = "get_loudness_from_wav")]
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ProgramProperty {
+    cm_structures: Vec<CMComposition>,
+    #[pyo3(get)]
+    #[serde(rename = "end_scene_duration")]
+    pub end_scene_duration_sec: f64,
+    #[pyo3(get)]
+    pub has_monolithic_cm: bool,
+    margin_sec: PositiveSec,
+    #[serde(rename = "duration_threshold")]
+    duration_frame_threshold: u64,
+    #[pyo3(get)]
+    pub duration_threshold_sec: Option<f64>,
+    additional_duration_units: Vec<PositiveSec>,
+    #[pyo3(get)]
+    pub channel_mode: ChannelMode,
+    #[pyo3(get)]
+    pub silence_threshold_dbfs: SilenceThreshold,
+    #[pyo3(get)]
+    pub silence_exit_threshold_dbfs: Option<f64>,
+    #[pyo3(get)]
+    pub silence_max_blip_frame: u64,
+    #[pyo3(get)]
+    pub silence_max_blip_sec: Option<f64>,
+    #[pyo3(get)]
+    pub audio_stream: AudioStreamSelector,
+    #[pyo3(get)]
+    pub compare_audio_streams: bool,
+    #[pyo3(get)]
+    pub cue_wav_path: Option<String>,
+    #[pyo3(get)]
+    pub lenient: bool,
+    #[pyo3(get)]
+    pub analysis_rate: Option<u32>,
+}
+
+impl Default for ProgramProperty {
+    fn default() -> Self {
+        let cm = CMComposition {
+            cm: Some(60.0),
+            monolithic_cm: None,
+            scene: None,
+        };
+        ProgramProperty {
+            cm_structures: vec![cm; 3],
+            end_scene_duration_sec: 15.0,
+            has_monolithic_cm: false,
+            margin_sec: PositiveSec(3.5),
+            duration_frame_threshold: 4000,
+            duration_threshold_sec: None,
+            additional_duration_units: Vec::new(),
+            channel_mode: ChannelMode::All,
+            silence_threshold_dbfs: SilenceThreshold::DigitalZero,
+            silence_exit_threshold_dbfs: None,
+            silence_max_blip_frame: 0,
+            silence_max_blip_sec: None,
+            audio_stream: AudioStreamSelector::default(),
+            compare_audio_streams: false,
+            cue_wav_path: None,
+            lenient: false,
+            analysis_rate: None,
+        }
+    }
+}
+
+impl ProgramProperty {
+    pub fn from_json(json: &str) -> PyResult<Self> {
+        let mut deserializer = serde_json::Deserializer::from_str(json);
+        let property: ProgramProperty = serde_path_to_error::deserialize(&mut deserializer)
+            .map_err(|e| {
+                let path = e.path().to_string();
+                // "." is the whole file and "?" somewhere serde could not follow, such as a syntax error.
+                let message = if path == "." || path == "?" {
+                    format!("Invalid program property: {}", e.inner())
+                } else {
+                    format!("Invalid program property at {}: {}", path, e.inner())
+                };
+                PyValueError::new_err(message)
+            })?;
+        deserializer
+            .end()
+            .map_err(|e| PyValueError::new_err(format!("Invalid program property: {}", e)))?;
+        Ok(property)
+    }
+
+    pub fn margin_sec(&self) -> f64 {
+        self.margin_sec.0
+    }
+
+    /// CM structures with the margin of the property.
+    pub fn structures(&self) -> Vec<NominalCMStructure> {
+        self.cm_structures
+            .iter()
+            .map(|composition| NominalCMStructure {
+                composition: composition.clone(),
+                margin_sec: self.margin_sec(),
+            })
+            .collect()
+    }
+
+    /// Duration threshold in frames, which `duration_threshold_sec` replaces when given.
+    pub fn duration_frame_threshold(&self) -> u64 {
+        self.duration_frame_threshold
+    }
+
+    pub fn units(&self) -> DurationSecUnits {
+        let additional: Vec<f64> = self
+            .additional_duration_units
+            .iter()
+            .map(|unit| unit.0)
+            .collect();
+        DurationSecUnits::with_defaults(&additional)
+    }
+}
+
+#[pymethods]
+impl ProgramProperty {
+    /// Property with every default.
+    #[new]
+    fn py_new() -> Self {
+        ProgramProperty::default()
+    }
+
+    /// Parse a property from JSON text.
+    #[staticmethod]
+    #[pyo3(name = "from_json")]
+    fn py_from_json(json: &str) -> PyResult<Self> {
+        ProgramProperty::from_json(json)
+    }
+
+    /// Read a property from a JSON file.
+    #[staticmethod]
+    fn load(path: &str) -> PyResult<Self> {
+        let json = fs::read_to_string(path).map_err(|e| {
+            PyErr::from(io::Error::new(
+                e.kind(),
+                format!("Failed to read {}: {}", path, e),
+            ))
+        })?;
+        ProgramProperty::from_json(&json)
+    }
+
+    /// Nominal CM structures in order, each with `margin_sec`.
+    #[getter]
+    fn cm_structures(&self) -> Vec<NominalCMStructure> {
+        self.structures()
+    }
+
+    #[getter(margin_sec)]
+    fn py_margin_sec(&self) -> f64 {
+        self.margin_sec()
+    }
+
+    /// Duration threshold in frames, None when `duration_threshold_sec` replaces it.
+    #[getter]
+    fn duration_threshold(&self) -> Option<u64> {
+        match self.duration_threshold_sec {
+            Some(_) => None,
+            None => Some(self.duration_frame_threshold),
+        }
+    }
+
+    #[getter]
+    fn additional_duration_units(&self) -> Vec<f64> {
+        self.additional_duration_units
+            .iter()
+            .map(|unit| unit.0)
+            .collect()
+    }
+
+    /// The 15 and 30 sec units with `additional_duration_units`.
+    #[getter]
+    fn duration_sec_units(&self) -> DurationSecUnits {
+        self.units()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "ProgramProperty(cm_structures={}, margin_sec={}, end_scene_duration={})",
+            self.cm_structures.len(),
+            self.margin_sec(),
+            self.end_scene_duration_sec
+        )
+    }
+}
@@ -0,0 +1,190 @@
+use std::f64::consts::PI;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::wav::Analyzer;
+
+/// Zero crossings of the sinc on each side of an output frame, at the output rate.
+const ZERO_CROSSINGS: f64 = 16.0;
+/// Cutoff as a fraction of the output Nyquist frequency, leaving room for the transition band.
+const ROLL_OFF: f64 = 0.9;
+
+/// Check `analysis_rate` against the sample rate and give the rate frames are analysed at,
+/// the sample rate itself by default.
+pub fn analysis_rate(analysis_rate: Option<u32>, sample_rate: u32) -> PyResult<u32> {
+    match analysis_rate {
+        None => Ok(sample_rate),
+        Some(rate) if rate == 0 || rate > sample_rate => Err(PyValueError::new_err(format!(
+            "analysis_rate must be positive and at most the sample rate: {} {}.",
+            rate, sample_rate
+        ))),
+        Some(rate) => Ok(rate),
+    }
+}
+
+/// Lowers the rate of interleaved samples by any ratio with a windowed sinc filter,
+/// which also removes what lies above the new Nyquist frequency so that it does not alias.
+///
+/// Output frame `k` is centred on input frame `k * sample_rate / analysis_rate`. The ratio is
+/// reduced to `step_in / step_out`, so the centres fall on `step_out` fractional phases whose
+/// taps are computed once. Each set of taps sums to 1, keeping the level of steady signals.
+pub struct Decimator {
+    channels: usize,
+    step_in: u64,
+    step_out: u64,
+    /// Input frames on each side of a centre that the taps cover.
+    half_width: usize,
+    /// Taps of each phase, `2 * half_width` of them.
+    taps: Vec<Vec<f32>>,
+    /// Interleaved input frames, starting `half_width` frames of zeros before the first one.
+    history: Vec<f32>,
+    /// Index of the first frame of `history` counting those zeros.
+    history_start: u64,
+    /// Integer part and phase of the centre of the next output frame, in input frames.
+    next_in: u64,
+    phase: u64,
+    frames_in: u64,
+}
+
+impl Decimator {
+    pub fn new(channels: usize, sample_rate: u32, analysis_rate: u32) -> Self {
+        let divisor = gcd(sample_rate as u64, analysis_rate as u64);
+        let (step_in, step_out) = (sample_rate as u64 / divisor, analysis_rate as u64 / divisor);
+        let ratio = step_in as f64 / step_out as f64;
+        // Cutoff in cycles per input frame.
+        let cutoff = 0.5 / ratio * ROLL_OFF;
+        let half_width = (ZERO_CROSSINGS * ratio).ceil() as usize;
+        let taps = (0..step_out)
+            .map(|phase| {
+                let fraction = phase as f64 / step_out as f64;
+                let taps: Vec<f64> = (0..2 * half_width)
+                    .map(|i| {
+                        let distance = i as f64 + 1.0 - half_width as f64 - fraction;
+                        windowed_sinc(distance, cutoff, half_width as f64)
+                    })
+                    .collect();
+                let sum: f64 = taps.iter().sum();
+                taps.iter().map(|tap| (tap / sum) as f32).collect()
+            })
+            .collect();
+        Decimator {
+            channels,
+            step_in,
+            step_out,
+            half_width,
+            taps,
+            history: vec![0.0; half_width * channels],
+            history_start: 0,
+            next_in: 0,
+            phase: 0,
+            frames_in: 0,
+        }
+    }
+
+    /// Decimate a block of interleaved samples, appending the output frames whose taps are
+    /// all available to `out`.
+    pub fn process(&mut self, samples: &[f32], out: &mut Vec<f32>) {
+        self.history.extend_from_slice(samples);
+        self.frames_in += (samples.len() / self.channels) as u64;
+        self.emit(out, false);
+    }
+
+    /// Output the frames centred before the end of the input, with zeros after it.
+    pub fn finish(&mut self, out: &mut Vec<f32>) {
+        let zeros = 2 * self.half_width * self.channels;
+        self.history.extend(std::iter::repeat_n(0.0, zeros));
+        self.emit(out, true);
+    }
+
+    fn emit(&mut self, out: &mut Vec<f32>, at_end: bool) {
+        let channels = self.channels;
+        let history_frames = (self.history.len() / channels) as u64;
+        loop {
+            if at_end && self.next_in >= self.frames_in {
+                break;
+            }
+            // The taps run up to the frame `half_width` after the centre, zeros included.
+            if self.next_in + 2 * self.half_width as u64 >= self.history_start + history_frames {
+                break;
+            }
+            let first = (self.next_in + 1 - self.history_start) as usize * channels;
+            let taps = &self.taps[self.phase as usize];
+            for channel in 0..channels {
+                let value = taps
+                    .iter()
+                    .zip(self.history[first + channel..].iter().step_by(channels))
+                    .map(|(tap, x)| tap * x)
+                    .sum();
+                out.push(value);
+            }
+            self.phase += self.step_in;
+            self.next_in += self.phase / self.step_out;
+            self.phase %= self.step_out;
+        }
+        let consumed = (self.next_in + 1).saturating_sub(self.history_start);
+        let consumed = consumed.min(history_frames);
+        self.history.drain(..consumed as usize * channels);
+        self.history_start += consumed;
+    }
+}
+
+/// Runs an analyzer on frames decimated to the analysis rate.
+pub struct Decimated<'a, A: Analyzer> {
+    decimator: Decimator,
+    inner: &'a mut A,
+    channels: usize,
+    out: Vec<f32>,
+}
+
+impl<'a, A: Analyzer> Decimated<'a, A> {
+    pub fn new(decimator: Decimator, inner: &'a mut A) -> Self {
+        let channels = decimator.channels;
+        Decimated {
+            decimator,
+            inner,
+            channels,
+            out: Vec::new(),
+        }
+    }
+}
+
+impl<A: Analyzer> Analyzer for Decimated<'_, A> {
+    fn process(&mut self, samples: &[f32], _channels: usize) {
+        self.out.clear();
+        self.decimator.process(samples, &mut self.out);
+        self.inner.process(&self.out, self.channels);
+    }
+
+    fn finish(&mut self) {
+        self.out.clear();
+        self.decimator.finish(&mut self.out);
+        self.inner.process(&self.out, self.channels);
+        self.inner.finish();
+    }
+}
+
+/// Low-pass impulse response at `distance` input frames, Blackman windowed over `half_width`.
+fn windowed_sinc(distance: f64, cutoff: f64, half_width: f64) -> f64 {
+    let x = 2.0 * cutoff * distance;
+    let sinc = if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    };
+    let u = distance / half_width;
+    let window = if u.abs() >= 1.0 {
+        0.0
+    } else {
+        0.42 + 0.5 * (PI * u).cos() + 0.08 * (2.0 * PI * u).cos()
+    };
+    2.0 * cutoff * sinc * window
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
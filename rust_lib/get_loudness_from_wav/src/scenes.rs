@@ -0,0 +1,218 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::duration_units::DurationSecUnits;
+use crate::property::{CMComposition, NominalCMStructure, ProgramProperty};
+use crate::section::SilentSection;
+
+/// Silences starting within this from the beginning start the first scene, as
+/// `ProgramScenes.starting_range_sec`.
+pub const STARTING_RANGE_SEC: f64 = 5.0;
+/// Added to the duration of the last scene, as `ProgramScenes.end_margin_sec`.
+pub const END_MARGIN_SEC: f64 = 1.0;
+/// Margin of the duration units when CM sections are searched without structures.
+const SEARCH_MARGIN_SEC: f64 = 1.0;
+/// Duration threshold of silences without a `ProgramProperty`, 5000 frames at 8000 fps
+/// as cmcut_default.py has used, which holds at any sample rate.
+pub const UNSTRUCTURED_DURATION_THRESHOLD_SEC: f64 = 0.625;
+
+/// Check that silent sections are in order of their ends, which dividers are searched by.
+pub fn check_sorted(sections: &[SilentSection]) -> PyResult<()> {
+    match sections
+        .windows(2)
+        .find(|pair| pair[1].end_sec() < pair[0].end_sec())
+    {
+        Some(pair) => Err(PyValueError::new_err(format!(
+            "Silent sections must be sorted: {} after {}.",
+            pair[1].end_sec(),
+            pair[0].end_sec()
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Whether `sections[index]` could divide CMs: a following section ends a duration unit,
+/// up to `margin_sec` longer, after its start. Same as `SilentSection.is_cm_divider_candidate`
+/// on the following sections, but as their ends are sorted, the first section ending past
+/// each unit is found by binary search instead of scanning.
+pub fn is_cm_divider_candidate(
+    sections: &[SilentSection],
+    index: usize,
+    durations_sec: &[f64],
+    margin_sec: f64,
+) -> bool {
+    let Some(&largest) = durations_sec.last() else {
+        return false;
+    };
+    let start_sec = sections[index].start_sec();
+    let following = &sections[index + 1..];
+    let duration_sec = |section: &SilentSection| section.end_sec() - start_sec;
+    durations_sec.iter().any(|unit| {
+        let first = following.partition_point(|section| duration_sec(section) <= *unit);
+        following.get(first).is_some_and(|section| {
+            let duration_sec = duration_sec(section);
+            // A scan stops at the first section this long, before checking it.
+            duration_sec <= unit + margin_sec && duration_sec < largest + margin_sec
+        })
+    })
+}
+
+/// CM sections between silent sections which follow `cm_structures` in order, as
+/// `ProgramScenes.construct_cm_sections` finds them.
+///
+/// Silences whose following silences end a duration unit after them are kept as dividers.
+/// Once the dividers since the first one span longer than the nominal duration of the
+/// current structure, and by less than its margin, the CMs between them make a section
+/// and the next structure is looked for. A `monolithic_cm` adds its duration to the units
+/// and needs a single divider. `margin_sec` is that of the first structure throughout.
+pub fn construct_cm_sections(
+    sections: &[SilentSection],
+    units: &DurationSecUnits,
+    cm_structures: &[NominalCMStructure],
+    has_monolithic_cm: bool,
+) -> Vec<(f64, f64)> {
+    let Some(first) = cm_structures.first() else {
+        return Vec::new();
+    };
+    let cm_num_threshold = if has_monolithic_cm { 0 } else { 1 };
+    let margin_sec = first.margin_sec;
+    let mut candidates: Vec<&SilentSection> = Vec::new();
+    let mut cm_sections = Vec::new();
+    let mut structure_reference = 0;
+    let units_of = |target: &CMComposition| match target.monolithic_cm {
+        Some(monolithic_cm) => units.with_duration(monolithic_cm),
+        None => units.clone(),
+    };
+    let mut target = &first.composition;
+    let mut target_units = units_of(target);
+    let mut nominal_cm_duration = target.nominal_duration();
+
+    for (index, section) in sections.iter().enumerate() {
+        if is_cm_divider_candidate(sections, index, target_units.durations_sec(), margin_sec) {
+            candidates.push(section);
+        }
+        let candidates_could_be_cm = match target.monolithic_cm {
+            Some(_) => candidates.len() == 1,
+            None => candidates.len() > cm_num_threshold,
+        };
+        if !candidates_could_be_cm {
+            continue;
+        }
+        let combined_duration_sec = section.end_sec() - candidates[0].start_sec();
+        if combined_duration_sec <= nominal_cm_duration {
+            continue;
+        }
+        if combined_duration_sec < nominal_cm_duration + margin_sec {
+            cm_sections
+                .push(target.actual_cm_section(candidates[0].end_sec(), section.start_sec()));
+            structure_reference += 1;
+            match cm_structures.get(structure_reference) {
+                Some(next) => {
+                    target = &next.composition;
+                    target_units = units_of(target);
+                    nominal_cm_duration = target.nominal_duration();
+                }
+                None => break,
+            }
+        }
+        candidates.drain(..candidates.len() - 1);
+    }
+    cm_sections
+}
+
+/// CM sections between series of silent sections dividing CMs, as
+/// `ProgramScenes.search_cm_sections` finds them without CM structures.
+///
+/// Dividers in a row, at least two of them, make a section from the end of the first to the
+/// start of the last. A silence which is not a divider ends the series.
+pub fn search_cm_sections(sections: &[SilentSection], units: &DurationSecUnits) -> Vec<(f64, f64)> {
+    let mut candidates: Vec<&SilentSection> = Vec::new();
+    let mut cm_sections = Vec::new();
+    let mut continuity = false;
+    let section_of = |candidates: &[&SilentSection]| {
+        (
+            candidates[0].end_sec(),
+            candidates[candidates.len() - 1].start_sec(),
+        )
+    };
+
+    for (index, section) in sections.iter().enumerate() {
+        if is_cm_divider_candidate(sections, index, units.durations_sec(), SEARCH_MARGIN_SEC) {
+            candidates.push(section);
+            continuity = true;
+        } else if continuity && candidates.len() >= 2 {
+            cm_sections.push(section_of(&candidates));
+            candidates.clear();
+            continuity = false;
+        }
+    }
+    if candidates.len() >= 2 {
+        cm_sections.push(section_of(&candidates));
+    }
+    cm_sections
+}
+
+/// Scene sections around `cm_sections`, as `ProgramScenes.generate_scenes` makes them.
+///
+/// The first scene starts at `first_start_sec_candidate` if it is within
+/// `starting_range_sec`, otherwise at 0. The last scene lasts `last_scene_duration` and
+/// `end_margin_sec` after the last CM, or up to `last_end_sec_candidate` if the duration is 0.
+pub fn generate_scenes(
+    first_start_sec_candidate: f64,
+    last_end_sec_candidate: f64,
+    cm_sections: &[(f64, f64)],
+    last_scene_duration: f64,
+    starting_range_sec: f64,
+    end_margin_sec: f64,
+) -> Vec<(f64, f64)> {
+    let mut scenes = Vec::with_capacity(cm_sections.len() + 1);
+    let mut start_sec = if first_start_sec_candidate < starting_range_sec {
+        first_start_sec_candidate
+    } else {
+        0.0
+    };
+    for &(cm_start_sec, cm_end_sec) in cm_sections {
+        scenes.push((start_sec, cm_start_sec));
+        start_sec = cm_end_sec;
+    }
+    if last_scene_duration != 0.0 {
+        scenes.push((start_sec, start_sec + last_scene_duration + end_margin_sec));
+    } else {
+        scenes.push((start_sec, last_end_sec_candidate));
+    }
+    scenes
+}
+
+/// Scene sections of a program divided by its silent sections, sorted and not empty,
+/// following `property` if given and searching CMs without structures otherwise.
+/// `starting_range_sec` and `end_margin_sec` are given to `generate_scenes`.
+pub fn program_scenes(
+    sections: &[SilentSection],
+    property: Option<&ProgramProperty>,
+    starting_range_sec: f64,
+    end_margin_sec: f64,
+) -> Vec<(f64, f64)> {
+    let (cm_sections, last_scene_duration) = match property {
+        Some(property) => (
+            construct_cm_sections(
+                sections,
+                &property.units(),
+                &property.structures(),
+                property.has_monolithic_cm,
+            ),
+            property.end_scene_duration_sec,
+        ),
+        None => (
+            search_cm_sections(sections, &DurationSecUnits::with_defaults(&[])),
+            0.0,
+        ),
+    };
+    generate_scenes(
+        sections[0].start_sec(),
+        sections[sections.len() - 1].end_sec(),
+        &cm_sections,
+        last_scene_duration,
+        starting_range_sec,
+        end_margin_sec,
+    )
+}
@@ -0,0 +1,211 @@
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use pyo3::basic::CompareOp;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyList, PyLong};
+use pyo3::PyTypeInfo;
+
+use crate::duration_units::DurationSecUnits;
+
+/// Silent section between two frame indices of the loudness, `end_frame_index` excluded.
+///
+/// The indices are kept as they are and converted to sec by `frame_per_sec` only when asked,
+/// so boundaries stay exact however long the recording is. Sections are equal when their
+/// indices and rate are, and are ordered by start then end in sec.
+#[pyclass(module = "get_loudness_from_wav")]
+#[derive(Clone, Debug)]
+pub struct SilentSection {
+    #[pyo3(get)]
+    pub start_frame_index: u64,
+    #[pyo3(get)]
+    pub end_frame_index: u64,
+    #[pyo3(get)]
+    pub frame_per_sec: f64,
+}
+
+impl SilentSection {
+    pub fn new(start_frame_index: u64, end_frame_index: u64, frame_per_sec: f64) -> Self {
+        SilentSection {
+            start_frame_index,
+            end_frame_index,
+            frame_per_sec,
+        }
+    }
+
+    pub fn start_sec(&self) -> f64 {
+        self.start_frame_index as f64 / self.frame_per_sec
+    }
+
+    pub fn end_sec(&self) -> f64 {
+        self.end_frame_index as f64 / self.frame_per_sec
+    }
+
+    fn cmp_key(&self, other: &Self) -> Ordering {
+        self.start_sec()
+            .total_cmp(&other.start_sec())
+            .then(self.end_sec().total_cmp(&other.end_sec()))
+            .then(self.frame_per_sec.total_cmp(&other.frame_per_sec))
+            .then(self.start_frame_index.cmp(&other.start_frame_index))
+            .then(self.end_frame_index.cmp(&other.end_frame_index))
+    }
+
+    /// Whether a section of `following` ends a duration unit, up to `margin_sec` longer,
+    /// after the start of this one. `durations_sec` is sorted, so following sections ending
+    /// past the largest unit stop the search.
+    pub fn is_cm_divider_candidate_of(
+        &self,
+        following: &[SilentSection],
+        durations_sec: &[f64],
+        margin_sec: f64,
+    ) -> bool {
+        let Some(&largest) = durations_sec.last() else {
+            return false;
+        };
+        let start_sec = self.start_sec();
+        for section in following {
+            let duration_sec = section.end_sec() - start_sec;
+            if largest + margin_sec <= duration_sec {
+                break;
+            }
+            if durations_sec
+                .iter()
+                .any(|unit| *unit < duration_sec && duration_sec <= unit + margin_sec)
+            {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Frame index given from Python, which must be an `int` itself as in `SilentSection`
+/// of lib/cmcut.py before it.
+fn frame_index(ob: &PyAny, name: &str) -> PyResult<i64> {
+    if !ob.get_type().is(PyLong::type_object(ob.py())) {
+        return Err(PyTypeError::new_err(format!(
+            "Type of {} must be int: {}.",
+            name,
+            ob.get_type()
+        )));
+    }
+    ob.extract()
+}
+
+#[pymethods]
+impl SilentSection {
+    #[new]
+    #[args(frame_per_sec = "8000.0")]
+    fn py_new(
+        start_frame_index: &PyAny,
+        end_frame_index: &PyAny,
+        frame_per_sec: f64,
+    ) -> PyResult<Self> {
+        let start = frame_index(start_frame_index, "start_frame_index")?;
+        if start < 0 {
+            return Err(PyValueError::new_err(format!(
+                "start_frame_index must be non-negative: {}.",
+                start
+            )));
+        }
+        let end = frame_index(end_frame_index, "end_frame_index")?;
+        if end <= 0 {
+            return Err(PyValueError::new_err(format!(
+                "end_frame_index must be positive: {}.",
+                end
+            )));
+        }
+        if start >= end {
+            return Err(PyValueError::new_err(format!(
+                "start_frame_index must be less than end_frame_index: {} {}.",
+                start, end
+            )));
+        }
+        if frame_per_sec.is_nan() || frame_per_sec <= 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "frame_per_sec must be positive: {}.",
+                frame_per_sec
+            )));
+        }
+        Ok(SilentSection::new(start as u64, end as u64, frame_per_sec))
+    }
+
+    /// Start timing of the section in sec.
+    #[getter(start_sec)]
+    fn py_start_sec(&self) -> f64 {
+        self.start_sec()
+    }
+
+    /// End timing of the section in sec.
+    #[getter(end_sec)]
+    fn py_end_sec(&self) -> f64 {
+        self.end_sec()
+    }
+
+    /// Duration of the section.
+    fn duration_sec(&self) -> f64 {
+        self.end_sec() - self.start_sec()
+    }
+
+    /// Judge if the section is candidate of CM divider: a section of `following_sections`
+    /// ends one of the units of `duration_sec_units` after its start, up to `margin_sec` longer.
+    fn is_cm_divider_candidate(
+        &self,
+        following_sections: Vec<SilentSection>,
+        duration_sec_units: PyRef<DurationSecUnits>,
+        margin_sec: f64,
+    ) -> bool {
+        self.is_cm_divider_candidate_of(
+            &following_sections,
+            duration_sec_units.durations_sec(),
+            margin_sec,
+        )
+    }
+
+    fn __richcmp__(&self, other: &PyAny, op: CompareOp) -> PyObject {
+        let py = other.py();
+        match other.extract::<PyRef<SilentSection>>() {
+            Ok(other) => op.matches(self.cmp_key(&other)).into_py(py),
+            Err(_) => py.NotImplemented(),
+        }
+    }
+
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.start_frame_index.hash(&mut hasher);
+        self.end_frame_index.hash(&mut hasher);
+        self.frame_per_sec.to_bits().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn __reduce__(slf: &PyCell<Self>) -> (PyObject, (u64, u64, f64)) {
+        let section = slf.borrow();
+        (
+            slf.get_type().into(),
+            (
+                section.start_frame_index,
+                section.end_frame_index,
+                section.frame_per_sec,
+            ),
+        )
+    }
+
+    /// `[start_sec, end_sec, frame_per_sec]`, as the sections have always been printed.
+    /// An integral `frame_per_sec` is printed as the int callers give.
+    fn __repr__(&self, py: Python) -> PyResult<String> {
+        let frame_per_sec =
+            if self.frame_per_sec.fract() == 0.0 && self.frame_per_sec <= i64::MAX as f64 {
+                (self.frame_per_sec as i64).into_py(py)
+            } else {
+                self.frame_per_sec.into_py(py)
+            };
+        let fields = [
+            self.start_sec().into_py(py),
+            self.end_sec().into_py(py),
+            frame_per_sec,
+        ];
+        PyList::new(py, fields).repr()?.extract()
+    }
+}
@@ -0,0 +1,152 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::channels::ChannelMode;
+use crate::info::WavInfo;
+use crate::wav::Analyzer;
+
+/// Convert a level in dBFS to a linear magnitude, full scale being 1.0.
+pub fn dbfs_to_linear(dbfs: f64) -> f32 {
+    10f64.powf(dbfs / 20.0) as f32
+}
+
+/// Convert a linear magnitude to a level in dBFS, -inf for digital zero.
+pub fn linear_to_dbfs(magnitude: f64) -> f64 {
+    20.0 * magnitude.log10()
+}
+
+/// Convert a duration in sec to a number of frames, rounding to the nearest one.
+pub fn sec_to_frame_count(sec: f64, frame_per_sec: f64, name: &str) -> PyResult<u64> {
+    if !sec.is_finite() || sec < 0.0 {
+        return Err(PyValueError::new_err(format!(
+            "{} must be non-negative: {}.",
+            name, sec
+        )));
+    }
+    Ok((sec * frame_per_sec).round() as u64)
+}
+
+/// Finds runs of silent loudness values while they stream by.
+///
+/// A silence starts at a value of at most `enter_threshold` and lasts while values stay at
+/// most `exit_threshold`, tolerating runs of up to `max_blip` louder values inside it.
+/// It is reported as `(start, end)` when a louder run ends it, `end` being the first value
+/// of that run, and it lasted longer than `min_duration` values. A silence still running at
+/// the end of the data is never reported.
+///
+/// With both thresholds at 0 and no blips this is `ProgramScenes.extract_silent_sections`.
+pub struct SilenceDetector {
+    enter_threshold: f32,
+    exit_threshold: f32,
+    min_duration: u64,
+    max_blip: u64,
+    index: u64,
+    in_silence: bool,
+    start: u64,
+    /// Number of loud values since `blip_start` while in a silence.
+    blip: u64,
+    blip_start: u64,
+    /// Number of values at most `enter_threshold`.
+    silent_values: u64,
+    pub sections: Vec<(u64, u64)>,
+}
+
+impl SilenceDetector {
+    pub fn new(
+        enter_threshold: f32,
+        exit_threshold: f32,
+        min_duration: u64,
+        max_blip: u64,
+    ) -> Self {
+        SilenceDetector {
+            enter_threshold,
+            exit_threshold: exit_threshold.max(enter_threshold),
+            min_duration,
+            max_blip,
+            index: 0,
+            in_silence: false,
+            start: 0,
+            blip: 0,
+            blip_start: 0,
+            silent_values: 0,
+            sections: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, value: f32) {
+        if value <= self.enter_threshold {
+            self.silent_values += 1;
+        }
+        if !self.in_silence {
+            if value <= self.enter_threshold {
+                self.in_silence = true;
+                self.start = self.index;
+            }
+        } else if value <= self.exit_threshold {
+            self.blip = 0;
+        } else {
+            if self.blip == 0 {
+                self.blip_start = self.index;
+            }
+            self.blip += 1;
+            if self.blip > self.max_blip {
+                if self.blip_start - self.start > self.min_duration {
+                    self.sections.push((self.start, self.blip_start));
+                }
+                self.in_silence = false;
+                self.blip = 0;
+            }
+        }
+        self.index += 1;
+    }
+
+    /// Ratio of the values pushed so far which were at most `enter_threshold`.
+    pub fn silent_ratio(&self) -> f64 {
+        if self.index == 0 {
+            return 0.0;
+        }
+        self.silent_values as f64 / self.index as f64
+    }
+}
+
+/// Runs a `SilenceDetector` over the loudness of each frame, reduced with a `ChannelMode`.
+pub struct SilenceAnalyzer {
+    mode: ChannelMode,
+    pub detector: SilenceDetector,
+}
+
+impl SilenceAnalyzer {
+    pub fn new(mode: ChannelMode, detector: SilenceDetector) -> Self {
+        SilenceAnalyzer { mode, detector }
+    }
+}
+
+impl Analyzer for SilenceAnalyzer {
+    fn process(&mut self, samples: &[f32], channels: usize) {
+        match self.mode {
+            ChannelMode::Interleaved => samples.iter().for_each(|x| self.detector.push(x.abs())),
+            mode => samples
+                .chunks_exact(channels)
+                .for_each(|frame| self.detector.push(mode.reduce(frame))),
+        }
+    }
+}
+
+/// Silent sections detected in a WAV file.
+/// `sections` holds `(start, end)` loudness indices, converted to sec by `frame_per_sec`.
+#[pyclass]
+pub struct Silences {
+    #[pyo3(get)]
+    pub sections: Vec<(u64, u64)>,
+    /// Threshold the sections were detected with, None for digital zero.
+    #[pyo3(get)]
+    pub threshold_dbfs: Option<f64>,
+    /// Ratio of frames at most the threshold, near 1 for a track silent throughout,
+    /// whose silence is never reported as a section.
+    #[pyo3(get)]
+    pub silent_ratio: f64,
+    #[pyo3(get)]
+    pub frame_per_sec: f64,
+    #[pyo3(get)]
+    pub info: WavInfo,
+}
@@ -0,0 +1,339 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom};
+
+use pyo3::buffer::PyBuffer;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::decoded::{DecodedAudio, DecodedReader};
+use crate::error::{ReadError, ReadErrorKind};
+use crate::info::WavInfo;
+use crate::resample::{Decimated, Decimator};
+use crate::ts::{self, AudioStream, AudioStreamSelector, TsReader};
+use crate::wav::{Analyzer, WavFormat, WavReader, BLOCK_FRAMES};
+
+/// Where the audio to analyse comes from.
+///
+/// From Python it is given as one of:
+/// - a `str` or `os.PathLike`: path of a WAV file or a transport stream.
+/// - `bytes`: the whole file in memory, read without a copy.
+/// - any other object of the buffer protocol of bytes, such as `bytearray`, `memoryview`
+///   or a NumPy `uint8` array: the whole file in memory, copied once.
+/// - an object with a `read` method returning `bytes`, such as a file opened in binary mode
+///   or the stdout of a subprocess: read as a stream, so a pipe needs no temporary file.
+/// - a `DecodedAudio`: samples already decoded.
+pub enum AudioSource<'py> {
+    Path(String),
+    Bytes(&'py [u8]),
+    Buffer(Vec<u8>),
+    File {
+        file: &'py PyAny,
+        /// Position to seek back to for a second pass, None if the file is not seekable.
+        start: Option<u64>,
+    },
+    Decoded(PyRef<'py, DecodedAudio>),
+}
+
+impl<'py> FromPyObject<'py> for AudioSource<'py> {
+    fn extract(ob: &'py PyAny) -> PyResult<Self> {
+        if let Ok(audio) = ob.extract::<PyRef<DecodedAudio>>() {
+            return Ok(AudioSource::Decoded(audio));
+        }
+        if let Ok(path) = ob.extract::<String>() {
+            return Ok(AudioSource::Path(path));
+        }
+        if ob.hasattr("__fspath__")? {
+            return Ok(AudioSource::Path(ob.call_method0("__fspath__")?.extract()?));
+        }
+        if let Ok(bytes) = ob.downcast::<PyBytes>() {
+            return Ok(AudioSource::Bytes(bytes.as_bytes()));
+        }
+        if ob.hasattr("read")? {
+            let seekable = ob.hasattr("seekable")? && ob.call_method0("seekable")?.is_true()?;
+            let start = match seekable {
+                true => Some(ob.call_method0("tell")?.extract()?),
+                false => None,
+            };
+            return Ok(AudioSource::File { file: ob, start });
+        }
+        if let Ok(buffer) = PyBuffer::<u8>::get(ob) {
+            return Ok(AudioSource::Buffer(buffer.to_vec(ob.py())?));
+        }
+        Err(PyTypeError::new_err(format!(
+            "Audio source must be a path, bytes, a buffer, a readable binary file or DecodedAudio: {}.",
+            ob.get_type().name()?
+        )))
+    }
+}
+
+impl AudioSource<'_> {
+    /// Name of the source in errors.
+    pub fn name(&self) -> String {
+        match self {
+            AudioSource::Path(path) => path.clone(),
+            AudioSource::Bytes(_) => "<bytes>".to_string(),
+            AudioSource::Buffer(_) => "<buffer>".to_string(),
+            AudioSource::File { file, .. } => file
+                .getattr("name")
+                .and_then(|name| name.extract::<String>())
+                .unwrap_or_else(|_| "<file>".to_string()),
+            AudioSource::Decoded(_) => "<decoded audio>".to_string(),
+        }
+    }
+
+    /// Check the source can be read twice, before an analysis taking two passes starts
+    /// consuming a pipe it could not read again.
+    pub fn check_rewindable(&self) -> PyResult<()> {
+        match self {
+            AudioSource::File { start: None, .. } => Err(PyValueError::new_err(format!(
+                "The audio is read twice, which a file that is not seekable cannot be: {}.",
+                self.name()
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Get ready to be opened again, for analyses taking two passes.
+    pub fn rewind(&self) -> PyResult<()> {
+        match self {
+            AudioSource::File {
+                file,
+                start: Some(start),
+            } => file.call_method1("seek", (*start,)).map(|_| ()),
+            _ => self.check_rewindable(),
+        }
+    }
+}
+
+/// Reads a Python file-like object through its `read` method.
+struct PyFileReader<'py> {
+    file: &'py PyAny,
+}
+
+impl Read for PyFileReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let data = self.file.call_method1("read", (buf.len(),))?;
+        let data: &[u8] = data.extract().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "read must return bytes, so open the file in binary mode",
+            )
+        })?;
+        if data.len() > buf.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "read returned more bytes than asked for",
+            ));
+        }
+        buf[..data.len()].copy_from_slice(data);
+        Ok(data.len())
+    }
+}
+
+impl Seek for PyFileReader<'_> {
+    /// Seek through the `seek` method, which raises for a pipe.
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (offset, whence) = match pos {
+            SeekFrom::Start(offset) => (offset as i64, 0),
+            SeekFrom::Current(offset) => (offset, 1),
+            SeekFrom::End(offset) => (offset, 2),
+        };
+        Ok(self
+            .file
+            .call_method1("seek", (offset, whence))?
+            .extract::<u64>()?)
+    }
+}
+
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+type Stream<'a> = BufReader<Box<dyn ReadSeek + 'a>>;
+
+/// Reader of the samples of a WAV file or of the audio of an MPEG-2 transport stream,
+/// told apart by their first bytes, or of samples already decoded. The readers are boxed
+/// as they hold their buffers and metadata inline.
+pub enum AudioReader<'a> {
+    Wav(Box<WavReader<Stream<'a>>>),
+    Ts(Box<TsReader<Stream<'a>>>),
+    Decoded(DecodedReader<'a>),
+}
+
+impl<'a> AudioReader<'a> {
+    /// Open the audio stream of `source` chosen by `selector`.
+    /// A WAV file and decoded samples have a single stream, the one at index 0.
+    /// `lenient` keeps the samples of a truncated or corrupted WAV file up to the corruption,
+    /// see `WavReader::new`, and goes past damaged audio of a transport stream, see
+    /// `TsReader::new`. A transport stream always ends at its last whole packet.
+    pub fn open(
+        source: &'a AudioSource,
+        selector: &AudioStreamSelector,
+        lenient: bool,
+    ) -> Result<Self, ReadError> {
+        let name = source.name();
+        let reader = match open_stream(source, &name)? {
+            None => AudioReader::Decoded(match source {
+                AudioSource::Decoded(audio) => DecodedReader::new(audio),
+                _ => unreachable!("only decoded audio has no stream"),
+            }),
+            Some((inner, _, true)) => {
+                return Ok(AudioReader::Ts(Box::new(TsReader::new(
+                    inner, &name, selector, lenient,
+                )?)))
+            }
+            Some((inner, len, false)) => {
+                AudioReader::Wav(Box::new(WavReader::new(inner, &name, len, lenient)?))
+            }
+        };
+        if *selector != AudioStreamSelector::Index(0) {
+            let kind = ReadErrorKind::StreamNotFound(format!(
+                "no audio stream of {} in a WAV file, which has only one",
+                selector
+            ));
+            return Err(ReadError::new(kind, &name, 0));
+        }
+        Ok(reader)
+    }
+
+    /// Audio streams of a transport stream, empty for a WAV file or decoded samples.
+    pub fn list_streams(source: &'a AudioSource) -> Result<Vec<AudioStream>, ReadError> {
+        let name = source.name();
+        match open_stream(source, &name)? {
+            None => Ok(Vec::new()),
+            Some((inner, _, true)) => TsReader::list_streams(inner, &name),
+            Some((inner, len, false)) => {
+                WavReader::new(inner, &name, len, false)?;
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    pub fn format(&self) -> &WavFormat {
+        match self {
+            AudioReader::Wav(reader) => reader.format(),
+            AudioReader::Ts(reader) => reader.format(),
+            AudioReader::Decoded(reader) => reader.format(),
+        }
+    }
+
+    /// Metadata of the samples. Frames of a transport stream or of a WAV stream of unknown
+    /// length, and the chunks after the data of a WAV file, are only known once read through,
+    /// so take it after `analyze` or `scan`.
+    pub fn info(&self) -> WavInfo {
+        match self {
+            AudioReader::Wav(reader) => {
+                let mut info = WavInfo::new(reader.format(), reader.frames());
+                reader.metadata().fill_info(&mut info);
+                info.corruption = reader.corruption().cloned();
+                info
+            }
+            AudioReader::Ts(reader) => {
+                let mut info = WavInfo::new(reader.format(), reader.frames());
+                info.start_time_sec = reader.start_time_sec();
+                info.audio_pid = reader.audio_pid();
+                info.audio_streams = reader.streams().to_vec();
+                info.corruption = reader.corruption().cloned();
+                info
+            }
+            AudioReader::Decoded(reader) => WavInfo::new(reader.format(), reader.frames()),
+        }
+    }
+
+    fn read_block(&mut self, out: &mut Vec<f32>) -> Result<usize, ReadError> {
+        match self {
+            AudioReader::Wav(reader) => reader.read_block(out),
+            AudioReader::Ts(reader) => reader.read_block(out),
+            AudioReader::Decoded(reader) => reader.read_block(out),
+        }
+    }
+
+    /// Go through the rest of the source without analysing it so that `info` is complete.
+    /// A transport stream or a WAV stream of unknown length is decoded to count its frames,
+    /// and the data of a WAV file is skipped to read the chunks after it.
+    pub fn scan(&mut self) -> Result<(), ReadError> {
+        let decodes = match self {
+            AudioReader::Wav(reader) if !reader.frames_unknown() => return reader.skip_data(),
+            AudioReader::Wav(_) | AudioReader::Ts(_) => true,
+            AudioReader::Decoded(_) => false,
+        };
+        if decodes {
+            let mut block = Vec::new();
+            while self.read_block(&mut block)? > 0 {}
+        }
+        Ok(())
+    }
+
+    /// Feed every block of samples to `analyzer`.
+    pub fn analyze<A: Analyzer>(&mut self, analyzer: &mut A) -> Result<(), ReadError> {
+        let channels = self.format().channels as usize;
+        let mut block = Vec::with_capacity(BLOCK_FRAMES * channels);
+        while self.read_block(&mut block)? > 0 {
+            analyzer.process(&block, channels);
+        }
+        analyzer.finish();
+        Ok(())
+    }
+
+    /// Feed every block of samples to `analyzer` decimated to `analysis_rate`,
+    /// checked by `resample::analysis_rate`.
+    pub fn analyze_at<A: Analyzer>(
+        &mut self,
+        analysis_rate: u32,
+        analyzer: &mut A,
+    ) -> Result<(), ReadError> {
+        let format = *self.format();
+        if analysis_rate == format.sample_rate {
+            return self.analyze(analyzer);
+        }
+        let decimator = Decimator::new(format.channels as usize, format.sample_rate, analysis_rate);
+        self.analyze(&mut Decimated::new(decimator, analyzer))
+    }
+}
+
+/// Open the bytes of `source` buffered, with their length if known and whether they look
+/// like a transport stream. None for decoded samples.
+fn open_stream<'a>(
+    source: &'a AudioSource,
+    name: &str,
+) -> Result<Option<(Stream<'a>, Option<u64>, bool)>, ReadError> {
+    let (inner, len): (Box<dyn ReadSeek + 'a>, Option<u64>) = match source {
+        AudioSource::Path(path) => {
+            let file = File::open(path).map_err(|e| ReadError::from_io(e, name, 0))?;
+            let len = file
+                .metadata()
+                .map_err(|e| ReadError::from_io(e, name, 0))?
+                .len();
+            (Box::new(file), Some(len))
+        }
+        AudioSource::Bytes(bytes) => (Box::new(Cursor::new(*bytes)), Some(bytes.len() as u64)),
+        AudioSource::Buffer(bytes) => (
+            Box::new(Cursor::new(bytes.as_slice())),
+            Some(bytes.len() as u64),
+        ),
+        AudioSource::File { file, start } => {
+            let len = match start {
+                Some(start) => Some(
+                    file_len(file, *start).map_err(|e| ReadError::from_io(e.into(), name, 0))?,
+                ),
+                None => None,
+            };
+            (Box::new(PyFileReader { file }), len)
+        }
+        AudioSource::Decoded(_) => return Ok(None),
+    };
+    let mut inner = BufReader::new(inner);
+    let head = inner
+        .fill_buf()
+        .map_err(|e| ReadError::from_io(e, name, 0))?;
+    let is_ts = ts::is_transport_stream(head);
+    Ok(Some((inner, len, is_ts)))
+}
+
+/// Bytes of a seekable file from `start` to its end, seeking back to `start`.
+fn file_len(file: &PyAny, start: u64) -> PyResult<u64> {
+    let end: u64 = file.call_method1("seek", (0, 2))?.extract()?;
+    file.call_method1("seek", (start,))?;
+    Ok(end.saturating_sub(start))
+}
@@ -0,0 +1,630 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use serde::de::{self, Deserialize, Deserializer, Unexpected, Visitor};
+
+use crate::error::{ReadError, ReadErrorKind};
+use crate::es::{AudioCodec, EsDecoder};
+use crate::info::Corruption;
+use crate::wav::{SampleFormat, WavFormat};
+
+pub const PACKET_SIZE: usize = 188;
+const SYNC_BYTE: u8 = 0x47;
+const PAT_PID: u16 = 0x0000;
+const PAT_TABLE_ID: u8 = 0x00;
+const PMT_TABLE_ID: u8 = 0x02;
+const ISO_639_LANGUAGE_DESCRIPTOR: u8 = 0x0A;
+const STREAM_IDENTIFIER_DESCRIPTOR: u8 = 0x52;
+const AUDIO_COMPONENT_DESCRIPTOR: u8 = 0xC4;
+/// Ticks of the 90 kHz clock of PTS per sec.
+const PTS_PER_SEC: f64 = 90000.0;
+/// PTS are 33 bits, wrapping around in about 26.5 hours.
+const PTS_MASK: u64 = (1 << 33) - 1;
+/// Longest gap of dropped audio filled with silence. A longer one is taken for a jump of PTS,
+/// as at a splice, and left as it is.
+const MAX_FILLED_GAP_SEC: f64 = 10.0;
+
+/// Whether a stream starting with `head` looks like an MPEG-2 transport stream.
+pub fn is_transport_stream(head: &[u8]) -> bool {
+    head.first() == Some(&SYNC_BYTE) && head.get(PACKET_SIZE).is_none_or(|b| *b == SYNC_BYTE)
+}
+
+/// Audio elementary stream listed in the PMT, with what its descriptors tell about it.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct AudioStream {
+    /// Position among the audio streams of the program.
+    #[pyo3(get)]
+    pub index: usize,
+    #[pyo3(get)]
+    pub pid: u16,
+    #[pyo3(get)]
+    pub stream_type: u8,
+    pub codec: AudioCodec,
+    /// ISO 639-2 code of the ISO 639 language or the ARIB audio component descriptor.
+    #[pyo3(get)]
+    pub language: Option<String>,
+    /// Tag of the stream identifier descriptor, by which ARIB tells main from sub audio.
+    #[pyo3(get)]
+    pub component_tag: Option<u8>,
+    /// Type of the ARIB audio component descriptor, e.g. 0x02 for dual mono or 0x03 for stereo.
+    #[pyo3(get)]
+    pub component_type: Option<u8>,
+}
+
+impl AudioStream {
+    fn read_descriptors(&mut self, mut descriptors: &[u8]) {
+        while descriptors.len() >= 2 {
+            let len = descriptors[1] as usize;
+            let Some(body) = descriptors.get(2..2 + len) else {
+                break;
+            };
+            match descriptors[0] {
+                ISO_639_LANGUAGE_DESCRIPTOR if len >= 3 => {
+                    self.language = language_code(&body[0..3]);
+                }
+                STREAM_IDENTIFIER_DESCRIPTOR if len >= 1 => self.component_tag = Some(body[0]),
+                AUDIO_COMPONENT_DESCRIPTOR if len >= 9 => {
+                    self.component_type = Some(body[1]);
+                    self.component_tag.get_or_insert(body[2]);
+                    if self.language.is_none() {
+                        self.language = language_code(&body[6..9]);
+                    }
+                }
+                _ => {}
+            }
+            descriptors = &descriptors[2 + len..];
+        }
+    }
+}
+
+#[pymethods]
+impl AudioStream {
+    /// Coding of the stream, either "mpeg", "adts" or "latm".
+    #[getter]
+    fn codec(&self) -> &'static str {
+        self.codec.name()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "AudioStream(index={}, pid=0x{:04x}, codec='{}', language={:?}, component_tag={:?})",
+            self.index,
+            self.pid,
+            self.codec.name(),
+            self.language,
+            self.component_tag
+        )
+    }
+}
+
+fn language_code(bytes: &[u8]) -> Option<String> {
+    if bytes.iter().all(u8::is_ascii_alphabetic) {
+        Some(String::from_utf8_lossy(bytes).to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+/// Which audio stream of a transport stream to analyse.
+///
+/// From Python it is given as one of:
+/// - an `int`: index among the audio streams of the program, 0 being the first.
+/// - a `str` starting with `"0x"`: PID of the stream, e.g. `"0x0111"`.
+/// - any other `str`: ISO 639-2 language of the stream, e.g. `"jpn"`, first match winning.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AudioStreamSelector {
+    Index(usize),
+    Pid(u16),
+    Language(String),
+}
+
+impl Default for AudioStreamSelector {
+    fn default() -> Self {
+        AudioStreamSelector::Index(0)
+    }
+}
+
+impl AudioStreamSelector {
+    fn select<'a>(&self, streams: &'a [AudioStream]) -> Option<&'a AudioStream> {
+        match self {
+            AudioStreamSelector::Index(index) => streams.get(*index),
+            AudioStreamSelector::Pid(pid) => streams.iter().find(|stream| stream.pid == *pid),
+            AudioStreamSelector::Language(language) => streams
+                .iter()
+                .find(|stream| stream.language.as_deref() == Some(language)),
+        }
+    }
+}
+
+impl fmt::Display for AudioStreamSelector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AudioStreamSelector::Index(index) => write!(f, "index {}", index),
+            AudioStreamSelector::Pid(pid) => write!(f, "PID 0x{:04x}", pid),
+            AudioStreamSelector::Language(language) => write!(f, "language '{}'", language),
+        }
+    }
+}
+
+impl AudioStreamSelector {
+    /// Selector of a PID like `"0x0111"` or a language.
+    fn from_text(text: &str) -> Option<Self> {
+        match text.strip_prefix("0x") {
+            Some(hex) => u16::from_str_radix(hex, 16)
+                .ok()
+                .map(AudioStreamSelector::Pid),
+            None if !text.is_empty() => {
+                Some(AudioStreamSelector::Language(text.to_ascii_lowercase()))
+            }
+            None => None,
+        }
+    }
+}
+
+impl<'source> FromPyObject<'source> for AudioStreamSelector {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        if let Ok(index) = ob.extract::<usize>() {
+            return Ok(AudioStreamSelector::Index(index));
+        }
+        ob.extract::<&str>()
+            .ok()
+            .and_then(AudioStreamSelector::from_text)
+            .ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "Audio stream must be an index, a PID like '0x0111' or a language: {}.",
+                    ob
+                ))
+            })
+    }
+}
+
+impl IntoPy<PyObject> for AudioStreamSelector {
+    fn into_py(self, py: Python) -> PyObject {
+        match self {
+            AudioStreamSelector::Index(index) => index.into_py(py),
+            AudioStreamSelector::Pid(pid) => format!("0x{:04x}", pid).into_py(py),
+            AudioStreamSelector::Language(language) => language.into_py(py),
+        }
+    }
+}
+
+/// Same forms as from Python, for program property files.
+impl<'de> Deserialize<'de> for AudioStreamSelector {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SelectorVisitor;
+
+        impl Visitor<'_> for SelectorVisitor {
+            type Value = AudioStreamSelector;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an index, a PID like '0x0111' or a language")
+            }
+
+            fn visit_u64<E: de::Error>(self, index: u64) -> Result<AudioStreamSelector, E> {
+                Ok(AudioStreamSelector::Index(index as usize))
+            }
+
+            fn visit_str<E: de::Error>(self, text: &str) -> Result<AudioStreamSelector, E> {
+                AudioStreamSelector::from_text(text)
+                    .ok_or_else(|| E::invalid_value(Unexpected::Str(text), &self))
+            }
+        }
+
+        deserializer.deserialize_any(SelectorVisitor)
+    }
+}
+
+/// Streaming reader which demuxes one audio elementary stream of the first program
+/// of a transport stream and decodes it block by block.
+pub struct TsReader<R: Read> {
+    inner: R,
+    path: String,
+    offset: u64,
+    /// PSI sections being assembled, by PID.
+    sections: HashMap<u16, Vec<u8>>,
+    pmt_pid: Option<u16>,
+    streams: Vec<AudioStream>,
+    audio_pid: Option<u16>,
+    /// Payloads are skipped until a PES starts on the audio PID.
+    in_pes: bool,
+    /// Continuity counter of the last packet of the audio PID.
+    continuity: Option<u8>,
+    /// Drop damaged audio and fill it with silence instead of failing.
+    lenient: bool,
+    /// Audio has been dropped since the last PES with a PTS.
+    damaged: bool,
+    corruption: Option<Corruption>,
+    /// Frames decoded or filled so far, handed out or pending.
+    decoded_frames: u64,
+    decoder: Option<EsDecoder>,
+    format: WavFormat,
+    start_pts: Option<u64>,
+    frames: u64,
+    /// Decoded samples not handed out yet.
+    pending: Vec<f32>,
+    eof: bool,
+}
+
+impl<R: Read> TsReader<R> {
+    /// Demux up to the first decoded audio frame of the stream chosen by `selector`,
+    /// which gives the format of the samples. `path` only names the source in errors.
+    ///
+    /// When `lenient`, a PES without a start code, a jump of the continuity counter or a
+    /// frame which cannot be decoded drops the rest of its PES, and decoding starts again at
+    /// the next one. The audio dropped is filled with silence up to the PTS of the next PES
+    /// so that the timing does not drift, and `corruption` tells where the first damage was.
+    pub fn new(
+        inner: R,
+        path: &str,
+        selector: &AudioStreamSelector,
+        lenient: bool,
+    ) -> Result<Self, ReadError> {
+        let mut reader = Self::read_streams(inner, path)?;
+        reader.lenient = lenient;
+        let stream = selector.select(&reader.streams).ok_or_else(|| {
+            let pids: Vec<String> = reader
+                .streams
+                .iter()
+                .map(|stream| format!("0x{:04x}", stream.pid))
+                .collect();
+            let kind = ReadErrorKind::StreamNotFound(format!(
+                "no audio stream of {} among PIDs {}",
+                selector,
+                pids.join(", ")
+            ));
+            ReadError::new(kind, path, reader.offset)
+        })?;
+        reader.audio_pid = Some(stream.pid);
+        reader.decoder = Some(EsDecoder::new(stream.codec));
+        reader.fill_pending()?;
+        let spec = reader.decoder.as_ref().and_then(|decoder| decoder.spec());
+        let (channels, sample_rate) = spec.ok_or_else(|| {
+            let kind = ReadErrorKind::Malformed("no audio frame in the transport stream".into());
+            ReadError::new(kind, path, reader.offset)
+        })?;
+        reader.format.channels = channels as u16;
+        reader.format.sample_rate = sample_rate;
+        reader.format.block_align = channels as u16 * 4;
+        Ok(reader)
+    }
+
+    /// Audio streams of the first program, demuxing up to its PMT.
+    pub fn list_streams(inner: R, path: &str) -> Result<Vec<AudioStream>, ReadError> {
+        Ok(Self::read_streams(inner, path)?.streams)
+    }
+
+    fn read_streams(inner: R, path: &str) -> Result<Self, ReadError> {
+        let mut reader = TsReader {
+            inner,
+            path: path.to_string(),
+            offset: 0,
+            sections: HashMap::new(),
+            pmt_pid: None,
+            streams: Vec::new(),
+            audio_pid: None,
+            in_pes: false,
+            continuity: None,
+            lenient: false,
+            damaged: false,
+            corruption: None,
+            decoded_frames: 0,
+            decoder: None,
+            format: WavFormat {
+                sample_format: SampleFormat::Float,
+                channels: 0,
+                sample_rate: 0,
+                bits_per_sample: 32,
+                valid_bits_per_sample: 32,
+                channel_mask: 0,
+                block_align: 0,
+            },
+            start_pts: None,
+            frames: 0,
+            pending: Vec::new(),
+            eof: false,
+        };
+        let mut packet = [0u8; PACKET_SIZE];
+        while reader.streams.is_empty() && !reader.eof {
+            if reader.read_packet(&mut packet)? {
+                reader.handle_packet(&packet, reader.offset - PACKET_SIZE as u64)?;
+            } else {
+                reader.eof = true;
+            }
+        }
+        if reader.streams.is_empty() {
+            let kind = ReadErrorKind::Malformed("no audio stream in the transport stream".into());
+            return Err(ReadError::new(kind, path, reader.offset));
+        }
+        Ok(reader)
+    }
+
+    /// Format of the decoded samples, which are 32 bit float.
+    pub fn format(&self) -> &WavFormat {
+        &self.format
+    }
+
+    /// Number of frames decoded so far.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Audio streams listed in the PMT.
+    pub fn streams(&self) -> &[AudioStream] {
+        &self.streams
+    }
+
+    /// PID of the audio stream being decoded.
+    pub fn audio_pid(&self) -> Option<u16> {
+        self.audio_pid
+    }
+
+    /// PTS of the first decoded frame in sec, to line samples up with the other streams.
+    pub fn start_time_sec(&self) -> Option<f64> {
+        self.start_pts.map(|pts| pts as f64 / PTS_PER_SEC)
+    }
+
+    /// The first damage to the audio that a lenient read went past.
+    pub fn corruption(&self) -> Option<&Corruption> {
+        self.corruption.as_ref()
+    }
+
+    /// Hand out the samples of the next decoded PES packets as `WavReader::read_block` does.
+    pub fn read_block(&mut self, out: &mut Vec<f32>) -> Result<usize, ReadError> {
+        out.clear();
+        self.fill_pending()?;
+        std::mem::swap(out, &mut self.pending);
+        let frames = out.len() / self.format.channels.max(1) as usize;
+        self.frames += frames as u64;
+        Ok(frames)
+    }
+
+    fn fill_pending(&mut self) -> Result<(), ReadError> {
+        let mut packet = [0u8; PACKET_SIZE];
+        while self.pending.is_empty() && !self.eof {
+            if self.read_packet(&mut packet)? {
+                self.handle_packet(&packet, self.offset - PACKET_SIZE as u64)?;
+            } else {
+                self.eof = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the next packet, skipping bytes up to a sync byte when out of sync.
+    /// A partial packet at the end of the stream is dropped.
+    fn read_packet(&mut self, packet: &mut [u8; PACKET_SIZE]) -> Result<bool, ReadError> {
+        if self.fill(packet)? < PACKET_SIZE {
+            return Ok(false);
+        }
+        while packet[0] != SYNC_BYTE {
+            let kept = match packet[1..].iter().position(|b| *b == SYNC_BYTE) {
+                Some(position) => {
+                    packet.copy_within(position + 1.., 0);
+                    PACKET_SIZE - position - 1
+                }
+                None => 0,
+            };
+            if self.fill(&mut packet[kept..])? < PACKET_SIZE - kept {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn fill(&mut self, buf: &mut [u8]) -> Result<usize, ReadError> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.inner.read(&mut buf[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    return Err(ReadError::from_io(
+                        e,
+                        &self.path,
+                        self.offset + filled as u64,
+                    ))
+                }
+            }
+        }
+        self.offset += filled as u64;
+        Ok(filled)
+    }
+
+    fn handle_packet(&mut self, packet: &[u8], offset: u64) -> Result<(), ReadError> {
+        // Packets flagged with a transport error are dropped.
+        if packet[1] & 0x80 != 0 {
+            return Ok(());
+        }
+        let unit_start = packet[1] & 0x40 != 0;
+        let pid = u16::from_be_bytes([packet[1] & 0x1F, packet[2]]);
+        let adaptation_field_control = packet[3] >> 4 & 3;
+        if adaptation_field_control & 1 == 0 {
+            return Ok(());
+        }
+        let start = match adaptation_field_control & 2 {
+            0 => 4,
+            _ => 5 + packet[4] as usize,
+        };
+        if start >= PACKET_SIZE {
+            return Ok(());
+        }
+        let payload = &packet[start..];
+        if pid == PAT_PID || Some(pid) == self.pmt_pid {
+            self.push_section(pid, unit_start, payload);
+        } else if Some(pid) == self.audio_pid {
+            if self.lenient {
+                let discontinuity = start > 5 && packet[5] & 0x80 != 0;
+                let continuity = packet[3] & 0x0F;
+                match self.continuity.replace(continuity) {
+                    // A packet may be sent twice.
+                    Some(last) if last == continuity && !discontinuity => return Ok(()),
+                    Some(last) if (last + 1) & 0x0F != continuity && !discontinuity => {
+                        let reason = format!(
+                            "continuity counter of the audio jumps from {} to {}",
+                            last, continuity
+                        );
+                        self.damage(offset, reason);
+                    }
+                    _ => {}
+                }
+            }
+            self.push_pes(unit_start, payload, offset)?;
+        }
+        Ok(())
+    }
+
+    fn push_section(&mut self, pid: u16, unit_start: bool, payload: &[u8]) {
+        let buffer = self.sections.entry(pid).or_default();
+        if unit_start {
+            let pointer = payload[0] as usize;
+            buffer.clear();
+            buffer.extend_from_slice(payload.get(1 + pointer..).unwrap_or_default());
+        } else if !buffer.is_empty() {
+            buffer.extend_from_slice(payload);
+        }
+        if buffer.len() < 3 {
+            return;
+        }
+        let len = 3 + ((buffer[1] as usize & 0x0F) << 8 | buffer[2] as usize);
+        if buffer.len() < len {
+            return;
+        }
+        let section: Vec<u8> = buffer.drain(..).take(len).collect();
+        // The header up to last_section_number and the CRC take 12 bytes.
+        if section.len() < 12 {
+            return;
+        }
+        let body = &section[8..section.len() - 4];
+        match section[0] {
+            PAT_TABLE_ID if self.pmt_pid.is_none() => {
+                self.pmt_pid = body
+                    .chunks_exact(4)
+                    .find(|program| program[0..2] != [0, 0])
+                    .map(|program| u16::from_be_bytes([program[2] & 0x1F, program[3]]));
+            }
+            PMT_TABLE_ID if self.streams.is_empty() => self.read_pmt(body),
+            _ => {}
+        }
+    }
+
+    fn read_pmt(&mut self, body: &[u8]) {
+        if body.len() < 4 {
+            return;
+        }
+        let program_info_len = (body[2] as usize & 0x0F) << 8 | body[3] as usize;
+        let mut i = 4 + program_info_len;
+        while i + 5 <= body.len() {
+            let pid = u16::from_be_bytes([body[i + 1] & 0x1F, body[i + 2]]);
+            let es_info_len = (body[i + 3] as usize & 0x0F) << 8 | body[i + 4] as usize;
+            if let Some(codec) = AudioCodec::from_stream_type(body[i]) {
+                let mut stream = AudioStream {
+                    index: self.streams.len(),
+                    pid,
+                    stream_type: body[i],
+                    codec,
+                    language: None,
+                    component_tag: None,
+                    component_type: None,
+                };
+                let end = (i + 5 + es_info_len).min(body.len());
+                stream.read_descriptors(&body[i + 5..end]);
+                self.streams.push(stream);
+            }
+            i += 5 + es_info_len;
+        }
+    }
+
+    fn push_pes(&mut self, unit_start: bool, payload: &[u8], offset: u64) -> Result<(), ReadError> {
+        let data = if unit_start {
+            if payload.len() < 9 || payload[0..3] != [0, 0, 1] {
+                let reason = "PES packet without start code";
+                if self.lenient {
+                    self.damage(offset, reason.into());
+                    return Ok(());
+                }
+                let kind = ReadErrorKind::Malformed(reason.into());
+                return Err(ReadError::new(kind, &self.path, offset));
+            }
+            // PTS_DTS_flags with a PTS.
+            if payload[7] & 0x80 != 0 && payload.len() >= 14 {
+                let pts = read_pts(&payload[9..14]);
+                self.start_pts.get_or_insert(pts);
+                if self.damaged {
+                    self.fill_gap(pts);
+                }
+            }
+            self.in_pes = true;
+            payload.get(9 + payload[8] as usize..).unwrap_or_default()
+        } else if self.in_pes {
+            payload
+        } else {
+            return Ok(());
+        };
+        let Some(decoder) = &mut self.decoder else {
+            return Ok(());
+        };
+        let pending = self.pending.len();
+        let result = decoder.push(data, &mut self.pending);
+        if let Some((channels, _)) = decoder.spec() {
+            self.decoded_frames += ((self.pending.len() - pending) / channels) as u64;
+        }
+        match result {
+            Err(ReadErrorKind::Malformed(reason)) if self.lenient => {
+                self.damage(offset, reason);
+                Ok(())
+            }
+            result => result.map_err(|kind| ReadError::new(kind, &self.path, offset)),
+        }
+    }
+
+    /// Drop the rest of the PES being decoded for the damage found at `offset`, to start again
+    /// at the next one.
+    fn damage(&mut self, offset: u64, reason: String) {
+        self.in_pes = false;
+        self.damaged = true;
+        if let Some(decoder) = &mut self.decoder {
+            decoder.resync();
+        }
+        self.corruption.get_or_insert(Corruption {
+            offset,
+            reason,
+            declared_frames: None,
+            recovered_frames: self.decoded_frames,
+            recovered_bytes: offset,
+            filled_frames: 0,
+        });
+    }
+
+    /// Fill the audio dropped before the PES at `pts` with silence, up to where it starts.
+    fn fill_gap(&mut self, pts: u64) {
+        self.damaged = false;
+        let spec = self.decoder.as_ref().and_then(|decoder| decoder.spec());
+        let (Some(start_pts), Some((channels, sample_rate))) = (self.start_pts, spec) else {
+            return;
+        };
+        let start_sec = (pts.wrapping_sub(start_pts) & PTS_MASK) as f64 / PTS_PER_SEC;
+        let gap = (start_sec * sample_rate as f64).round() as i64 - self.decoded_frames as i64;
+        if gap <= 0 || gap as f64 > MAX_FILLED_GAP_SEC * sample_rate as f64 {
+            return;
+        }
+        self.pending
+            .resize(self.pending.len() + gap as usize * channels, 0.0);
+        self.decoded_frames += gap as u64;
+        if let Some(corruption) = &mut self.corruption {
+            corruption.filled_frames += gap as u64;
+        }
+    }
+}
+
+fn read_pts(bytes: &[u8]) -> u64 {
+    (bytes[0] as u64 >> 1 & 0x07) << 30
+        | (bytes[1] as u64) << 22
+        | (bytes[2] as u64 >> 1) << 15
+        | (bytes[3] as u64) << 7
+        | bytes[4] as u64 >> 1
+}
@@ -0,0 +1,289 @@
+use std::fs::File;
+use std::io::Cursor;
+
+use memmap2::Mmap;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::buffer;
+use crate::channels::{ChannelLoudness, ChannelMode};
+use crate::envelope::{self, Envelope, EnvelopeAnalyzer, EnvelopeKind};
+use crate::error::{ReadError, ReadErrorKind};
+use crate::info::WavInfo;
+use crate::silence;
+use crate::wav::{self, Analyzer, WavFormat, WavReader, BLOCK_FRAMES};
+
+/// WAV file opened through a memory map, for random access by several passes of detection.
+///
+/// Ranges are `[start, end)` in frames, `end` defaulting to the end of the data chunk.
+/// Only the bytes of a range are decoded, block by block, so nothing copies the whole file.
+#[pyclass]
+pub struct WavView {
+    mmap: Mmap,
+    format: WavFormat,
+    info: WavInfo,
+    data_offset: usize,
+    frames: u64,
+}
+
+impl WavView {
+    fn open(path: &str, lenient: bool) -> Result<Self, ReadError> {
+        let file = File::open(path).map_err(|e| ReadError::from_io(e, path, 0))?;
+        // Safety: the map is only read. A file truncated by another process while mapped
+        // is not supported, as with any reader of a file being rewritten.
+        let mmap = unsafe { Mmap::map(&file) }.map_err(|e| ReadError::from_io(e, path, 0))?;
+        let (format, data_offset, frames, info) = {
+            let cursor = Cursor::new(&mmap[..]);
+            let mut reader = WavReader::new(cursor, path, Some(mmap.len() as u64), lenient)?;
+            let (data_offset, frames) = (reader.data_offset(), reader.frames());
+            reader.skip_data()?;
+            let mut info = WavInfo::new(reader.format(), frames);
+            reader.metadata().fill_info(&mut info);
+            info.corruption = reader.corruption().cloned();
+            (*reader.format(), data_offset, frames, info)
+        };
+        if data_offset + frames * format.block_align as u64 > mmap.len() as u64 {
+            let kind = ReadErrorKind::Truncated;
+            return Err(ReadError::new(kind, path, mmap.len() as u64));
+        }
+        Ok(WavView {
+            mmap,
+            format,
+            info,
+            data_offset: data_offset as usize,
+            frames,
+        })
+    }
+
+    fn range(&self, start: u64, end: Option<u64>) -> PyResult<(u64, u64)> {
+        let end = end.unwrap_or(self.frames);
+        if start > end || end > self.frames {
+            return Err(PyValueError::new_err(format!(
+                "Frame range out of the data: {} {} of {}.",
+                start, end, self.frames
+            )));
+        }
+        Ok((start, end))
+    }
+
+    /// Feed the samples of frames `[start, end)` to `analyzer` block by block.
+    fn analyze<A: Analyzer>(&self, start: u64, end: u64, analyzer: &mut A) {
+        let block_align = self.format.block_align as usize;
+        let channels = self.format.channels as usize;
+        let data = &self.mmap[self.data_offset + start as usize * block_align
+            ..self.data_offset + end as usize * block_align];
+        let mut block = Vec::with_capacity(BLOCK_FRAMES * channels);
+        for raw in data.chunks(BLOCK_FRAMES * block_align) {
+            block.clear();
+            wav::decode_samples(&self.format, raw, &mut block);
+            analyzer.process(&block, channels);
+        }
+        analyzer.finish();
+    }
+}
+
+#[pymethods]
+impl WavView {
+    /// `lenient` gives a view of the whole frames of a truncated file, see `WavInfo.corruption`.
+    #[new]
+    #[args(lenient = "false")]
+    fn new(file_path: &str, lenient: bool) -> PyResult<Self> {
+        Ok(WavView::open(file_path, lenient)?)
+    }
+
+    #[getter]
+    fn info(&self) -> WavInfo {
+        self.info.clone()
+    }
+
+    #[getter]
+    fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    fn __len__(&self) -> usize {
+        self.frames as usize
+    }
+
+    /// Interleaved samples of the range scaled so that full scale is 1.0.
+    #[args(end = "None")]
+    fn samples(&self, py: Python, start: u64, end: Option<u64>) -> PyResult<PyObject> {
+        let (start, end) = self.range(start, end)?;
+        let block_align = self.format.block_align as usize;
+        let mut samples =
+            Vec::with_capacity((end - start) as usize * self.format.channels as usize);
+        wav::decode_samples(
+            &self.format,
+            &self.mmap[self.data_offset + start as usize * block_align
+                ..self.data_offset + end as usize * block_align],
+            &mut samples,
+        );
+        buffer::into_ndarray(py, samples)
+    }
+
+    /// Loudness of every frame of the range as `wav2loudness` gives it.
+    #[args(end = "None", channel_mode = "ChannelMode::All")]
+    fn loudness(
+        &self,
+        py: Python,
+        start: u64,
+        end: Option<u64>,
+        channel_mode: ChannelMode,
+    ) -> PyResult<PyObject> {
+        let (start, end) = self.range(start, end)?;
+        channel_mode.validate(self.format.channels as usize)?;
+        let mut loudness = ChannelLoudness::new(channel_mode);
+        self.analyze(start, end, &mut loudness);
+        buffer::into_ndarray(py, loudness.values)
+    }
+
+    /// Peak, RMS and mean of the frame magnitudes over the range, see `RangeStats`.
+    #[args(end = "None", channel_mode = "ChannelMode::All")]
+    fn stats(
+        &self,
+        start: u64,
+        end: Option<u64>,
+        channel_mode: ChannelMode,
+    ) -> PyResult<RangeStats> {
+        let (start, end) = self.range(start, end)?;
+        channel_mode.validate(self.format.channels as usize)?;
+        let mut stats = StatsAnalyzer::new(channel_mode);
+        self.analyze(start, end, &mut stats);
+        Ok(stats.stats())
+    }
+
+    /// Envelope of the range as `wav2envelope` gives it, `values[0]` starting at `start`.
+    #[args(
+        end = "None",
+        window_sec = "0.01",
+        hop_sec = "None",
+        kind = "EnvelopeKind::Rms",
+        channel_mode = "ChannelMode::All"
+    )]
+    #[allow(clippy::too_many_arguments)]
+    fn envelope(
+        &self,
+        py: Python,
+        start: u64,
+        end: Option<u64>,
+        window_sec: f64,
+        hop_sec: Option<f64>,
+        kind: EnvelopeKind,
+        channel_mode: ChannelMode,
+    ) -> PyResult<Envelope> {
+        let (start, end) = self.range(start, end)?;
+        if channel_mode == ChannelMode::Interleaved {
+            return Err(PyValueError::new_err(
+                "Envelope needs channels reduced per frame: interleaved.",
+            ));
+        }
+        channel_mode.validate(self.format.channels as usize)?;
+        let sample_rate = self.format.sample_rate;
+        let window_frames = envelope::sec_to_frames(window_sec, sample_rate, "window_sec")?;
+        let hop_frames = match hop_sec {
+            Some(hop_sec) => envelope::sec_to_frames(hop_sec, sample_rate, "hop_sec")?,
+            None => window_frames,
+        };
+        let mut analyzer = EnvelopeAnalyzer::new(kind, channel_mode, window_frames, hop_frames);
+        self.analyze(start, end, &mut analyzer);
+        Ok(Envelope {
+            values: buffer::into_ndarray(py, analyzer.values)?,
+            frame_per_sec: sample_rate as f64 / hop_frames as f64,
+            window_frames,
+            hop_frames,
+            info: self.info.clone(),
+        })
+    }
+}
+
+/// Statistics of the frame magnitudes over a range of a `WavView`.
+#[pyclass]
+pub struct RangeStats {
+    /// Number of magnitudes, one per frame or one per sample when interleaved.
+    #[pyo3(get)]
+    pub count: u64,
+    #[pyo3(get)]
+    pub peak: f64,
+    #[pyo3(get)]
+    pub rms: f64,
+    #[pyo3(get)]
+    pub mean_abs: f64,
+    /// Number of magnitudes at digital zero.
+    #[pyo3(get)]
+    pub zero_count: u64,
+}
+
+#[pymethods]
+impl RangeStats {
+    #[getter]
+    fn peak_dbfs(&self) -> f64 {
+        silence::linear_to_dbfs(self.peak)
+    }
+
+    #[getter]
+    fn rms_dbfs(&self) -> f64 {
+        silence::linear_to_dbfs(self.rms)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "RangeStats(count={}, peak={}, rms={}, mean_abs={}, zero_count={})",
+            self.count, self.peak, self.rms, self.mean_abs, self.zero_count
+        )
+    }
+}
+
+/// Accumulates `RangeStats` over magnitudes reduced with a `ChannelMode`.
+struct StatsAnalyzer {
+    mode: ChannelMode,
+    count: u64,
+    peak: f32,
+    sum: f64,
+    sum_squares: f64,
+    zero_count: u64,
+}
+
+impl StatsAnalyzer {
+    fn new(mode: ChannelMode) -> Self {
+        StatsAnalyzer {
+            mode,
+            count: 0,
+            peak: 0.0,
+            sum: 0.0,
+            sum_squares: 0.0,
+            zero_count: 0,
+        }
+    }
+
+    fn push(&mut self, magnitude: f32) {
+        self.count += 1;
+        self.peak = self.peak.max(magnitude);
+        self.sum += magnitude as f64;
+        self.sum_squares += magnitude as f64 * magnitude as f64;
+        if magnitude == 0.0 {
+            self.zero_count += 1;
+        }
+    }
+
+    fn stats(&self) -> RangeStats {
+        let count = self.count.max(1) as f64;
+        RangeStats {
+            count: self.count,
+            peak: self.peak as f64,
+            rms: (self.sum_squares / count).sqrt(),
+            mean_abs: self.sum / count,
+            zero_count: self.zero_count,
+        }
+    }
+}
+
+impl Analyzer for StatsAnalyzer {
+    fn process(&mut self, samples: &[f32], channels: usize) {
+        match self.mode {
+            ChannelMode::Interleaved => samples.iter().for_each(|x| self.push(x.abs())),
+            mode => samples
+                .chunks_exact(channels)
+                .for_each(|frame| self.push(mode.reduce(frame))),
+        }
+    }
+}
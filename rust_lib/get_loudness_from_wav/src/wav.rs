@@ -0,0 +1,598 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::error::{ReadError, ReadErrorKind};
+use crate::info::Corruption;
+use crate::metadata::{WavMetadata, MAX_METADATA_CHUNK};
+
+/// Number of frames decoded per block while streaming the data chunk.
+pub const BLOCK_FRAMES: usize = 4096;
+
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+/// Bytes following the format tag in the sub format GUID of WAVE_FORMAT_EXTENSIBLE.
+const SUB_FORMAT_GUID_TAIL: [u8; 14] = [
+    0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+];
+/// Largest `fmt ` chunk, the extensible fields with as many extra bytes as `cbSize` can
+/// declare, so that a corrupt size is not allocated.
+const MAX_FMT_CHUNK: u64 = 18 + u16::MAX as u64;
+/// Largest `ds64` chunk, its fixed fields with a table of far more chunks than a file has.
+const MAX_DS64_CHUNK: u64 = 28 + 12 * 1024;
+/// 32-bit size field of a chunk whose size is held by the `ds64` chunk, or unknown.
+const UNKNOWN_SIZE: u32 = 0xFFFF_FFFF;
+
+/// Encoding of samples in the data chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleFormat {
+    Int,
+    Float,
+}
+
+/// Properties read from the `fmt ` chunk.
+#[derive(Clone, Copy, Debug)]
+pub struct WavFormat {
+    pub sample_format: SampleFormat,
+    pub channels: u16,
+    pub sample_rate: u32,
+    /// Size of the sample container.
+    pub bits_per_sample: u16,
+    /// Bits actually used in the container, the most significant ones.
+    pub valid_bits_per_sample: u16,
+    /// Speaker positions of WAVE_FORMAT_EXTENSIBLE, 0 when not given.
+    pub channel_mask: u32,
+    pub block_align: u16,
+}
+
+impl WavFormat {
+    fn bytes_per_sample(&self) -> usize {
+        self.bits_per_sample as usize / 8
+    }
+}
+
+impl SampleFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SampleFormat::Int => "int",
+            SampleFormat::Float => "float",
+        }
+    }
+}
+
+/// Streaming reader which walks the data chunk of a WAV file block by block.
+/// Only one block of raw bytes is held in memory at a time.
+pub struct WavReader<R: Read> {
+    inner: R,
+    path: String,
+    offset: u64,
+    format: WavFormat,
+    frames: u64,
+    data_remaining: u64,
+    /// The data chunk size is unknown, so samples run to the end of the stream.
+    to_eof: bool,
+    /// Frames read so far, which replace the estimate at the end of the stream.
+    frames_read: u64,
+    /// The data chunk has an odd size, so a pad byte follows it.
+    data_padded: bool,
+    /// The chunks after the data chunk have been read.
+    trailing_read: bool,
+    /// Byte offset of the first sample.
+    data_start: u64,
+    /// Keep the frames read so far instead of failing when the samples cannot be read.
+    lenient: bool,
+    corruption: Option<Corruption>,
+    metadata: WavMetadata,
+    raw: Vec<u8>,
+}
+
+/// 64-bit chunk sizes of an RF64/BW64 file.
+#[derive(Default)]
+struct Ds64 {
+    data_size: u64,
+    table: Vec<([u8; 4], u64)>,
+}
+
+impl<R: Read> WavReader<R> {
+    /// Parse the RIFF header and chunks up to the data chunk.
+    /// `path` only names the source in errors and `len` is the length of the stream if known.
+    ///
+    /// RF64 and BW64 files take their sizes from the `ds64` chunk. When the size of the data
+    /// chunk is still 0 or 0xFFFFFFFF, as left by an interrupted recorder, samples are read up
+    /// to the end of the stream and `frames` is estimated from `len`, or 0 without it,
+    /// until the end is reached.
+    ///
+    /// When `lenient`, samples which cannot be read, past the end of a truncated file or at
+    /// a read error, end the data instead of failing, and `corruption` tells what was
+    /// recovered. A data chunk declared larger than a file of known `len` is cut to the
+    /// whole frames it holds right away, so `frames` is the number recovered.
+    pub fn new(inner: R, path: &str, len: Option<u64>, lenient: bool) -> Result<Self, ReadError> {
+        let mut reader = WavReader {
+            inner,
+            path: path.to_string(),
+            offset: 0,
+            format: WavFormat {
+                sample_format: SampleFormat::Int,
+                channels: 0,
+                sample_rate: 0,
+                bits_per_sample: 0,
+                valid_bits_per_sample: 0,
+                channel_mask: 0,
+                block_align: 0,
+            },
+            frames: 0,
+            data_remaining: 0,
+            to_eof: false,
+            frames_read: 0,
+            data_padded: false,
+            trailing_read: false,
+            data_start: 0,
+            lenient: false,
+            corruption: None,
+            metadata: WavMetadata::default(),
+            raw: Vec::new(),
+        };
+        let mut riff = [0u8; 12];
+        reader.read_exact(&mut riff)?;
+        let rf64 = matches!(&riff[0..4], b"RF64" | b"BW64");
+        if !(rf64 || &riff[0..4] == b"RIFF") || &riff[8..12] != b"WAVE" {
+            return Err(reader.error_at(0, ReadErrorKind::Malformed("not a RIFF/WAVE file".into())));
+        }
+        let mut ds64 = Ds64::default();
+        if rf64 {
+            let chunk_offset = reader.offset;
+            let (id, size) = reader.read_chunk_header()?;
+            if &id != b"ds64" {
+                let kind = ReadErrorKind::Malformed("RF64 file without ds64 chunk".into());
+                return Err(reader.error_at(chunk_offset, kind));
+            }
+            ds64 = reader.read_ds64_chunk(size, chunk_offset)?;
+        }
+        let mut has_format = false;
+        loop {
+            let chunk_offset = reader.offset;
+            let (id, size) = reader.read_chunk_header()?;
+            let size = match (size, &id) {
+                (UNKNOWN_SIZE, b"data") => ds64.data_size,
+                (UNKNOWN_SIZE, _) => {
+                    match ds64.table.iter().find(|(table_id, _)| *table_id == id) {
+                        Some((_, size)) => *size,
+                        None => {
+                            let kind = ReadErrorKind::Malformed(format!(
+                                "{} chunk of unknown size",
+                                String::from_utf8_lossy(&id)
+                            ));
+                            return Err(reader.error_at(chunk_offset, kind));
+                        }
+                    }
+                }
+                (size, _) => size as u64,
+            };
+            match &id {
+                b"fmt " => {
+                    reader.read_fmt_chunk(size, chunk_offset)?;
+                    has_format = true;
+                }
+                b"data" => {
+                    if !has_format {
+                        let kind = ReadErrorKind::Malformed("data chunk before fmt chunk".into());
+                        return Err(reader.error_at(chunk_offset, kind));
+                    }
+                    let block_align = reader.format.block_align as u64;
+                    reader.data_start = reader.offset;
+                    reader.lenient = lenient;
+                    if size == 0 {
+                        reader.to_eof = true;
+                        reader.data_remaining = u64::MAX;
+                        reader.frames =
+                            len.map_or(0, |len| len.saturating_sub(reader.offset) / block_align);
+                    } else {
+                        reader.frames = size / block_align;
+                        reader.data_remaining = size;
+                        reader.data_padded = size & 1 == 1;
+                        match len {
+                            Some(len) if lenient && reader.offset + size > len => {
+                                let reason = format!(
+                                    "data chunk of {} bytes runs past the end of the file",
+                                    size
+                                );
+                                reader.recover(len, reason);
+                            }
+                            _ => {}
+                        }
+                    }
+                    return Ok(reader);
+                }
+                _ => reader.read_other_chunk(&id, size)?,
+            }
+        }
+    }
+
+    pub fn format(&self) -> &WavFormat {
+        &self.format
+    }
+
+    /// Number of whole frames declared by the data chunk, or estimated when it has no size.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Metadata chunks read so far: those before the data chunk, and those after it once
+    /// `read_block` reached its end or `skip_data` skipped it.
+    pub fn metadata(&self) -> &WavMetadata {
+        &self.metadata
+    }
+
+    /// Byte offset of the next sample to read, the start of the data chunk right after `new`.
+    pub fn data_offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// What a lenient read recovered of samples which could not all be read.
+    pub fn corruption(&self) -> Option<&Corruption> {
+        self.corruption.as_ref()
+    }
+
+    /// Whether the data chunk has no size, so its samples run to the end of the stream.
+    pub fn data_runs_to_eof(&self) -> bool {
+        self.to_eof
+    }
+
+    /// Whether `frames` is only known once the samples are read to the end of a stream
+    /// of unknown length.
+    pub fn frames_unknown(&self) -> bool {
+        self.to_eof && self.data_remaining != 0 && self.frames == 0
+    }
+
+    /// Decode up to `BLOCK_FRAMES` frames of interleaved samples into `out`.
+    /// Returns the number of frames decoded, 0 at the end of the data chunk.
+    pub fn read_block(&mut self, out: &mut Vec<f32>) -> Result<usize, ReadError> {
+        out.clear();
+        let frame_bytes = self.format.block_align as u64;
+        let frames = (self.data_remaining / frame_bytes).min(BLOCK_FRAMES as u64) as usize;
+        if frames == 0 {
+            self.read_trailing_chunks();
+            return Ok(0);
+        }
+        let mut raw = std::mem::take(&mut self.raw);
+        raw.resize(frames * frame_bytes as usize, 0);
+        let result = if self.to_eof {
+            self.fill(&mut raw).map(|filled| {
+                let frames = filled / frame_bytes as usize;
+                if filled % frame_bytes as usize != 0 && self.lenient {
+                    let end = self.offset - (filled % frame_bytes as usize) as u64;
+                    self.recover(end, "the stream ends inside a frame".into());
+                } else if frames == 0 {
+                    self.data_remaining = 0;
+                    self.frames = self.frames_read;
+                }
+                // A partial frame at the end of the stream is dropped.
+                raw.truncate(frames * frame_bytes as usize);
+                frames
+            })
+        } else if self.lenient {
+            let (start, wanted) = (self.offset, raw.len());
+            match self.fill(&mut raw) {
+                Ok(filled) => {
+                    let frames = filled / frame_bytes as usize;
+                    raw.truncate(frames * frame_bytes as usize);
+                    self.data_remaining -= raw.len() as u64;
+                    if filled < wanted {
+                        let reason = format!("the file ends at byte {}", self.offset);
+                        self.recover(start + raw.len() as u64, reason);
+                    }
+                    Ok(frames)
+                }
+                Err(error) => {
+                    raw.clear();
+                    self.recover(start, error.to_string());
+                    Ok(0)
+                }
+            }
+        } else {
+            self.read_exact(&mut raw).map(|_| {
+                self.data_remaining -= raw.len() as u64;
+                frames
+            })
+        };
+        if let Ok(frames) = result {
+            self.frames_read += frames as u64;
+            decode_samples(&self.format, &raw, out);
+        }
+        self.raw = raw;
+        result
+    }
+
+    /// End the data at byte `end` for the corruption found there, keeping the whole frames
+    /// before it.
+    fn recover(&mut self, end: u64, reason: String) {
+        let frame_bytes = self.format.block_align as u64;
+        let recovered_frames = end.saturating_sub(self.data_start) / frame_bytes;
+        self.corruption = Some(Corruption {
+            offset: self.data_start + recovered_frames * frame_bytes,
+            reason,
+            declared_frames: (!self.to_eof).then_some(self.frames),
+            recovered_frames,
+            recovered_bytes: recovered_frames * frame_bytes,
+            filled_frames: 0,
+        });
+        self.frames = recovered_frames;
+        // What is left of the recovered frames, all of them when cut before reading.
+        self.data_remaining =
+            (self.data_start + recovered_frames * frame_bytes).saturating_sub(self.offset);
+        self.to_eof = false;
+        // Nothing after the corruption is trusted to be a chunk.
+        self.trailing_read = true;
+    }
+
+    fn error_at(&self, offset: u64, kind: ReadErrorKind) -> ReadError {
+        ReadError::new(kind, &self.path, offset)
+    }
+
+    /// Fill `buf`, reporting a short read as truncation at the first missing byte.
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), ReadError> {
+        if self.fill(buf)? < buf.len() {
+            return Err(self.error_at(self.offset, ReadErrorKind::Truncated));
+        }
+        Ok(())
+    }
+
+    /// Read into `buf` until it is full or the stream ends, returning the bytes read.
+    fn fill(&mut self, buf: &mut [u8]) -> Result<usize, ReadError> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.inner.read(&mut buf[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    return Err(ReadError::from_io(
+                        e,
+                        &self.path,
+                        self.offset + filled as u64,
+                    ))
+                }
+            }
+        }
+        self.offset += filled as u64;
+        Ok(filled)
+    }
+
+    fn read_chunk_header(&mut self) -> Result<([u8; 4], u32), ReadError> {
+        let mut header = [0u8; 8];
+        self.read_exact(&mut header)?;
+        let mut id = [0u8; 4];
+        id.copy_from_slice(&header[0..4]);
+        Ok((
+            id,
+            u32::from_le_bytes([header[4], header[5], header[6], header[7]]),
+        ))
+    }
+
+    fn read_ds64_chunk(&mut self, size: u32, chunk_offset: u64) -> Result<Ds64, ReadError> {
+        if size < 28 {
+            let kind = ReadErrorKind::Malformed(format!("ds64 chunk is too short: {} bytes", size));
+            return Err(self.error_at(chunk_offset, kind));
+        }
+        if size as u64 > MAX_DS64_CHUNK {
+            let kind = ReadErrorKind::Malformed(format!("ds64 chunk is too long: {} bytes", size));
+            return Err(self.error_at(chunk_offset, kind));
+        }
+        let mut body = vec![0u8; size as usize + (size as usize & 1)];
+        self.read_exact(&mut body)?;
+        let u64_at = |i: usize| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&body[i..i + 8]);
+            u64::from_le_bytes(bytes)
+        };
+        let table_len = u32::from_le_bytes([body[24], body[25], body[26], body[27]]) as usize;
+        let table = (0..table_len)
+            .map(|i| 28 + i * 12)
+            .take_while(|start| start + 12 <= size as usize)
+            .map(|start| {
+                let mut id = [0u8; 4];
+                id.copy_from_slice(&body[start..start + 4]);
+                (id, u64_at(start + 4))
+            })
+            .collect();
+        Ok(Ds64 {
+            data_size: u64_at(8),
+            table,
+        })
+    }
+
+    fn read_fmt_chunk(&mut self, size: u64, chunk_offset: u64) -> Result<(), ReadError> {
+        if size < 16 {
+            let kind = ReadErrorKind::Malformed(format!("fmt chunk is too short: {} bytes", size));
+            return Err(self.error_at(chunk_offset, kind));
+        }
+        if size > MAX_FMT_CHUNK {
+            let kind = ReadErrorKind::Malformed(format!("fmt chunk is too long: {} bytes", size));
+            return Err(self.error_at(chunk_offset, kind));
+        }
+        let mut body = vec![0u8; size as usize + (size as usize & 1)];
+        self.read_exact(&mut body)?;
+        let u16_at = |i: usize| u16::from_le_bytes([body[i], body[i + 1]]);
+        let u32_at =
+            |i: usize| u32::from_le_bytes([body[i], body[i + 1], body[i + 2], body[i + 3]]);
+        let bits_per_sample = u16_at(14);
+        let (tag, tag_offset, valid_bits_per_sample, channel_mask) = match u16_at(0) {
+            WAVE_FORMAT_EXTENSIBLE => {
+                if size < 40 {
+                    let kind = ReadErrorKind::Malformed(format!(
+                        "extensible fmt chunk is too short: {} bytes",
+                        size
+                    ));
+                    return Err(self.error_at(chunk_offset, kind));
+                }
+                if body[26..40] != SUB_FORMAT_GUID_TAIL {
+                    let kind = ReadErrorKind::Unsupported("non-PCM sub format GUID".into());
+                    return Err(self.error_at(chunk_offset + 32, kind));
+                }
+                (u16_at(24), 32, u16_at(18), u32_at(20))
+            }
+            tag => (tag, 8, bits_per_sample, 0),
+        };
+        let sample_format = match tag {
+            WAVE_FORMAT_PCM => SampleFormat::Int,
+            WAVE_FORMAT_IEEE_FLOAT => SampleFormat::Float,
+            tag => {
+                let kind = ReadErrorKind::Unsupported(format!("format tag 0x{:04x}", tag));
+                return Err(self.error_at(chunk_offset + tag_offset, kind));
+            }
+        };
+        let format = WavFormat {
+            sample_format,
+            channels: u16_at(2),
+            sample_rate: u32_at(4),
+            block_align: u16_at(12),
+            bits_per_sample,
+            // Some writers leave the valid bits at 0 when the whole container is used.
+            valid_bits_per_sample: match valid_bits_per_sample {
+                0 => bits_per_sample,
+                bits => bits,
+            },
+            channel_mask,
+        };
+        let supported = match format.sample_format {
+            SampleFormat::Int => matches!(format.bits_per_sample, 8 | 16 | 24 | 32),
+            SampleFormat::Float => matches!(format.bits_per_sample, 32 | 64),
+        };
+        if !supported {
+            let kind = ReadErrorKind::Unsupported(format!(
+                "{} bit {} samples",
+                format.bits_per_sample,
+                format.sample_format.name()
+            ));
+            return Err(self.error_at(chunk_offset + 22, kind));
+        }
+        if format.channels == 0 || format.sample_rate == 0 {
+            let kind = ReadErrorKind::Malformed("no channels or zero sample rate".into());
+            return Err(self.error_at(chunk_offset + 10, kind));
+        }
+        if format.valid_bits_per_sample > format.bits_per_sample {
+            let kind = ReadErrorKind::Malformed(format!(
+                "{} valid bits in a {} bit container",
+                format.valid_bits_per_sample, format.bits_per_sample
+            ));
+            return Err(self.error_at(chunk_offset + 26, kind));
+        }
+        if format.block_align as usize != format.channels as usize * format.bytes_per_sample() {
+            let kind = ReadErrorKind::Malformed(format!(
+                "block align {} does not match channels and bit depth",
+                format.block_align
+            ));
+            return Err(self.error_at(chunk_offset + 20, kind));
+        }
+        self.format = format;
+        Ok(())
+    }
+
+    /// Read a chunk other than `fmt ` and `data`, keeping it if it is metadata.
+    fn read_other_chunk(&mut self, id: &[u8; 4], size: u64) -> Result<(), ReadError> {
+        let padded = size + (size & 1);
+        if WavMetadata::is_metadata(id) && size <= MAX_METADATA_CHUNK {
+            let mut body = vec![0u8; padded as usize];
+            self.read_exact(&mut body)?;
+            self.metadata.read_chunk(id, &body[..size as usize]);
+            Ok(())
+        } else {
+            self.skip(padded)
+        }
+    }
+
+    /// Read the metadata chunks following the data chunk. Whatever cannot be read there,
+    /// such as garbage left by a writer, ends them without an error.
+    fn read_trailing_chunks(&mut self) {
+        if self.trailing_read || self.to_eof {
+            return;
+        }
+        self.trailing_read = true;
+        // Bytes short of a whole frame at the end of the data chunk, then its pad byte.
+        let rest = self.data_remaining + self.data_padded as u64;
+        self.data_remaining = 0;
+        if rest != 0 && self.skip(rest).is_err() {
+            return;
+        }
+        let mut header = [0u8; 8];
+        while let Ok(8) = self.fill(&mut header) {
+            let id = [header[0], header[1], header[2], header[3]];
+            let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+            if size == UNKNOWN_SIZE || self.read_other_chunk(&id, size as u64).is_err() {
+                break;
+            }
+        }
+    }
+
+    fn skip(&mut self, len: u64) -> Result<(), ReadError> {
+        let skipped = io::copy(&mut (&mut self.inner).take(len), &mut io::sink())
+            .map_err(|e| ReadError::from_io(e, &self.path, self.offset))?;
+        self.offset += skipped;
+        if skipped < len {
+            return Err(self.error_at(self.offset, ReadErrorKind::Truncated));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> WavReader<R> {
+    /// Move past the rest of the data chunk without decoding it, seeking when the stream
+    /// allows it and reading through otherwise, and read the chunks after it.
+    pub fn skip_data(&mut self) -> Result<(), ReadError> {
+        if self.to_eof {
+            return Ok(());
+        }
+        let len = self.data_remaining;
+        match self.inner.seek(SeekFrom::Current(len as i64)) {
+            Ok(_) => self.offset += len,
+            Err(_) => match self.skip(len) {
+                Err(_) if self.lenient => {
+                    let reason = format!("the file ends at byte {}", self.offset);
+                    self.recover(self.offset, reason);
+                    return Ok(());
+                }
+                result => result?,
+            },
+        }
+        self.data_remaining = 0;
+        self.read_trailing_chunks();
+        Ok(())
+    }
+}
+
+/// Running analysis fed with interleaved sample blocks by `AudioReader::analyze`.
+pub trait Analyzer {
+    fn process(&mut self, samples: &[f32], channels: usize);
+
+    /// Called once after the last block.
+    fn finish(&mut self) {}
+}
+
+/// Decode little-endian samples so that full scale is 1.0 whatever the layout.
+/// Integers are divided by 2^(bits - 1) of their container, 8-bit ones being unsigned
+/// with 128 as zero, so padding below the valid bits does not change the scale.
+/// Floats are taken as they are.
+pub fn decode_samples(format: &WavFormat, raw: &[u8], out: &mut Vec<f32>) {
+    let width = format.bytes_per_sample();
+    let chunks = raw.chunks_exact(width);
+    match (format.sample_format, width) {
+        (SampleFormat::Int, 1) => out.extend(chunks.map(|b| (b[0] as f32 - 128.0) / 128.0)),
+        (SampleFormat::Int, 2) => {
+            out.extend(chunks.map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0))
+        }
+        (SampleFormat::Int, 3) => out.extend(
+            chunks.map(|b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8388608.0),
+        ),
+        (SampleFormat::Int, 4) => out.extend(
+            chunks.map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2147483648.0),
+        ),
+        (SampleFormat::Float, 4) => {
+            out.extend(chunks.map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])))
+        }
+        (SampleFormat::Float, 8) => {
+            out.extend(chunks.map(|b| {
+                f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as f32
+            }))
+        }
+        _ => unreachable!("layout is validated in read_fmt_chunk"),
+    }
+}
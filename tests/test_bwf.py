@@ -0,0 +1,126 @@
+import datetime
+import io
+import struct
+import subprocess
+
+import pytest
+
+import get_loudness_from_wav
+from lib.cmcut import BroadcastClock
+from tests.wav_files import write_wav
+
+
+SAMPLES = [16384] * 10 + [0] * 30
+
+
+def chunk(chunk_id, body):
+    return chunk_id + struct.pack("<I", len(body)) + body + b"\0" * (len(body) % 2)
+
+
+def bext_chunk(date=b"2024-04-01", time=b"21:00:00", time_reference=0):
+    body = b"News".ljust(256, b"\0") + b"capture".ljust(32, b"\0") + bytes(32)
+    body += date + time + struct.pack("<QH", time_reference, 2)
+    body += bytes(602 - len(body)) + b"A=PCM,F=8000\r\n"
+    return chunk(b"bext", body)
+
+
+def cue_chunks(points):
+    cue = struct.pack("<I", len(points))
+    adtl = b"adtl"
+    for cue_id, position, label, length in points:
+        cue += struct.pack("<II4sIII", cue_id, position, b"data", 0, 0, position)
+        adtl += chunk(b"labl", struct.pack("<I", cue_id) + label + b"\0")
+        if length is not None:
+            adtl += chunk(b"ltxt", struct.pack("<II4sHHHH", cue_id, length, b"rgn ", 0, 0, 0, 0))
+    return chunk(b"cue ", cue) + chunk(b"LIST", adtl)
+
+
+def write_bwf(path, samples, before_data=b"", after_data=b""):
+    data = open(write_wav(path, samples), "rb").read()
+    body = data[12:36] + before_data + data[36:] + after_data
+    with open(path, "wb") as wav_file:
+        wav_file.write(b"RIFF" + struct.pack("<I", 4 + len(body)) + b"WAVE" + body)
+    return str(path)
+
+
+CUE_POINTS = [(1, 10, b"CM start", 20), (2, 30, b"Program", None)]
+
+
+class TestBroadcastWav:
+    def test_bext(self, tmp_path):
+        wav_path = write_bwf(tmp_path / "a.wav", SAMPLES, bext_chunk(time_reference=8000 * 3600))
+        info = get_loudness_from_wav.wav_info(wav_path)
+        assert info.bext.description == "News"
+        assert info.bext.originator == "capture"
+        assert (info.bext.origination_date, info.bext.origination_time) == ("2024-04-01", "21:00:00")
+        assert info.bext.time_reference == 8000 * 3600
+        assert info.bext.time_reference_sec == 3600
+        assert info.bext.version == 2
+        assert info.bext.coding_history == "A=PCM,F=8000"
+        assert info.cue_points == []
+        loudness, info = get_loudness_from_wav.wav2loudness_with_info(wav_path)
+        assert list(loudness) == list(get_loudness_from_wav.wav2loudness(write_wav(tmp_path / "b.wav", SAMPLES)))
+        assert info.bext.time_reference == 8000 * 3600
+
+    def test_cue_points_after_data(self, tmp_path):
+        wav_path = write_bwf(tmp_path / "a.wav", SAMPLES, after_data=cue_chunks(CUE_POINTS))
+        expected = [(1, 10, 10 / 8000, "CM start", 20), (2, 30, 30 / 8000, "Program", None)]
+        infos = [
+            get_loudness_from_wav.wav_info(wav_path),
+            get_loudness_from_wav.wav2loudness_with_info(wav_path)[1],
+            get_loudness_from_wav.wav_info(io.BytesIO(open(wav_path, "rb").read())),
+            get_loudness_from_wav.WavView(wav_path).info,
+            ]
+        for info in infos:
+            assert info.frames == len(SAMPLES)
+            assert [
+                (cue.id, cue.position, cue.position_sec, cue.label, cue.length) for cue in info.cue_points
+                ] == expected
+        process = subprocess.Popen(["cat", wav_path], stdout=subprocess.PIPE)
+        info = get_loudness_from_wav.wav_info(process.stdout)
+        process.wait()
+        assert [cue.label for cue in info.cue_points] == ["CM start", "Program"]
+
+    def test_cue_points_after_partial_frame(self, tmp_path):
+        # 16 bit stereo data of 2 frames and 3 bytes, then a pad byte.
+        data = open(write_wav(tmp_path / "a.wav", [0, 0, 16384, 16384], channels=2), "rb").read()
+        body = data[12:40] + chunk(b"data", data[44:] + b"\1\2\3")[4:] + cue_chunks(CUE_POINTS)
+        wav_path = tmp_path / "b.wav"
+        wav_path.write_bytes(b"RIFF" + struct.pack("<I", 4 + len(body)) + b"WAVE" + body)
+        for info in [
+            get_loudness_from_wav.wav_info(str(wav_path)),
+            get_loudness_from_wav.wav2loudness_with_info(str(wav_path))[1],
+            ]:
+            assert info.frames == 2
+            assert [cue.label for cue in info.cue_points] == ["CM start", "Program"]
+
+    def test_garbage_after_data(self, tmp_path):
+        after_data = cue_chunks(CUE_POINTS[:1]) + b"\xff" * 5
+        wav_path = write_bwf(tmp_path / "a.wav", SAMPLES, after_data=after_data)
+        info = get_loudness_from_wav.wav_info(wav_path)
+        assert [cue.label for cue in info.cue_points] == ["CM start"]
+        short_bext = chunk(b"bext", bytes(100))
+        wav_path = write_bwf(tmp_path / "b.wav", SAMPLES, short_bext)
+        assert get_loudness_from_wav.wav_info(wav_path).bext is None
+
+
+class TestBroadcastClock:
+    def test_time_reference(self, tmp_path):
+        wav_path = write_bwf(tmp_path / "a.wav", SAMPLES, bext_chunk(time_reference=8000 * 3600))
+        clock = BroadcastClock.from_info(get_loudness_from_wav.wav_info(wav_path))
+        assert clock.start == datetime.datetime(2024, 4, 1, 1, 0, 0)
+        assert clock.at(90) == datetime.datetime(2024, 4, 1, 1, 1, 30)
+
+    def test_origination_time(self, tmp_path):
+        wav_path = write_bwf(tmp_path / "a.wav", SAMPLES, bext_chunk())
+        clock = BroadcastClock.from_info(get_loudness_from_wav.wav_info(wav_path))
+        assert clock.start == datetime.datetime(2024, 4, 1, 21, 0, 0)
+        assert BroadcastClock.from_info(get_loudness_from_wav.wav_info(write_wav(tmp_path / "b.wav", SAMPLES))) is None
+
+    def test_unmatched_cue_points(self, tmp_path):
+        wav_path = write_bwf(tmp_path / "a.wav", SAMPLES, after_data=cue_chunks(CUE_POINTS))
+        cue_points = get_loudness_from_wav.wav_info(wav_path).cue_points
+        unmatched = BroadcastClock.unmatched_cue_points([0.0012], cue_points, 0.0005)
+        assert [cue.label for cue in unmatched] == ["Program"]
+        with pytest.raises(ValueError):
+            BroadcastClock.unmatched_cue_points([], cue_points, -1)
@@ -0,0 +1,93 @@
+import os
+
+import pytest
+
+import get_loudness_from_wav
+from lib.cmcut import ProgramScenes
+from tests.test_bwf import bext_chunk, cue_chunks, write_bwf
+from tests.wav_files import write_wav
+
+
+SAMPLES = [16384] * 10 + [0] * 30 + [-8192] * 9
+
+
+def data_chunk(path):
+    data = open(path, "rb").read()
+    start = data.index(b"data")
+    size = int.from_bytes(data[start + 4:start + 8], "little")
+    return data[start:start + 8 + size]
+
+
+class TestWriteCuePoints:
+    def test_markers(self, tmp_path):
+        wav_path = write_bwf(tmp_path / "a.wav", SAMPLES, bext_chunk(time_reference=8000))
+        output_path = str(tmp_path / "b.wav")
+        get_loudness_from_wav.write_cue_points(wav_path, output_path, [(10, "CM", 30), (40, "program")])
+        info = get_loudness_from_wav.wav_info(output_path)
+        assert [(cue.id, cue.position, cue.label, cue.length) for cue in info.cue_points] == [
+            (1, 10, "CM", 30), (2, 40, "program", None),
+            ]
+        assert info.bext.time_reference == 8000
+        assert data_chunk(output_path) == data_chunk(wav_path)
+        assert list(get_loudness_from_wav.wav2loudness(output_path)) == list(get_loudness_from_wav.wav2loudness(wav_path))
+        assert len(os.listdir(tmp_path)) == 2
+
+    def test_merge_cue_points(self, tmp_path):
+        after_data = cue_chunks([(3, 5, b"old", 10)]) + b"\xff" * 5
+        wav_path = write_bwf(tmp_path / "a.wav", SAMPLES, after_data=after_data)
+        for _ in range(2):
+            get_loudness_from_wav.write_cue_points(wav_path, wav_path, [(20, "new"), (5, "old", 10)])
+            info = get_loudness_from_wav.wav_info(wav_path)
+            assert [(cue.id, cue.position, cue.label, cue.length) for cue in info.cue_points] == [
+                (3, 5, "old", 10), (4, 20, "new", None),
+                ]
+            assert info.frames == len(SAMPLES)
+        assert len(os.listdir(tmp_path)) == 1
+
+    def test_data_without_size(self, tmp_path):
+        wav_path = write_wav(tmp_path / "a.wav", SAMPLES)
+        data = bytearray(open(wav_path, "rb").read())
+        samples = bytes(data[44:])
+        output_path = str(tmp_path / "b.wav")
+        for size in [0, 0xFFFFFFFF]:
+            data[40:44] = size.to_bytes(4, "little")
+            # A partial frame left at the end by the interrupted recorder.
+            open(wav_path, "wb").write(data + b"\x01")
+            get_loudness_from_wav.write_cue_points(wav_path, output_path, [(10, "CM", 30)])
+            info = get_loudness_from_wav.wav_info(output_path)
+            assert [(cue.position, cue.label, cue.length) for cue in info.cue_points] == [(10, "CM", 30)]
+            assert info.frames == len(SAMPLES)
+            assert data_chunk(output_path) == b"data" + len(samples).to_bytes(4, "little") + samples
+
+    def test_stale_temp_file(self, tmp_path):
+        wav_path = write_wav(tmp_path / "a.wav", SAMPLES)
+        # Left by a crashed run of a process which had the same PID.
+        (tmp_path / f".b.wav.{os.getpid()}.tmp").write_bytes(b"stale")
+        output_path = str(tmp_path / "b.wav")
+        get_loudness_from_wav.write_cue_points(wav_path, output_path, [(10, "CM")])
+        assert [cue.label for cue in get_loudness_from_wav.wav_info(output_path).cue_points] == ["CM"]
+        assert sorted(os.listdir(tmp_path)) == ["a.wav", "b.wav"]
+
+    def test_invalid(self, tmp_path):
+        wav_path = write_wav(tmp_path / "a.wav", SAMPLES)
+        output_path = tmp_path / "b.wav"
+        with pytest.raises(ValueError) as e:
+            get_loudness_from_wav.write_cue_points(wav_path, str(output_path), [(40, "CM", 10)])
+        assert "Marker out of the data:" in str(e.value)
+        with pytest.raises(get_loudness_from_wav.WavNotFoundError):
+            get_loudness_from_wav.write_cue_points(str(tmp_path / "missing.wav"), str(output_path), [])
+        with pytest.raises(OSError):
+            get_loudness_from_wav.write_cue_points(wav_path, str(tmp_path / "missing" / "b.wav"), [])
+        assert not output_path.exists()
+        assert os.listdir(tmp_path) == ["a.wav"]
+
+
+class TestProgramScenesCuePoints:
+    def test_scenes_and_cms(self, tmp_path):
+        wav_path = write_wav(tmp_path / "a.wav", SAMPLES)
+        output_path = str(tmp_path / "b.wav")
+        ProgramScenes([(0, 0.00125), (0.005, 0.01)]).write_cue_points(wav_path, output_path)
+        cue_points = get_loudness_from_wav.wav_info(output_path).cue_points
+        assert [(cue.position, cue.label, cue.length) for cue in cue_points] == [
+            (0, "program 0", 10), (10, "CM", 30), (40, "program 1", 9),
+            ]
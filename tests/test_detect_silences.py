@@ -0,0 +1,64 @@
+import pytest
+
+import get_loudness_from_wav
+from tests.wav_files import write_wav
+
+
+class TestDetectSilences:
+    def test_digital_zero(self, tmp_path):
+        wav_path = write_wav(tmp_path / "zero.wav", [5] * 5 + [0] * 20 + [1] + [0] * 5 + [5] + [0] * 30)
+        silences = get_loudness_from_wav.detect_silences(wav_path, min_duration=10)
+        assert silences.sections == [(5, 25)]
+        assert silences.frame_per_sec == 8000
+
+    def test_threshold_dbfs(self, tmp_path):
+        samples = [10000] * 5 + [10] * 20 + [10000] * 2 + [10] * 20 + [10000] * 10 + [10] * 3
+        wav_path = write_wav(tmp_path / "noise.wav", samples)
+        assert get_loudness_from_wav.detect_silences(wav_path, min_duration=10).sections == []
+        silences = get_loudness_from_wav.detect_silences(wav_path, -60, 10)
+        assert silences.sections == [(5, 25), (27, 47)]
+        silences = get_loudness_from_wav.detect_silences(wav_path, -60, 10, max_blip=2)
+        assert silences.sections == [(5, 47)]
+
+    def test_hysteresis(self, tmp_path):
+        samples = [10000] * 5 + [50] * 5 + [10] * 20 + [50] * 5 + [10000] * 5
+        wav_path = write_wav(tmp_path / "fade.wav", samples)
+        silences = get_loudness_from_wav.detect_silences(wav_path, -70, 10)
+        assert silences.sections == [(10, 30)]
+        silences = get_loudness_from_wav.detect_silences(wav_path, -70, 10, exit_threshold_dbfs=-50)
+        assert silences.sections == [(10, 35)]
+        with pytest.raises(ValueError) as e:
+            get_loudness_from_wav.detect_silences(wav_path, -50, 10, exit_threshold_dbfs=-70)
+        assert "exit_threshold_dbfs must not be lower than threshold_dbfs:" in str(e.value)
+
+    def test_auto_threshold(self, tmp_path):
+        noise = [(-1) ** i * (10 - i % 4) for i in range(800)]
+        samples = [10000] * 800 + noise + [10000] * 800 + noise + [10000] * 800
+        wav_path = write_wav(tmp_path / "noise.wav", samples)
+        assert get_loudness_from_wav.detect_silences(wav_path, min_duration=100).sections == []
+        silences = get_loudness_from_wav.detect_silences(wav_path, "auto", 100)
+        assert silences.sections == [(800, 1600), (2400, 3200)]
+        assert silences.threshold_dbfs == pytest.approx(-64.5)
+        with pytest.raises(ValueError) as e:
+            get_loudness_from_wav.detect_silences(wav_path, "loud", 100)
+        assert "Silence threshold must be None, a level in dBFS or 'auto':" in str(e.value)
+
+
+class TestEstimateNoiseFloor:
+    def test_noise_floor(self, tmp_path):
+        noise = [(-1) ** i * (10 - i % 4) for i in range(800)]
+        wav_path = write_wav(tmp_path / "noise.wav", [10000] * 800 + noise * 3)
+        noise_floor = get_loudness_from_wav.estimate_noise_floor(wav_path)
+        assert noise_floor.noise_floor_dbfs == pytest.approx(-70.5)
+        assert noise_floor.threshold_dbfs == pytest.approx(-64.5)
+        assert noise_floor.digital_silence_ratio == 0
+        noise_floor = get_loudness_from_wav.estimate_noise_floor(wav_path, margin_db=10)
+        assert noise_floor.threshold_dbfs == pytest.approx(-60.5)
+
+    def test_digital_silence(self, tmp_path):
+        wav_path = write_wav(tmp_path / "zero.wav", [10000] * 800 + [0] * 800)
+        noise_floor = get_loudness_from_wav.estimate_noise_floor(wav_path)
+        assert noise_floor.noise_floor_dbfs is None
+        assert noise_floor.threshold_dbfs is None
+        assert noise_floor.digital_silence_ratio == 0.5
+        assert get_loudness_from_wav.detect_silences(wav_path, "auto").threshold_dbfs is None
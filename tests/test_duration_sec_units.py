@@ -0,0 +1,55 @@
+import pickle
+
+import pytest
+
+from lib.cmcut import DurationSecUnits
+
+class TestDurationSecUnits:
+    def test_init(self):
+        """Test init."""
+        assert DurationSecUnits([]).durations_sec == [15, 30]
+        assert DurationSecUnits([60, 5, 30.0, 60]).durations_sec == [5, 15, 30, 60]
+        assert DurationSecUnits([0.5]).durations_sec == [0.5, 15, 30]
+
+    def test_init_fail(self):
+        """Test init with failure."""
+        with pytest.raises(TypeError) as e:
+            DurationSecUnits(["hoge"])
+        assert "Element of duration units must be integer or float:" in str(e.value)
+        with pytest.raises(TypeError) as e:
+            DurationSecUnits([True])
+        assert "Element of duration units must be integer or float:" in str(e.value)
+        with pytest.raises(ValueError) as e:
+            DurationSecUnits([0])
+        assert "CM durations must be positive value:" in str(e.value)
+
+    def test_immutable(self):
+        """Test that new units are returned and the original ones are kept."""
+        units = DurationSecUnits([])
+        appended = units.append_duration(60)
+        assert appended.durations_sec == [15, 30, 60]
+        assert units.durations_sec == [15, 30]
+        assert appended.append_duration(60) == appended
+        removed = appended.remove_duration(60)
+        assert removed.durations_sec == [15, 30]
+        assert appended.durations_sec == [15, 30, 60]
+        assert removed.remove_duration(45) == removed
+        # The 15 and 30 sec defaults are kept by every set of units.
+        assert appended.remove_duration(15) == appended
+        units.durations_sec.append(45)
+        assert units.durations_sec == [15, 30]
+        with pytest.raises(ValueError):
+            units.append_duration(-1)
+
+    def test_value(self):
+        """Test comparison, hashing and pickling."""
+        units = DurationSecUnits([60, 90]).remove_duration(90)
+        assert units == DurationSecUnits([60])
+        assert units != DurationSecUnits([90])
+        assert len({units, DurationSecUnits([60])}) == 1
+        assert 60 in units and 90 not in units
+        assert len(units) == 3
+        restored = pickle.loads(pickle.dumps(units))
+        assert restored == units
+        assert restored.durations_sec == [15, 30, 60]
+        assert not hasattr(restored, "__setstate__")
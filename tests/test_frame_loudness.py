@@ -0,0 +1,461 @@
+import math
+import struct
+import subprocess
+
+import numpy as np
+import pytest
+
+import get_loudness_from_wav
+from lib.cmcut import FrameLoudness, ProgramScenes
+from tests.wav_files import write_wav
+
+
+def write_pcm(path, sample_bytes, format_tag, bits, channels=1, frame_rate=8000,
+              valid_bits=None, channel_mask=None):
+    block_align = channels * bits // 8
+    fmt = struct.pack(
+        "<HHIIHH", 0xFFFE if channel_mask is not None else format_tag,
+        channels, frame_rate, frame_rate * block_align, block_align, bits,
+        )
+    if channel_mask is not None:
+        sub_format = struct.pack("<H", format_tag) + bytes.fromhex("000000001000800000aa00389b71")
+        fmt += struct.pack("<HHI", 22, valid_bits or bits, channel_mask) + sub_format
+    chunks = b"fmt " + struct.pack("<I", len(fmt)) + fmt
+    chunks += b"data" + struct.pack("<I", len(sample_bytes)) + sample_bytes
+    with open(path, "wb") as wav_file:
+        wav_file.write(b"RIFF" + struct.pack("<I", 4 + len(chunks)) + b"WAVE" + chunks)
+    return str(path)
+
+
+def write_rf64(path, samples, magic=b"RF64"):
+    data = open(write_wav(path, samples), "rb").read()
+    fmt_chunk, sample_bytes = data[12:36], data[44:]
+    ds64_chunk = b"ds64" + struct.pack("<IQQQI", 28, 0, len(sample_bytes), len(samples), 0)
+    with open(path, "wb") as wav_file:
+        wav_file.write(magic + struct.pack("<I", 0xFFFFFFFF) + b"WAVE" + ds64_chunk + fmt_chunk)
+        wav_file.write(b"data" + struct.pack("<I", 0xFFFFFFFF) + sample_bytes)
+    return str(path)
+
+
+class TestWav2Loudness:
+    def test_wav2loudness(self, tmp_path):
+        wav_path = write_wav(tmp_path / "short.wav", [0, 16384, -16384, 0, -32768])
+        loudness = get_loudness_from_wav.wav2loudness(wav_path)
+        assert isinstance(loudness, np.ndarray)
+        assert loudness.dtype == np.float32
+        assert isinstance(loudness.base, get_loudness_from_wav.LoudnessBuffer)
+        assert list(loudness) == [0, 0.5, 0.5, 0, 1]
+
+    def test_wav2loudness_spans_blocks(self, tmp_path):
+        samples = [0] * 5000 + [8192] * 5000
+        wav_path = write_wav(tmp_path / "long.wav", samples)
+        loudness = list(get_loudness_from_wav.wav2loudness(wav_path))
+        assert len(loudness) == 10000
+        assert loudness[:5000] == [0] * 5000
+        assert loudness[5000:] == [0.25] * 5000
+
+    def test_wav2loudness_8bit(self, tmp_path):
+        wav_path = write_wav(tmp_path / "8bit.wav", [128, 192, 64], sample_width=1)
+        assert list(get_loudness_from_wav.wav2loudness(wav_path)) == [0, 0.5, 0.5]
+
+
+class TestWavErrors:
+    def test_not_found(self, tmp_path):
+        wav_path = str(tmp_path / "missing.wav")
+        with pytest.raises(get_loudness_from_wav.WavNotFoundError) as e:
+            get_loudness_from_wav.wav2loudness(wav_path)
+        assert e.value.path == wav_path
+        assert e.value.offset == 0
+        assert isinstance(e.value, OSError)
+
+    def test_malformed(self, tmp_path):
+        wav_path = tmp_path / "text.wav"
+        wav_path.write_bytes(b"RIFX\x00\x00\x00\x00WAVEfmt ")
+        with pytest.raises(get_loudness_from_wav.MalformedWavError) as e:
+            get_loudness_from_wav.wav_info(str(wav_path))
+        assert e.value.offset == 0
+
+    def test_fmt_too_long(self, tmp_path):
+        wav_path = write_wav(tmp_path / "huge_fmt.wav", [0, 1])
+        data = bytearray(open(wav_path, "rb").read())
+        data[16:20] = struct.pack("<I", 0xFFFFFFF0)
+        open(wav_path, "wb").write(data)
+        with pytest.raises(get_loudness_from_wav.MalformedWavError) as e:
+            get_loudness_from_wav.wav_info(wav_path)
+        assert e.value.offset == 12
+        assert "fmt chunk is too long" in str(e.value)
+
+    def test_unsupported(self, tmp_path):
+        wav_path = write_wav(tmp_path / "alaw.wav", [0, 1])
+        data = bytearray(open(wav_path, "rb").read())
+        data[20:22] = struct.pack("<H", 6)
+        open(wav_path, "wb").write(data)
+        with pytest.raises(get_loudness_from_wav.UnsupportedWavFormatError) as e:
+            get_loudness_from_wav.wav2loudness(wav_path)
+        assert e.value.offset == 20
+        assert "format tag 0x0006" in str(e.value)
+
+    def test_truncated(self, tmp_path):
+        wav_path = write_wav(tmp_path / "cut.wav", [0] * 100)
+        data = open(wav_path, "rb").read()
+        open(wav_path, "wb").write(data[:-11])
+        with pytest.raises(get_loudness_from_wav.TruncatedWavError) as e:
+            get_loudness_from_wav.wav2loudness(wav_path)
+        assert e.value.path == wav_path
+        assert e.value.offset == len(data) - 11
+        assert issubclass(get_loudness_from_wav.TruncatedWavError, get_loudness_from_wav.WavError)
+
+
+class TestPcmFormats:
+    def test_int(self, tmp_path):
+        samples = [0, 1 << 22, -(1 << 23), (1 << 23) - 1]
+        sample_bytes = b"".join(x.to_bytes(3, "little", signed=True) for x in samples)
+        wav_path = write_pcm(tmp_path / "24bit.wav", sample_bytes, 1, 24)
+        assert list(get_loudness_from_wav.wav2loudness(wav_path)[:3]) == [0, 0.5, 1]
+        wav_path = write_pcm(tmp_path / "32bit.wav", struct.pack("<3i", 0, 1 << 30, -(1 << 31)), 1, 32)
+        assert list(get_loudness_from_wav.wav2loudness(wav_path)) == [0, 0.5, 1]
+
+    def test_float(self, tmp_path):
+        wav_path = write_pcm(tmp_path / "float32.wav", struct.pack("<3f", 0, 0.5, -1), 3, 32)
+        assert list(get_loudness_from_wav.wav2loudness(wav_path)) == [0, 0.5, 1]
+        wav_path = write_pcm(tmp_path / "float64.wav", struct.pack("<3d", 0, -0.5, 1), 3, 64)
+        assert list(get_loudness_from_wav.wav2loudness(wav_path)) == [0, 0.5, 1]
+        assert get_loudness_from_wav.wav_info(wav_path).sample_format == "float"
+
+    def test_extensible(self, tmp_path):
+        # 24 valid bits in a 32 bit container, front left and right.
+        sample_bytes = struct.pack("<4i", 1 << 30, 0, 0, -(1 << 31))
+        wav_path = write_pcm(
+            tmp_path / "extensible.wav", sample_bytes, 1, 32, channels=2, valid_bits=24, channel_mask=0x3,
+            )
+        assert list(get_loudness_from_wav.wav2loudness(wav_path, "all")) == [0.5, 1]
+        info = get_loudness_from_wav.wav_info(wav_path)
+        assert info.channel_mask == 0x3
+        assert info.bits_per_sample == 32
+        assert info.valid_bits_per_sample == 24
+        wav_path = write_pcm(
+            tmp_path / "extensible_float.wav", struct.pack("<2f", 0.25, 0), 3, 32, channel_mask=0x4,
+            )
+        assert list(get_loudness_from_wav.wav2loudness(wav_path)) == [0.25, 0]
+        assert get_loudness_from_wav.wav_info(write_wav(tmp_path / "plain.wav", [0])).channel_mask is None
+
+    def test_extensible_unsupported(self, tmp_path):
+        wav_path = write_pcm(tmp_path / "alaw.wav", b"\x00\x00", 6, 8, channel_mask=0x4)
+        with pytest.raises(get_loudness_from_wav.UnsupportedWavFormatError) as e:
+            get_loudness_from_wav.wav2loudness(wav_path)
+        assert e.value.offset == 44
+        wav_path = write_pcm(tmp_path / "wide.wav", b"\x00\x00", 1, 16, valid_bits=20, channel_mask=0x4)
+        with pytest.raises(get_loudness_from_wav.MalformedWavError):
+            get_loudness_from_wav.wav2loudness(wav_path)
+
+
+class TestLargeWav:
+    def test_rf64(self, tmp_path):
+        for magic in [b"RF64", b"BW64"]:
+            wav_path = write_rf64(tmp_path / "rf64.wav", [0, 16384, -16384, 0, -32768], magic)
+            assert list(get_loudness_from_wav.wav2loudness(wav_path)) == [0, 0.5, 0.5, 0, 1]
+            assert get_loudness_from_wav.wav_info(wav_path).frames == 5
+
+    def test_rf64_without_ds64(self, tmp_path):
+        wav_path = write_wav(tmp_path / "rf64.wav", [0, 1])
+        data = open(wav_path, "rb").read()
+        open(wav_path, "wb").write(b"RF64" + data[4:])
+        with pytest.raises(get_loudness_from_wav.MalformedWavError) as e:
+            get_loudness_from_wav.wav_info(wav_path)
+        assert e.value.offset == 12
+
+    def test_ds64_too_long(self, tmp_path):
+        wav_path = write_rf64(tmp_path / "rf64.wav", [0, 1])
+        data = bytearray(open(wav_path, "rb").read())
+        data[16:20] = struct.pack("<I", 0xFFFFFFF0)
+        open(wav_path, "wb").write(data)
+        with pytest.raises(get_loudness_from_wav.MalformedWavError) as e:
+            get_loudness_from_wav.wav_info(wav_path)
+        assert e.value.offset == 12
+        assert "ds64 chunk is too long" in str(e.value)
+
+    def test_unknown_data_size(self, tmp_path):
+        for data_size in [0, 0xFFFFFFFF]:
+            wav_path = write_wav(tmp_path / "unfinished.wav", [0, 16384, -16384])
+            data = bytearray(open(wav_path, "rb").read())
+            data[4:8] = struct.pack("<I", data_size)
+            data[40:44] = struct.pack("<I", data_size)
+            # Samples written after the header, ending with a partial frame.
+            open(wav_path, "wb").write(data + struct.pack("<2h", -32768, 8192)[:3])
+            assert get_loudness_from_wav.wav_info(wav_path).frames == 4
+            assert list(get_loudness_from_wav.wav2loudness(wav_path)) == [0, 0.5, 0.5, 1]
+
+
+class TestLenient:
+    def write_cut_wav(self, tmp_path):
+        samples = [0] * 50 + [16384] * 50
+        wav_path = write_wav(tmp_path / "cut.wav", samples)
+        data = open(wav_path, "rb").read()
+        # Ends in the middle of the 95th sample.
+        open(wav_path, "wb").write(data[:-11])
+        return wav_path
+
+    def assert_corruption(self, info):
+        assert info.frames == 94
+        assert info.corruption.offset == 44 + 188
+        assert info.corruption.declared_frames == 100
+        assert info.corruption.recovered_frames == 94
+        assert info.corruption.recovered_bytes == 188
+
+    def test_truncated(self, tmp_path):
+        wav_path = self.write_cut_wav(tmp_path)
+        loudness, info = get_loudness_from_wav.wav2loudness_with_info(wav_path, lenient=True)
+        assert list(loudness) == [0] * 50 + [0.5] * 44
+        self.assert_corruption(info)
+        assert "runs past the end of the file" in info.corruption.reason
+        self.assert_corruption(get_loudness_from_wav.wav_info(wav_path, lenient=True))
+        self.assert_corruption(get_loudness_from_wav.wav_info(open(wav_path, "rb"), lenient=True))
+        self.assert_corruption(get_loudness_from_wav.WavView(wav_path, lenient=True).info)
+        silences = get_loudness_from_wav.detect_silences(wav_path, min_duration=10, lenient=True)
+        assert silences.sections == [(0, 50)]
+        self.assert_corruption(silences.info)
+        assert get_loudness_from_wav.wav_info(write_wav(tmp_path / "a.wav", [0] * 10), lenient=True).corruption is None
+
+    def test_truncated_pipe(self, tmp_path):
+        wav_path = self.write_cut_wav(tmp_path)
+        process = subprocess.Popen(["cat", wav_path], stdout=subprocess.PIPE)
+        loudness, info = get_loudness_from_wav.wav2loudness_with_info(process.stdout, lenient=True)
+        process.wait()
+        assert len(loudness) == 94
+        self.assert_corruption(info)
+        assert info.corruption.reason == "the file ends at byte 233"
+        process = subprocess.Popen(["cat", wav_path], stdout=subprocess.PIPE)
+        self.assert_corruption(get_loudness_from_wav.wav_info(process.stdout, lenient=True))
+        process.wait()
+
+    def test_unknown_data_size(self, tmp_path):
+        wav_path = write_wav(tmp_path / "unfinished.wav", [0, 16384, -16384])
+        data = bytearray(open(wav_path, "rb").read())
+        data[40:44] = struct.pack("<I", 0)
+        open(wav_path, "wb").write(data + b"\x00")
+        loudness, info = get_loudness_from_wav.wav2loudness_with_info(wav_path, lenient=True)
+        assert list(loudness) == [0, 0.5, 0.5]
+        assert info.corruption.offset == 50
+        assert info.corruption.declared_frames is None
+        assert info.corruption.recovered_frames == info.frames == 3
+        assert info.corruption.reason == "the stream ends inside a frame"
+
+    def test_cmcut(self, tmp_path):
+        wav_path = self.write_cut_wav(tmp_path)
+        loudness = FrameLoudness.get_loudness_from_wav(wav_path, lenient=True)
+        assert len(loudness.values) == 94
+        self.assert_corruption(loudness.info)
+        sections = ProgramScenes.extract_silent_sections_from_wav(wav_path, 10, lenient=True)
+        assert [(section.start_sec, section.end_sec) for section in sections] == [(0, 50 / 8000)]
+        with pytest.raises(get_loudness_from_wav.TruncatedWavError):
+            ProgramScenes.extract_silent_sections_from_wav(wav_path, 10)
+
+
+class TestChannelMode:
+    stereo = [0, 0, 16384, 0, 16384, -16384, 0, 8192]
+
+    def test_channel_modes(self, tmp_path):
+        wav_path = write_wav(tmp_path / "stereo.wav", self.stereo, channels=2)
+        assert list(get_loudness_from_wav.wav2loudness(wav_path, "all")) == [0, 0.5, 0.5, 0.25]
+        assert list(get_loudness_from_wav.wav2loudness(wav_path, "mid")) == [0, 0.25, 0, 0.125]
+        assert list(get_loudness_from_wav.wav2loudness(wav_path, "side")) == [0, 0.25, 0.5, 0.125]
+        assert list(get_loudness_from_wav.wav2loudness(wav_path, 1)) == [0, 0, 0.5, 0.25]
+        assert list(get_loudness_from_wav.wav2loudness(wav_path, "interleaved")) == [
+            0, 0, 0.5, 0, 0.5, 0.5, 0, 0.25
+        ]
+        # Every interleaved sample by default, as wav2loudness always gave.
+        assert list(get_loudness_from_wav.wav2loudness(wav_path)) == [0, 0, 0.5, 0, 0.5, 0.5, 0, 0.25]
+        assert list(FrameLoudness.get_loudness_from_wav(wav_path).values) == [0, 0.5, 0.5, 0.25]
+
+    def test_channel_modes_fail(self, tmp_path):
+        wav_path = write_wav(tmp_path / "stereo.wav", self.stereo, channels=2)
+        with pytest.raises(ValueError) as e:
+            get_loudness_from_wav.wav2loudness(wav_path, 2)
+        assert "Channel index out of range:" in str(e.value)
+        with pytest.raises(ValueError) as e:
+            get_loudness_from_wav.wav2loudness(wav_path, "left")
+        assert "Channel mode must be" in str(e.value)
+        wav_path = write_wav(tmp_path / "mono.wav", [0, 1])
+        with pytest.raises(ValueError) as e:
+            get_loudness_from_wav.wav2loudness(wav_path, "side")
+        assert "Side downmix needs 2 channels:" in str(e.value)
+
+    def test_wav2channel_loudness(self, tmp_path):
+        wav_path = write_wav(tmp_path / "stereo.wav", self.stereo, channels=2)
+        left, right = get_loudness_from_wav.wav2channel_loudness(wav_path)
+        assert list(left) == [0, 0.5, 0.5, 0]
+        assert list(right) == [0, 0, 0.5, 0.25]
+
+    def test_frame_per_sec(self, tmp_path):
+        wav_path = write_wav(tmp_path / "stereo.wav", self.stereo, channels=2)
+        assert FrameLoudness.get_loudness_from_wav(wav_path).frame_per_sec == 8000
+        loudness = FrameLoudness.get_loudness_from_wav(wav_path, "interleaved")
+        assert loudness.frame_per_sec == 16000
+
+
+class TestEnvelope:
+    def test_wav2envelope(self, tmp_path):
+        samples = [0] * 80 + [16384, -16384] * 40 + [0] * 30
+        wav_path = write_wav(tmp_path / "tone.wav", samples)
+        envelope = get_loudness_from_wav.wav2envelope(wav_path)
+        assert envelope.window_frames == 80
+        assert envelope.hop_frames == 80
+        assert envelope.frame_per_sec == 100
+        assert envelope.info.frames == 190
+        assert list(envelope.values) == [0, 0.5, 0]
+
+    def test_wav2envelope_kinds(self, tmp_path):
+        wav_path = write_wav(tmp_path / "steps.wav", [0, 8192, 16384, 0, 0, 0], frame_rate=4)
+        peak = get_loudness_from_wav.wav2envelope(wav_path, 1, 0.5, "peak")
+        assert peak.frame_per_sec == 2
+        assert list(peak.values) == [0.5, 0.5, 0]
+        mean_abs = get_loudness_from_wav.wav2envelope(wav_path, 1, 0.5, "mean_abs")
+        assert list(mean_abs.values) == [0.1875, 0.125, 0]
+        gapped = get_loudness_from_wav.wav2envelope(wav_path, 0.25, 0.5, "peak")
+        assert list(gapped.values) == [0, 0.5, 0]
+        with pytest.raises(ValueError) as e:
+            get_loudness_from_wav.wav2envelope(wav_path, 0.1)
+        assert "window_sec must be at least one frame:" in str(e.value)
+        with pytest.raises(ValueError) as e:
+            get_loudness_from_wav.wav2envelope(wav_path, 1, None, "median")
+        assert "Envelope kind must be" in str(e.value)
+
+    def test_get_envelope_from_wav(self, tmp_path):
+        wav_path = write_wav(tmp_path / "tone.wav", [16384, -16384] * 160, channels=2)
+        loudness = FrameLoudness.get_envelope_from_wav(wav_path, hop_sec=0.005)
+        assert loudness.frame_per_sec == 200
+        assert list(loudness.values) == [0.5, 0.5, 0.5, 0.5]
+
+
+class TestLufs:
+    def test_wav2lufs(self, tmp_path):
+        # A -20 dBFS 1 kHz sine measures -23 LUFS, as 0 dBFS does -3.01 LUFS.
+        frame_rate = 48000
+        samples = [
+            round(3276.8 * math.sin(2 * math.pi * 1000 * t / frame_rate))
+            for t in range(frame_rate * 5)
+        ]
+        wav_path = write_wav(tmp_path / "sine.wav", samples, frame_rate=frame_rate)
+        loudness = get_loudness_from_wav.wav2lufs(wav_path)
+        assert loudness.frame_per_sec == 10
+        assert abs(loudness.integrated + 23.0) < 0.1
+        assert loudness.loudness_range < 0.1
+        assert len(loudness.momentary) == 47
+        assert len(loudness.short_term) == 21
+        assert all(abs(value + 23.0) < 0.1 for value in loudness.momentary[1:])
+
+    def test_wav2lufs_channel_mask(self, tmp_path):
+        # The same sine on a back channel is boosted by 1.5 dB, on the LFE channel ignored.
+        frame_rate = 48000
+        sine = [
+            round(3276.8 * math.sin(2 * math.pi * 1000 * t / frame_rate))
+            for t in range(frame_rate)
+        ]
+        integrated = []
+        for channel in [0, 3, 2]:
+            samples = [x if c == channel else 0 for x in sine for c in range(4)]
+            wav_path = write_pcm(
+                tmp_path / "quad.wav", struct.pack(f"<{len(samples)}h", *samples), 1, 16,
+                channels=4, frame_rate=frame_rate, channel_mask=0x1B,
+                )
+            integrated.append(get_loudness_from_wav.wav2lufs(wav_path).integrated)
+        assert abs(integrated[1] - integrated[0] - 1.49) < 0.01
+        assert integrated[2] == -math.inf
+
+    def test_wav2lufs_silence(self, tmp_path):
+        wav_path = write_wav(tmp_path / "silence.wav", [0] * 8000)
+        loudness = get_loudness_from_wav.wav2lufs(wav_path)
+        assert loudness.integrated == -math.inf
+        assert loudness.loudness_range == 0
+        assert list(loudness.momentary) == [-math.inf] * 7
+
+
+class TestWavInfo:
+    def test_wav_info(self, tmp_path):
+        wav_path = write_wav(tmp_path / "stereo.wav", [0] * 96000, channels=2, frame_rate=48000)
+        info = get_loudness_from_wav.wav_info(wav_path)
+        assert info.sample_rate == 48000
+        assert info.channels == 2
+        assert info.bits_per_sample == 16
+        assert info.sample_format == "int"
+        assert info.frames == 48000
+        assert info.duration_sec == 1
+
+    def test_wav2loudness_with_info(self, tmp_path):
+        wav_path = write_wav(tmp_path / "short.wav", [0, 16384], frame_rate=44100)
+        loudness, info = get_loudness_from_wav.wav2loudness_with_info(wav_path)
+        assert list(loudness) == [0, 0.5]
+        assert info.sample_rate == 44100
+        assert info.frames == 2
+
+
+class TestAnalysisRate:
+    def test_decimate(self, tmp_path):
+        wav_path = write_wav(tmp_path / "steady.wav", [16384] * 4801, frame_rate=48000)
+        loudness, info = get_loudness_from_wav.wav2loudness_with_info(wav_path, analysis_rate=8000)
+        assert len(loudness) == 801
+        assert info.sample_rate == 48000
+        assert info.analysis_rate == 8000
+        assert list(loudness[100:700]) == pytest.approx([0.5] * 600, abs=1e-4)
+        assert get_loudness_from_wav.wav_info(wav_path).analysis_rate == 48000
+        loudness = FrameLoudness.get_loudness_from_wav(wav_path, analysis_rate=8000)
+        assert loudness.frame_per_sec == 8000
+
+    def test_anti_alias(self, tmp_path):
+        # 6 kHz folds back to 2 kHz at 8000 fps unless it is filtered out.
+        samples = [round(16384 * math.sin(2 * math.pi * 6000 * i / 48000)) for i in range(4800)]
+        wav_path = write_wav(tmp_path / "tone.wav", samples, frame_rate=48000)
+        loudness = get_loudness_from_wav.wav2loudness(wav_path, analysis_rate=8000)
+        assert max(loudness[100:700]) < 0.001
+        loudness = get_loudness_from_wav.wav2loudness(wav_path, analysis_rate=16000)
+        assert max(loudness[200:1400]) > 0.4
+
+    def test_invalid_rate(self, tmp_path):
+        wav_path = write_wav(tmp_path / "short.wav", [0, 16384])
+        for analysis_rate in [0, 16000]:
+            with pytest.raises(ValueError) as e:
+                get_loudness_from_wav.wav2loudness(wav_path, analysis_rate=analysis_rate)
+            assert "analysis_rate must be positive and at most the sample rate:" in str(e.value)
+
+    def test_same_silences_at_any_rate(self, tmp_path):
+        pattern = [(16384, 0.05), (0, 0.03), (16384, 0.02), (0, 0.005), (16384, 0.05)]
+        boundaries = []
+        for frame_rate in [8000, 48000]:
+            samples = [value for value, sec in pattern for _ in range(round(sec * frame_rate))]
+            wav_path = write_wav(tmp_path / f"{frame_rate}.wav", samples, frame_rate=frame_rate)
+            silences = get_loudness_from_wav.detect_silences(
+                wav_path, threshold_dbfs=-40.0, analysis_rate=8000, min_duration_sec=0.01
+                )
+            assert silences.frame_per_sec == 8000
+            boundaries.append([sec for start, end in silences.sections for sec in (start / 8000, end / 8000)])
+        assert boundaries[0] == pytest.approx([0.05, 0.08], abs=0.002)
+        assert boundaries[1] == pytest.approx(boundaries[0], abs=0.002)
+
+
+class TestFrameLoudness:
+    def test_init(self, tmp_path):
+        wav_path = write_wav(tmp_path / "short.wav", [0, 16384, -16384])
+        loudness = FrameLoudness.get_loudness_from_wav(wav_path)
+        assert list(loudness.values) == [0, 0.5, 0.5]
+        assert loudness.frame_per_sec == 8000
+        assert loudness.info.sample_rate == 8000
+        loudness = FrameLoudness([0.0, 0.5])
+        assert list(loudness.values) == [0, 0.5]
+        assert loudness.frame_per_sec is None
+
+    def test_init_fail(self):
+        with pytest.raises(TypeError) as e:
+            FrameLoudness((0.0, 0.5))
+        assert "Loudness_array must be 1-D float array:" in str(e.value)
+        with pytest.raises(TypeError) as e:
+            FrameLoudness([])
+        assert "Loudness_array must be 1-D float array:" in str(e.value)
+        with pytest.raises(TypeError) as e:
+            FrameLoudness([0, 1])
+        assert "Loudness_array must be 1-D float array:" in str(e.value)
+        with pytest.raises(TypeError) as e:
+            FrameLoudness([0.0, -0.5])
+        assert "Loudness must be positive." in str(e.value)
+        with pytest.raises(ValueError) as e:
+            FrameLoudness([0.0, 0.5], 0)
+        assert "frame_per_sec must be positive:" in str(e.value)
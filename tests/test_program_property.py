@@ -0,0 +1,125 @@
+import pathlib
+
+import pytest
+
+from lib.cmcut import NominalCMStructure, ProgramProperty
+
+class TestNominalCMStructure:
+    def test_init(self):
+        """Test init."""
+        structure = NominalCMStructure({"scene": 30, "cm": 60}, 3.5)
+        assert structure.composition == {"scene": 30, "cm": 60}
+        assert structure.scene_position == "before"
+        assert structure.nominal_duration == 90
+        assert structure.margin_sec == 3.5
+        assert structure.get_actual_cm_section(10, 100) == (40, 100)
+        structure = NominalCMStructure({"cm": 60, "scene": 30}, 3.5)
+        assert structure.scene_position == "after"
+        assert structure.get_actual_cm_section(10, 100) == (10, 70)
+        structure = NominalCMStructure({"cm": 60, "scene_before": 30}, 1)
+        assert structure.scene_position == "before"
+        assert list(structure.composition) == ["scene", "cm"]
+        structure = NominalCMStructure({"monolithic_cm": 120}, 1)
+        assert (structure.cm, structure.monolithic_cm, structure.scene) == (None, 120, None)
+        assert structure.get_actual_cm_section(10, 100) == (10, 100)
+
+    def test_init_fail(self):
+        """Test init with failure."""
+        with pytest.raises(TypeError) as e:
+            NominalCMStructure([("cm", 60)], 3.5)
+        assert "Unexpected type for NominalCMStructure:" in str(e.value)
+        with pytest.raises(ValueError) as e:
+            NominalCMStructure({"scene": 30, "cm": 60, "monolithic_cm": 60}, 3.5)
+        assert "CM Structure length(must be less than 3) is too long:" in str(e.value)
+        with pytest.raises(ValueError) as e:
+            NominalCMStructure({"scene": 30}, 3.5)
+        assert "CM Structure must include 'cm' key." in str(e.value)
+        with pytest.raises(ValueError) as e:
+            NominalCMStructure({}, 3.5)
+        assert "CM Structure is empty." in str(e.value)
+        with pytest.raises(KeyError):
+            NominalCMStructure({"commercial": 60}, 3.5)
+        with pytest.raises(TypeError) as e:
+            NominalCMStructure({"cm": "60"}, 3.5)
+        assert "Type of value must be either integer or float:" in str(e.value)
+        with pytest.raises(ValueError) as e:
+            NominalCMStructure({"cm": 0}, 3.5)
+        assert "Value must be positive value:" in str(e.value)
+        with pytest.raises(ValueError) as e:
+            NominalCMStructure({"scene": 30, "scene_after": 30}, 3.5)
+        assert "CM Structure has more than one of the key:" in str(e.value)
+        with pytest.raises(TypeError) as e:
+            NominalCMStructure({"cm": 60}, "3.5")
+        assert "Type of margin sec must be either integer or float:" in str(e.value)
+        with pytest.raises(ValueError) as e:
+            NominalCMStructure({"cm": 60}, 0)
+        assert "margin_sec must be positive value:" in str(e.value)
+
+
+class TestProgramProperty:
+    def test_defaults(self):
+        """Test the defaults of keys left out."""
+        for program_property in [ProgramProperty(), ProgramProperty.from_json("{}")]:
+            assert [structure.composition for structure in program_property.cm_structures] == [{"cm": 60}] * 3
+            assert program_property.margin_sec == 3.5
+            assert all(structure.margin_sec == 3.5 for structure in program_property.cm_structures)
+            assert program_property.end_scene_duration_sec == 15
+            assert program_property.has_monolithic_cm is False
+            assert program_property.duration_threshold == 4000
+            assert program_property.duration_threshold_sec is None
+            assert program_property.duration_sec_units.durations_sec == [15, 30]
+            assert program_property.channel_mode == "all"
+            assert program_property.silence_threshold_dbfs is None
+            assert program_property.audio_stream == 0
+            assert program_property.cue_wav_path is None
+            assert program_property.lenient is False
+            assert program_property.analysis_rate is None
+
+    def test_load(self, tmp_path):
+        """Test reading a file like sample.json."""
+        program_property = ProgramProperty.load(str(pathlib.Path(__file__).parent.parent / "sample.json"))
+        assert [structure.composition for structure in program_property.cm_structures] == [{"cm": 135}, {"cm": 105}]
+        assert program_property.margin_sec == 2
+        assert program_property.end_scene_duration_sec == 0
+        assert program_property.duration_threshold == 3000
+        assert program_property.duration_sec_units.durations_sec == [15, 30, 60]
+        with pytest.raises(FileNotFoundError):
+            ProgramProperty.load(str(tmp_path / "missing.json"))
+
+    def test_values(self):
+        """Test values of every form."""
+        program_property = ProgramProperty.from_json("""{
+            "cm_structures": [{"cm": 60, "scene": 30}, {"scene": 30, "cm": 60}, {"scene_after": 5, "cm": 90}],
+            "channel_mode": 1,
+            "silence_threshold_dbfs": "auto",
+            "audio_stream": "0x0111",
+            "duration_threshold_sec": 0.5,
+            "analysis_rate": 8000,
+            "unknown": true
+            }""")
+        assert [structure.scene_position for structure in program_property.cm_structures] == ["after", "before", "after"]
+        assert program_property.channel_mode == 1
+        assert program_property.silence_threshold_dbfs == "auto"
+        assert program_property.audio_stream == "0x0111"
+        assert program_property.duration_threshold is None
+        assert program_property.duration_threshold_sec == 0.5
+        assert program_property.analysis_rate == 8000
+        assert ProgramProperty.from_json('{"silence_threshold_dbfs": -60}').silence_threshold_dbfs == -60
+
+    def test_errors(self):
+        """Test that errors point to the JSON path."""
+        cases = [
+            ('{"cm_structures": [{"cm": 60}, {"cm": "60"}]}', "at cm_structures[1].cm:"),
+            ('{"cm_structures": [{"cm": 60}, {"cm": -60}]}', "at cm_structures[1]: Value must be positive value:"),
+            ('{"cm_structures": [{"scene": 30}]}', "at cm_structures[0]: CM Structure must include 'cm' key."),
+            ('{"margin_sec": 0}', "at margin_sec: Value must be positive value:"),
+            ('{"additional_duration_units": [60, -5]}', "at additional_duration_units[1]:"),
+            ('{"channel_mode": "left"}', "at channel_mode:"),
+            ('{"audio_stream": -1}', "at audio_stream:"),
+            ('{"duration_threshold": 1.5}', "at duration_threshold:"),
+            ('{"margin_sec": 3', "Invalid program property: EOF"),
+            ]
+        for json, expected in cases:
+            with pytest.raises(ValueError) as e:
+                ProgramProperty.from_json(json)
+            assert expected in str(e.value)
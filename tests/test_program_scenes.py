@@ -1,6 +1,108 @@
+import random
+
 import pytest
 
-from lib.cmcut import ProgramScenes
+from lib.cmcut import (
+    DurationSecUnits, FrameLoudness, NominalCMStructure, ProgramProperty, ProgramScenes, SilentSection
+    )
+from tests.wav_files import write_wav
+
+
+def is_cm_divider_candidate_by_scan(section, following_sections, duration_sec_units, margin_sec):
+    """SilentSection.is_cm_divider_candidate as it was in Python, kept apart from the Rust one."""
+    for following_section in following_sections:
+        duration_sec = following_section.end_sec - section.start_sec
+        if duration_sec_units.durations_sec[-1] + margin_sec <= duration_sec:
+            break
+        for duration_sec_unit in duration_sec_units.durations_sec:
+            if duration_sec_unit < duration_sec <= duration_sec_unit + margin_sec:
+                return True
+    return False
+
+
+def construct_cm_sections_by_scan(silent_sections, duration_sec_units, cm_structures, has_monolithic_cm):
+    """ProgramScenes.construct_cm_sections as it was in Python, scanning following sections."""
+    if len(cm_structures) == 0:
+        return []
+    cm_num_threshold = 0 if has_monolithic_cm else 1
+    cm_divider_candidates = []
+    cm_sections = []
+    structure_reference = 0
+    target_cm_structure = cm_structures[structure_reference]
+    margin_sec = target_cm_structure.margin_sec
+    nominal_cm_duration = target_cm_structure.nominal_duration
+    for index, section in enumerate(silent_sections):
+        tmp_duration_sec_units = duration_sec_units
+        if target_cm_structure.monolithic_cm is not None:
+            tmp_duration_sec_units = duration_sec_units.append_duration(target_cm_structure.monolithic_cm)
+        if is_cm_divider_candidate_by_scan(section, silent_sections[index+1:], tmp_duration_sec_units, margin_sec):
+            cm_divider_candidates.append(section)
+        candidates_could_be_cm = len(cm_divider_candidates) > cm_num_threshold
+        if target_cm_structure.monolithic_cm is not None:
+            candidates_could_be_cm = len(cm_divider_candidates) == 1
+        if candidates_could_be_cm:
+            combined_duration_sec = section.end_sec - cm_divider_candidates[0].start_sec
+            if combined_duration_sec <= nominal_cm_duration:
+                continue
+            if nominal_cm_duration < combined_duration_sec < nominal_cm_duration + margin_sec:
+                cm_sections.append(target_cm_structure.get_actual_cm_section(
+                    cm_divider_candidates[0].end_sec, section.start_sec
+                    ))
+                structure_reference += 1
+                if structure_reference < len(cm_structures):
+                    target_cm_structure = cm_structures[structure_reference]
+                    nominal_cm_duration = target_cm_structure.nominal_duration
+                else:
+                    break
+            cm_divider_candidates = [cm_divider_candidates[-1]]
+    return cm_sections
+
+
+def search_cm_sections_by_scan(silent_sections, duration_sec_units):
+    """ProgramScenes.search_cm_sections as it was in Python, scanning following sections."""
+    margin_sec = 1
+    cm_divider_candidates = []
+    cm_sections = []
+    continuity = False
+    for index, section in enumerate(silent_sections):
+        if is_cm_divider_candidate_by_scan(section, silent_sections[index+1:], duration_sec_units, margin_sec):
+            cm_divider_candidates.append(section)
+            continuity = True
+        else:
+            if continuity and len(cm_divider_candidates) >= 2:
+                cm_sections.append((cm_divider_candidates[0].end_sec, cm_divider_candidates[-1].start_sec))
+                cm_divider_candidates = []
+                continuity = False
+    if len(cm_divider_candidates) >= 2:
+        cm_sections.append((cm_divider_candidates[0].end_sec, cm_divider_candidates[-1].start_sec))
+    return cm_sections
+
+
+def random_silences(rng):
+    time = 0.0
+    boundaries = []
+    for _ in range(rng.randint(5, 80)):
+        time += rng.choice([15, 30, 60, rng.uniform(0.1, 40)]) + rng.uniform(-0.3, 0.3)
+        duration = rng.uniform(0.01, 1)
+        boundaries.append((time, time + duration))
+        time += duration
+    return silences_at(boundaries, rng.choice([8000, 48000]))
+
+
+def write_program_wav(path, boundaries_sec, length_sec, frame_rate=1000):
+    """Write a program loud but for silences of digital zero at boundaries_sec."""
+    samples = [1000] * round(length_sec * frame_rate)
+    for start, end in boundaries_sec:
+        samples[round(start * frame_rate):round(end * frame_rate)] = [0] * round((end - start) * frame_rate)
+    return write_wav(path, samples, frame_rate=frame_rate)
+
+
+def silences_at(boundaries_sec, frame_per_sec=8000):
+    return [
+        SilentSection(round(start * frame_per_sec), round(end * frame_per_sec), frame_per_sec)
+        for start, end in boundaries_sec
+        ]
+
 
 @pytest.fixture
 def generate_scenes_inputs():
@@ -54,4 +156,164 @@ class TestProgramScenes:
             cm_sections = input[2]
             last_scene_duration = input[3]
             expected_output = input[4]
-            assert ProgramScenes.generate_scenes(first_start_sec_candidate, last_end_sec_candidate, cm_sections, last_scene_duration) == expected_output
\ No newline at end of file
+            assert ProgramScenes.generate_scenes(first_start_sec_candidate, last_end_sec_candidate, cm_sections, last_scene_duration) == expected_output
+
+    def test_resolve_frame_per_sec(self):
+        loudness = FrameLoudness([0.0, 0.5], 48000)
+        assert ProgramScenes.resolve_frame_per_sec(loudness, None) == 48000
+        assert ProgramScenes.resolve_frame_per_sec(loudness, 8000) == 8000
+        with pytest.raises(ValueError) as e:
+            ProgramScenes.resolve_frame_per_sec(FrameLoudness([0.0, 0.5]), None)
+        assert "frame_per_sec is unknown for the loudness." in str(e.value)
+
+
+    def test_extract_silent_sections_from_wav(self, tmp_path):
+        samples = [0] * 30 + [100] * 10 + [0] * 5 + [100] * 10 + [0] * 20 + [100] + [0] * 40
+        wav_path = write_wav(tmp_path / "silences.wav", samples)
+        loudness = FrameLoudness.get_loudness_from_wav(wav_path)
+        expected = ProgramScenes.extract_silent_sections(loudness, 10, 8000)
+        silent_sections = ProgramScenes.extract_silent_sections_from_wav(wav_path, 10)
+        # The trailing silence is never closed, as in extract_silent_sections.
+        boundaries = [(section.start_sec, section.end_sec) for section in silent_sections]
+        assert boundaries == [(section.start_sec, section.end_sec) for section in expected]
+        assert boundaries == [(0, 30 / 8000), (55 / 8000, 75 / 8000)]
+
+    def test_duration_threshold_sec(self, tmp_path):
+        samples = [0] * 30 + [100] * 10 + [0] * 5 + [100] * 10 + [0] * 20 + [100] + [0] * 40
+        loudness = FrameLoudness.get_loudness_from_wav(write_wav(tmp_path / "silences.wav", samples))
+        expected = ProgramScenes.extract_silent_sections(loudness, 10, 8000)
+        assert ProgramScenes.resolve_duration_frame_threshold(None, 10 / 8000, 8000) == 10
+        silent_sections = ProgramScenes.extract_silent_sections(loudness, None, 8000, 10 / 8000)
+        assert [(section.start_sec, section.end_sec) for section in silent_sections] == [
+            (section.start_sec, section.end_sec) for section in expected
+            ]
+        wav_path = write_wav(tmp_path / "silences_48k.wav", [s for s in samples for _ in range(6)], frame_rate=48000)
+        silent_sections = ProgramScenes.extract_silent_sections_from_wav(wav_path, None, duration_threshold_sec=10 / 8000)
+        assert [(section.start_sec, section.end_sec) for section in silent_sections] == [
+            (section.start_sec, section.end_sec) for section in expected
+            ]
+        with pytest.raises(ValueError) as e:
+            ProgramScenes.extract_silent_sections(loudness, 10, 8000, 10 / 8000)
+        assert "Give either duration_frame_threshold or duration_threshold_sec:" in str(e.value)
+        with pytest.raises(ValueError):
+            ProgramScenes.extract_silent_sections_from_wav(wav_path, None)
+
+
+class TestConstructCMSections:
+    def test_construct_cm_sections(self):
+        # Program until 100 sec, four 15 sec CMs, a 30 sec scene before a 60 sec CM, then program.
+        silences = silences_at([
+            (0, 0.5), (100, 100.5), (115, 115.5), (130, 130.5), (145, 145.5), (160, 160.5),
+            (190, 190.5), (205, 205.5), (220, 220.5), (235, 235.5), (250, 250.5), (400, 400.5),
+            ])
+        structures = [NominalCMStructure({"cm": 60}, 3.5), NominalCMStructure({"scene": 30, "cm": 60}, 3.5)]
+        cm_sections = ProgramScenes.construct_cm_sections(silences, DurationSecUnits([]), structures, False)
+        assert cm_sections == [(100.5, 160), (190.5, 250)]
+        assert cm_sections == construct_cm_sections_by_scan(silences, DurationSecUnits([]), structures, False)
+        assert ProgramScenes.construct_cm_sections(silences, DurationSecUnits([]), [], False) == []
+
+    def test_same_as_scan(self):
+        rng = random.Random(0)
+        structure_sets = [
+            [NominalCMStructure({"cm": 60}, 3.5)] * 3,
+            [NominalCMStructure({"cm": 30}, 2), NominalCMStructure({"cm": 45, "scene": 15}, 2)],
+            [NominalCMStructure({"monolithic_cm": 60}, 1), NominalCMStructure({"cm": 90}, 1)],
+            ]
+        for trial in range(40):
+            silences = random_silences(rng)
+            units = DurationSecUnits(rng.choice([[], [60], [5, 10]]))
+            structures = structure_sets[trial % len(structure_sets)]
+            has_monolithic_cm = rng.random() < 0.3
+            assert ProgramScenes.construct_cm_sections(silences, units, structures, has_monolithic_cm) == \
+                construct_cm_sections_by_scan(silences, units, structures, has_monolithic_cm)
+
+    def test_many_silences(self):
+        # Noisy recording of 3 hours with a short silence every 0.5 sec.
+        silences = silences_at([(0.5 * i, 0.5 * i + 0.1) for i in range(21600)])
+        structures = [NominalCMStructure({"cm": 60}, 3.5)] * 3
+        cm_sections = ProgramScenes.construct_cm_sections(silences, DurationSecUnits([]), structures, False)
+        assert len(cm_sections) == 3
+
+    def test_unsorted(self):
+        silences = silences_at([(10, 11), (0, 1)])
+        with pytest.raises(ValueError) as e:
+            ProgramScenes.construct_cm_sections(silences, DurationSecUnits([]), [NominalCMStructure({"cm": 60}, 1)], False)
+        assert "Silent sections must be sorted:" in str(e.value)
+
+
+
+class TestSearchCMSections:
+    def test_search_cm_sections(self):
+        # Program until 100 sec, four 15 sec CMs, program, then two 30 sec CMs.
+        silences = silences_at([
+            (0, 0.5), (100, 100.5), (115, 115.5), (130, 130.5), (145, 145.5), (160, 160.5),
+            (200, 200.5), (230, 230.5), (260, 260.5), (400, 400.5),
+            ])
+        cm_sections = ProgramScenes.search_cm_sections(silences, DurationSecUnits([]))
+        # The silence after the last CM of a series is not a divider, no unit following it.
+        assert cm_sections == [(100.5, 145), (200.5, 230)]
+        assert cm_sections == search_cm_sections_by_scan(silences, DurationSecUnits([]))
+
+    def test_same_as_scan(self):
+        rng = random.Random(1)
+        for _ in range(40):
+            silences = random_silences(rng)
+            units = DurationSecUnits(rng.choice([[], [60], [5, 10]]))
+            assert ProgramScenes.search_cm_sections(silences, units) == search_cm_sections_by_scan(silences, units)
+
+    def test_unsorted(self):
+        with pytest.raises(ValueError) as e:
+            ProgramScenes.search_cm_sections(silences_at([(10, 11), (0, 1)]), DurationSecUnits([]))
+        assert "Silent sections must be sorted:" in str(e.value)
+
+
+class TestDetectProgramScenes:
+    # Program until 20 sec, two 15 sec CMs, then program for 45 sec.
+    boundaries_sec = [(20, 20.8), (35, 35.8), (50, 50.8), (95, 95.8)]
+
+    def test_without_structure(self, tmp_path):
+        wav_path = write_program_wav(tmp_path / "program.wav", self.boundaries_sec, 100)
+        program_scenes = ProgramScenes.detect_program_scenes(wav_path)
+        assert program_scenes.scene_sections == [(0, 20.8), (35, 95.8)]
+        silent_sections = ProgramScenes.extract_silent_sections_from_wav(wav_path, None, duration_threshold_sec=0.625)
+        expected = ProgramScenes.construct_program_scenes_without_structure_from_silent_sections(
+            silent_sections, DurationSecUnits([])
+            )
+        assert program_scenes.scene_sections == expected.scene_sections
+
+    def test_with_property(self, tmp_path):
+        wav_path = write_program_wav(tmp_path / "program.wav", self.boundaries_sec, 100)
+        program_property = ProgramProperty.from_json(
+            '{"cm_structures": [{"cm": 30}], "end_scene_duration": 10, "margin_sec": 2, "duration_threshold_sec": 0.5}'
+            )
+        program_scenes = ProgramScenes.detect_program_scenes(wav_path, program_property)
+        # The last scene lasts end_scene_duration and end_margin_sec after the CM.
+        assert program_scenes.scene_sections == [(0, 20.8), (50, 61)]
+        silent_sections = ProgramScenes.extract_silent_sections_from_wav(wav_path, None, duration_threshold_sec=0.5)
+        expected = ProgramScenes.construct_program_scenes_from_silent_sections(
+            silent_sections, program_property.duration_sec_units, program_property.cm_structures, 10, False
+            )
+        assert program_scenes.scene_sections == expected.scene_sections
+        # The silences of the one pass over the file are kept for reporting.
+        assert len(program_scenes.silences.sections) == len(silent_sections)
+        assert program_scenes.silences.threshold_dbfs is None
+        assert program_scenes.silences.info.corruption is None
+        assert ProgramScenes(expected.scene_sections).silences is None
+
+    def test_subclass_attributes(self, tmp_path):
+        class WideMarginScenes(ProgramScenes):
+            end_margin_sec = 3
+
+        wav_path = write_program_wav(tmp_path / "program.wav", self.boundaries_sec, 100)
+        program_property = ProgramProperty.from_json(
+            '{"cm_structures": [{"cm": 30}], "end_scene_duration": 10, "margin_sec": 2, "duration_threshold_sec": 0.5}'
+            )
+        program_scenes = WideMarginScenes.detect_program_scenes(wav_path, program_property)
+        assert isinstance(program_scenes, WideMarginScenes)
+        assert program_scenes.scene_sections == [(0, 20.8), (50, 63)]
+
+    def test_no_silence(self, tmp_path):
+        wav_path = write_program_wav(tmp_path / "loud.wav", [], 10)
+        with pytest.raises(ValueError) as e:
+            ProgramScenes.detect_program_scenes(wav_path)
+        assert "No silent section is found" in str(e.value)
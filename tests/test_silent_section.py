@@ -1,3 +1,5 @@
+import pickle
+
 import pytest
 
 from lib.cmcut import SilentSection, DurationSecUnits
@@ -35,6 +37,38 @@ class TestSilentSection:
             silent_section = SilentSection(0, 10, -1)
         assert "frame_per_sec must be positive:" in str(e.value)
 
+    def test_frame_index(self):
+        """Test that boundaries are kept in frames."""
+        # 3 hours at 48 kHz.
+        silent_section = SilentSection(518399999, 518400000, 48000)
+        assert silent_section.start_frame_index == 518399999
+        assert silent_section.end_frame_index == 518400000
+        assert silent_section.end_sec == 10800
+        assert silent_section.duration_sec() == pytest.approx(1 / 48000)
+
+    def test_compare(self):
+        """Test comparison and hashing."""
+        assert SilentSection(0, 10, 2) == SilentSection(0, 10, 2)
+        assert SilentSection(0, 10, 2) != SilentSection(0, 10, 4)
+        assert SilentSection(0, 10, 2) != (0, 10, 2)
+        assert SilentSection(0, 10, 2) < SilentSection(0, 12, 2) < SilentSection(1, 2, 2)
+        assert SilentSection(0, 10, 4) < SilentSection(0, 10, 2)
+        assert sorted([SilentSection(3, 4), SilentSection(1, 2)]) == [SilentSection(1, 2), SilentSection(3, 4)]
+        assert len({SilentSection(0, 10, 2), SilentSection(0, 10, 2), SilentSection(0, 10)}) == 2
+
+    def test_pickle(self):
+        """Test pickling."""
+        silent_section = SilentSection(5, 10, 2)
+        restored = pickle.loads(pickle.dumps(silent_section))
+        assert restored == silent_section
+        assert (restored.start_sec, restored.end_sec, restored.frame_per_sec) == (2.5, 5, 2)
+
+    def test_repr(self):
+        """Test repr."""
+        assert repr(SilentSection(5, 10, 2)) == "[2.5, 5.0, 2]"
+        assert repr([SilentSection(0, 16000)]) == "[[0.0, 2.0, 8000]]"
+        assert repr(SilentSection(1, 3, 2.5)) == "[0.4, 1.2, 2.5]"
+
     def test_duration_sec(self):
         """Test duration_sec."""
         silent_section = SilentSection(0, 16000)
@@ -0,0 +1,121 @@
+import array
+import io
+import pathlib
+import struct
+import subprocess
+
+import pytest
+
+import get_loudness_from_wav
+from tests.wav_files import write_wav
+from tests.test_ts import write_ts, adts_frame, AAC_SILENCE
+
+
+SAMPLES = [5] * 5 + [0] * 20 + [1] + [0] * 5 + [5] + [0] * 30
+NOISE = [(-1) ** i * (10 - i % 4) for i in range(800)]
+NOISY_SAMPLES = [10000] * 800 + NOISE + [10000] * 800 + NOISE + [10000] * 800
+
+
+class TestInMemorySources:
+    def test_bytes_and_buffers(self, tmp_path):
+        wav_path = write_wav(tmp_path / "a.wav", SAMPLES)
+        expected = list(get_loudness_from_wav.wav2loudness(wav_path))
+        data = open(wav_path, "rb").read()
+        for source in [pathlib.Path(wav_path), data, bytearray(data), memoryview(data)]:
+            assert list(get_loudness_from_wav.wav2loudness(source)) == expected
+        silences = get_loudness_from_wav.detect_silences(data, min_duration=10)
+        assert silences.sections == [(5, 25)]
+
+    def test_transport_stream_bytes(self, tmp_path):
+        ts_path = write_ts(tmp_path / "adts.ts", [(0x111, 0x0F, [adts_frame(AAC_SILENCE)] * 2)])
+        data = open(ts_path, "rb").read()
+        assert get_loudness_from_wav.wav_info(data).frames == 2048
+        assert [stream.pid for stream in get_loudness_from_wav.list_audio_streams(data)] == [0x111]
+
+    def test_unsupported_source(self):
+        with pytest.raises(TypeError) as e:
+            get_loudness_from_wav.wav2loudness(1.5)
+        assert "Audio source must be" in str(e.value)
+        with pytest.raises(get_loudness_from_wav.MalformedWavError) as e:
+            get_loudness_from_wav.wav2loudness(b"RIFX" + bytes(8))
+        assert e.value.path == "<bytes>"
+
+
+class TestFileSources:
+    def test_file_object(self, tmp_path):
+        wav_path = write_wav(tmp_path / "noise.wav", NOISY_SAMPLES)
+        with open(wav_path, "rb") as wav_file:
+            silences = get_loudness_from_wav.detect_silences(wav_file, "auto", 100)
+        assert silences.sections == [(800, 1600), (2400, 3200)]
+        data = b"junk" + open(wav_path, "rb").read()
+        wav_file = io.BytesIO(data)
+        wav_file.seek(4)
+        silences = get_loudness_from_wav.detect_silences(wav_file, "auto", 100)
+        assert silences.sections == [(800, 1600), (2400, 3200)]
+
+    def test_pipe(self, tmp_path):
+        data = bytearray(open(write_wav(tmp_path / "a.wav", SAMPLES), "rb").read())
+        # Sizes of a WAV streamed by a recorder which cannot seek back to fill them in.
+        data[4:8] = data[40:44] = struct.pack("<I", 0xFFFFFFFF)
+        stream_path = tmp_path / "stream.wav"
+        stream_path.write_bytes(bytes(data))
+        process = subprocess.Popen(["cat", str(stream_path)], stdout=subprocess.PIPE)
+        loudness, info = get_loudness_from_wav.wav2loudness_with_info(process.stdout)
+        process.wait()
+        assert len(loudness) == len(SAMPLES)
+        assert info.frames == len(SAMPLES)
+        process = subprocess.Popen(["cat", str(stream_path)], stdout=subprocess.PIPE)
+        assert get_loudness_from_wav.wav_info(process.stdout).frames == len(SAMPLES)
+        process.wait()
+        process = subprocess.Popen(["cat", str(stream_path)], stdout=subprocess.PIPE)
+        with pytest.raises(ValueError) as e:
+            get_loudness_from_wav.detect_silences(process.stdout, "auto", 10)
+        assert "not seekable" in str(e.value)
+        # Raised before the pipe is consumed, so that the caller can still read it.
+        assert process.stdout.read() == bytes(data)
+        process.stdout.close()
+        process.wait()
+
+    def test_text_file(self, tmp_path):
+        wav_path = write_wav(tmp_path / "a.wav", SAMPLES)
+        with open(wav_path, "r", encoding="latin-1") as wav_file:
+            with pytest.raises(get_loudness_from_wav.WavError) as e:
+                get_loudness_from_wav.wav2loudness(wav_file)
+        assert "binary mode" in str(e.value)
+        assert e.value.path == wav_path
+
+
+class TestDecodedAudio:
+    def test_mono(self):
+        audio = get_loudness_from_wav.DecodedAudio(array.array("f", [0.5, -0.25, 0, 0]), 8000)
+        assert (audio.sample_rate, audio.channels, audio.frames) == (8000, 1, 4)
+        loudness, info = get_loudness_from_wav.wav2loudness_with_info(audio)
+        assert list(loudness) == [0.5, 0.25, 0, 0]
+        assert info.sample_format == "float"
+        assert info.frames == 4
+        assert list(get_loudness_from_wav.wav2loudness(get_loudness_from_wav.DecodedAudio([0.5, 0.0], 8000))) == [0.5, 0]
+
+    def test_channels(self):
+        samples = array.array("h", [16384, 0, 0, -8192, 0, 0])
+        frames = memoryview(samples).cast("B").cast("h", [3, 2])
+        audio = get_loudness_from_wav.DecodedAudio(frames, 8000)
+        assert audio.channels == 2
+        channels = get_loudness_from_wav.wav2channel_loudness(audio)
+        assert [list(values) for values in channels] == [[0.5, 0, 0], [0, 0.25, 0]]
+        interleaved = get_loudness_from_wav.DecodedAudio(samples, 8000, channels=2)
+        assert interleaved.frames == 3
+        assert get_loudness_from_wav.wav_info(interleaved).sample_format == "int"
+
+    def test_invalid_samples(self):
+        with pytest.raises(ValueError):
+            get_loudness_from_wav.DecodedAudio(array.array("f", [0, 0, 0]), 8000, channels=2)
+        with pytest.raises(ValueError):
+            get_loudness_from_wav.DecodedAudio(array.array("f", [0]), 0)
+        transposed = memoryview(array.array("f", [0] * 200)).cast("B").cast("f", [2, 100])
+        with pytest.raises(ValueError) as e:
+            get_loudness_from_wav.DecodedAudio(transposed, 8000)
+        assert "Transpose samples of shape (channels, frames)" in str(e.value)
+        with pytest.raises(ValueError):
+            get_loudness_from_wav.DecodedAudio(array.array("f", [0] * 1000), 8000, channels=1000)
+        with pytest.raises(get_loudness_from_wav.AudioStreamNotFoundError):
+            get_loudness_from_wav.wav2loudness(get_loudness_from_wav.DecodedAudio([0.0], 8000), audio_stream=1)
@@ -0,0 +1,300 @@
+import struct
+
+import pytest
+
+import get_loudness_from_wav
+from lib.cmcut import ProgramScenes, FrameLoudness
+from tests.wav_files import write_wav
+
+
+def crc32_mpeg2(data):
+    crc = 0xFFFFFFFF
+    for byte in data:
+        crc ^= byte << 24
+        for _ in range(8):
+            crc = ((crc << 1) ^ 0x04C11DB7 if crc & 0x80000000 else crc << 1) & 0xFFFFFFFF
+    return crc
+
+
+def psi_section(table_id, table_id_extension, body):
+    section = struct.pack(">BHHBBB", table_id, 0xB000 | (9 + len(body)), table_id_extension, 0xC1, 0, 0)
+    section += body
+    return section + struct.pack(">I", crc32_mpeg2(section))
+
+
+def ts_packets(pid, payload, continuity=0):
+    packets = b""
+    for index, start in enumerate(range(0, len(payload), 184)):
+        chunk = payload[start:start + 184]
+        unit_start = 0x4000 if index == 0 else 0
+        if len(chunk) == 184:
+            packets += struct.pack(">BHB", 0x47, unit_start | pid, 0x10 | (continuity + index) % 16) + chunk
+        else:
+            stuffing = 183 - len(chunk)
+            adaptation = bytes([stuffing]) + (b"\x00" + b"\xff" * (stuffing - 1) if stuffing else b"")
+            packets += struct.pack(">BHB", 0x47, unit_start | pid, 0x30 | (continuity + index) % 16) + adaptation + chunk
+    return packets
+
+
+def pes_packet(frame, pts):
+    pts_bytes = bytes([
+        0x21 | (pts >> 29) & 0x0E, (pts >> 22) & 0xFF, 0x01 | (pts >> 14) & 0xFE,
+        (pts >> 7) & 0xFF, 0x01 | (pts << 1) & 0xFE,
+        ])
+    return b"\x00\x00\x01\xc0" + struct.pack(">H", 8 + len(frame)) + b"\x80\x80\x05" + pts_bytes + frame
+
+
+def write_ts(path, streams, start_pts=900000, frame_pts=1920, descriptors=None):
+    """Write a transport stream of one program.
+    Args:
+        streams (List[Tuple[int, int, List[bytes]]]): PID, stream type and frames of each stream.
+        descriptors (Optional[Dict[int, bytes]]): ES descriptors in the PMT by PID.
+    """
+    descriptors = descriptors or {}
+    pmt_body = struct.pack(">HH", 0xFFFF, 0xF000)
+    for pid, stream_type, _ in streams:
+        es_info = descriptors.get(pid, b"")
+        pmt_body += struct.pack(">BHH", stream_type, 0xE000 | pid, 0xF000 | len(es_info)) + es_info
+    data = ts_packets(0, b"\x00" + psi_section(0, 1, struct.pack(">HH", 1, 0xE100)))
+    data += ts_packets(0x100, b"\x00" + psi_section(2, 1, pmt_body))
+    continuity = {pid: 0 for pid, _, _ in streams}
+    for index in range(max(len(frames) for _, _, frames in streams)):
+        for pid, _, frames in streams:
+            if index < len(frames):
+                packets = ts_packets(pid, pes_packet(frames[index], start_pts + index * frame_pts), continuity[pid])
+                continuity[pid] += len(packets) // 188
+                data += packets
+    open(path, "wb").write(data)
+    return str(path)
+
+
+def bits_to_bytes(bits):
+    bits += "0" * (-len(bits) % 8)
+    return int(bits, 2).to_bytes(len(bits) // 8, "big")
+
+
+# raw_data_block of a silent mono AAC-LC frame: a SCE with no scale factor bands and an END.
+AAC_SILENCE = bits_to_bytes("000" "0000" "01100100" "0" "00" "0" "000000" "0" "000" "111")
+
+
+def adts_frame(raw):
+    """ADTS frame of mono AAC-LC at 48 kHz."""
+    length = 7 + len(raw)
+    return bytes([
+        0xFF, 0xF1, 0x4C, 0x40 | length >> 11, (length >> 3) & 0xFF, (length & 7) << 5 | 0x1F, 0xFC,
+        ]) + raw
+
+
+def loas_frame(raw):
+    """LOAS frame of mono AAC-LC at 48 kHz carrying its StreamMuxConfig."""
+    mux_element = bits_to_bytes(
+        "0" "0" "1" "000000" "0000" "000"
+        "00010" "0011" "0001" "0" "0" "0"
+        "000" "11111111" "0" "0"
+        + format(len(raw), "08b") + "".join(format(byte, "08b") for byte in raw)
+        )
+    return struct.pack(">H", 0x56E0 | len(mux_element) >> 8) + bytes([len(mux_element) & 0xFF]) + mux_element
+
+
+def language_descriptor(language):
+    return b"\x0a\x04" + language.encode() + b"\x00"
+
+
+def stream_identifier_descriptor(component_tag):
+    return bytes([0x52, 1, component_tag])
+
+
+def mpeg_layer1_frame(loud, mono=False):
+    """64 byte MPEG-1 layer I frame of stereo at 48 kHz, with a constant first subband if loud,
+    or a silent one of a single channel if mono."""
+    if mono:
+        return b"\xff\xff\x24\xc0" + bytes(60)
+    if not loud:
+        return b"\xff\xff\x24\x00" + bytes(60)
+    body = "0100" * 2 + "0000" * 62 + "000011" * 2 + "11111" * 24
+    return b"\xff\xff\x24\x00" + bits_to_bytes(body + "0" * (480 - len(body)))
+
+
+class TestTransportStream:
+    def test_adts(self, tmp_path):
+        ts_path = write_ts(tmp_path / "adts.ts", [(0x111, 0x0F, [adts_frame(AAC_SILENCE)] * 10)])
+        loudness, info = get_loudness_from_wav.wav2loudness_with_info(ts_path)
+        assert list(loudness) == [0] * 10240
+        assert info.sample_rate == 48000
+        assert info.channels == 1
+        assert info.sample_format == "float"
+        assert info.frames == 10240
+        assert info.start_time_sec == 10
+        assert get_loudness_from_wav.wav_info(ts_path).frames == 10240
+
+    def test_latm(self, tmp_path):
+        ts_path = write_ts(tmp_path / "latm.ts", [(0x111, 0x11, [loas_frame(AAC_SILENCE)] * 3)])
+        assert list(get_loudness_from_wav.wav2loudness(ts_path)) == [0] * 3072
+
+    def test_mpeg_audio(self, tmp_path):
+        frames = [mpeg_layer1_frame(loud) for loud in [False] * 20 + [True] * 20 + [False] * 20 + [True] * 5]
+        ts_path = write_ts(tmp_path / "mp1.ts", [(0x111, 0x03, frames)], frame_pts=720)
+        loudness = get_loudness_from_wav.wav2loudness(ts_path, "all")
+        assert len(loudness) == 65 * 384
+        assert max(loudness[20 * 384:40 * 384]) > 0.1
+        silences = get_loudness_from_wav.detect_silences(ts_path, min_duration=1000)
+        assert silences.frame_per_sec == 48000
+        assert silences.sections[0][0] == 0
+        assert abs(silences.sections[0][1] - 20 * 384) < 384
+        assert abs(silences.sections[1][0] - 40 * 384) < 600
+        # Streaming detection agrees with the loudness timeseries.
+        silent_sections = ProgramScenes.extract_silent_sections(FrameLoudness(loudness), 1000, 48000)
+        assert [(section.start_sec, section.end_sec) for section in silent_sections] == [
+            (start / 48000, end / 48000) for start, end in silences.sections
+            ]
+
+    def test_layout_change(self, tmp_path):
+        # Stereo, then mono as at a CM boundary, then stereo again.
+        frames = [mpeg_layer1_frame(True)] * 5 + [mpeg_layer1_frame(False, mono=True)] * 5 + [mpeg_layer1_frame(True)] * 5
+        ts_path = write_ts(tmp_path / "switch.ts", [(0x111, 0x03, frames)], frame_pts=720)
+        left, right = get_loudness_from_wav.wav2channel_loudness(ts_path)
+        assert len(left) == len(right) == 15 * 384
+        assert max(left[:5 * 384]) > 0.1 and max(left[11 * 384:]) > 0.1
+        assert max(left[7 * 384:10 * 384]) == max(right[7 * 384:10 * 384]) == 0
+        assert get_loudness_from_wav.wav_info(ts_path).channels == 2
+        # Stereo samples are downmixed to the mono layout the stream starts with.
+        ts_path = write_ts(tmp_path / "mono_first.ts", [(0x111, 0x03, frames[5:])], frame_pts=720)
+        loudness = get_loudness_from_wav.wav2loudness(ts_path)
+        assert len(loudness) == 10 * 384
+        assert max(loudness[:4 * 384]) == 0
+        assert max(loudness[6 * 384:]) > 0.1
+        assert get_loudness_from_wav.wav_info(ts_path).channels == 1
+
+    def test_no_audio(self, tmp_path):
+        ts_path = write_ts(tmp_path / "video.ts", [(0x111, 0x1B, [b"\x00" * 100])])
+        with pytest.raises(get_loudness_from_wav.MalformedWavError) as e:
+            get_loudness_from_wav.wav2loudness(ts_path)
+        assert "no audio stream" in str(e.value)
+
+
+class TestAudioStreamSelection:
+    @staticmethod
+    def write_bilingual_ts(path):
+        """Main audio with a loud part and sub audio silent throughout."""
+        main_frames = [mpeg_layer1_frame(loud) for loud in [False] * 20 + [True] * 20 + [False] * 20 + [True] * 5]
+        sub_frames = [mpeg_layer1_frame(False)] * 65
+        return write_ts(
+            path,
+            [(0x111, 0x03, main_frames), (0x112, 0x04, sub_frames)],
+            frame_pts=720,
+            descriptors={
+                0x111: language_descriptor("jpn") + stream_identifier_descriptor(0x10),
+                0x112: language_descriptor("eng") + stream_identifier_descriptor(0x11),
+                },
+            )
+
+    def test_list_audio_streams(self, tmp_path):
+        ts_path = self.write_bilingual_ts(tmp_path / "bilingual.ts")
+        streams = get_loudness_from_wav.list_audio_streams(ts_path)
+        assert [stream.index for stream in streams] == [0, 1]
+        assert [stream.pid for stream in streams] == [0x111, 0x112]
+        assert [stream.stream_type for stream in streams] == [0x03, 0x04]
+        assert [stream.codec for stream in streams] == ["mpeg", "mpeg"]
+        assert [stream.language for stream in streams] == ["jpn", "eng"]
+        assert [stream.component_tag for stream in streams] == [0x10, 0x11]
+        assert streams[0].component_type is None
+        wav_path = write_wav(tmp_path / "a.wav", [0, 1])
+        assert get_loudness_from_wav.list_audio_streams(wav_path) == []
+
+    def test_select_audio_stream(self, tmp_path):
+        ts_path = self.write_bilingual_ts(tmp_path / "bilingual.ts")
+        loudness, info = get_loudness_from_wav.wav2loudness_with_info(ts_path)
+        assert info.audio_pid == 0x111
+        assert max(loudness) > 0.1
+        for audio_stream in [1, "0x0112", "eng", "ENG"]:
+            loudness, info = get_loudness_from_wav.wav2loudness_with_info(ts_path, audio_stream=audio_stream)
+            assert info.audio_pid == 0x112
+            assert max(loudness) == 0
+        info = get_loudness_from_wav.wav_info(ts_path, audio_stream="jpn")
+        assert info.audio_pid == 0x111
+        assert [stream.pid for stream in info.audio_streams] == [0x111, 0x112]
+        info = get_loudness_from_wav.wav_info(write_wav(tmp_path / "a.wav", [0, 1]))
+        assert info.audio_pid is None
+        assert info.audio_streams == []
+
+    def test_audio_stream_not_found(self, tmp_path):
+        ts_path = self.write_bilingual_ts(tmp_path / "bilingual.ts")
+        for audio_stream in [2, "0x0113", "fra"]:
+            with pytest.raises(get_loudness_from_wav.AudioStreamNotFoundError) as e:
+                get_loudness_from_wav.detect_silences(ts_path, audio_stream=audio_stream)
+            assert "0x0111, 0x0112" in str(e.value)
+            assert isinstance(e.value, get_loudness_from_wav.WavError)
+        with pytest.raises(get_loudness_from_wav.AudioStreamNotFoundError):
+            get_loudness_from_wav.wav2loudness(write_wav(tmp_path / "a.wav", [0, 1]), audio_stream=1)
+        with pytest.raises(ValueError):
+            get_loudness_from_wav.wav2loudness(ts_path, audio_stream="0xZZ")
+
+    def test_compare_audio_streams(self, tmp_path):
+        ts_path = self.write_bilingual_ts(tmp_path / "bilingual.ts")
+        comparison = ProgramScenes.compare_audio_streams(ts_path, 1000)
+        assert [stream.pid for stream in comparison.streams] == [0x111, 0x112]
+        assert len(comparison.silent_sections[0]) == 2
+        assert comparison.silent_sections[1] == []
+        assert comparison.silent_ratios[1] == 1
+        assert comparison.silent_ratios[0] < 0.7
+        assert comparison.unmatched_sections[0] == comparison.silent_sections[0]
+        assert comparison.unmatched_sections[1] == []
+        assert comparison.has_disagreement()
+        with pytest.raises(ValueError):
+            ProgramScenes.compare_audio_streams(write_wav(tmp_path / "a.wav", [0, 1]), 1000)
+
+
+class TestLenientTransportStream:
+    # PES of the 11th frame, each 78 byte PES filling the end of a packet after the PAT and PMT.
+    damaged_packet = 188 * 12
+    damaged_pes = damaged_packet + 188 - 78
+
+    def write_damaged_ts(self, tmp_path, damage):
+        frames = [mpeg_layer1_frame(True)] * 20
+        ts_path = write_ts(tmp_path / "damaged.ts", [(0x111, 0x03, frames)], frame_pts=720)
+        data = bytearray(open(ts_path, "rb").read())
+        damage(data)
+        open(ts_path, "wb").write(data)
+        return ts_path
+
+    def assert_filled(self, ts_path, reason):
+        loudness, info = get_loudness_from_wav.wav2loudness_with_info(ts_path, "all", lenient=True)
+        # The frame dropped is filled with silence, so the frames after it keep their timing.
+        assert len(loudness) == info.frames == 20 * 384
+        assert max(loudness[10 * 384:11 * 384]) == 0
+        assert max(loudness[12 * 384:]) > 0.1
+        assert reason in info.corruption.reason
+        assert info.corruption.offset == self.damaged_packet
+        assert info.corruption.recovered_frames == 10 * 384
+        assert info.corruption.filled_frames == 384
+        assert info.corruption.declared_frames is None
+
+    def test_pes_without_start_code(self, tmp_path):
+        def damage(data):
+            data[self.damaged_pes] = 0xFF
+        ts_path = self.write_damaged_ts(tmp_path, damage)
+        with pytest.raises(get_loudness_from_wav.MalformedWavError) as e:
+            get_loudness_from_wav.wav2loudness(ts_path)
+        assert "PES packet without start code" in str(e.value)
+        self.assert_filled(ts_path, "PES packet without start code")
+
+    def test_corrupt_frame(self, tmp_path):
+        def damage(data):
+            # Bit allocations of 15, which are forbidden.
+            data[self.damaged_pes + 14 + 4] = 0xFF
+        ts_path = self.write_damaged_ts(tmp_path, damage)
+        with pytest.raises(get_loudness_from_wav.MalformedWavError):
+            get_loudness_from_wav.wav2loudness(ts_path)
+        self.assert_filled(ts_path, "invalid bit allocation")
+
+    def test_lost_packet(self, tmp_path):
+        def damage(data):
+            del data[self.damaged_packet:self.damaged_packet + 188]
+        ts_path = self.write_damaged_ts(tmp_path, damage)
+        self.assert_filled(ts_path, "continuity counter of the audio jumps from 9 to 11")
+
+    def test_intact(self, tmp_path):
+        ts_path = self.write_damaged_ts(tmp_path, lambda data: None)
+        loudness, info = get_loudness_from_wav.wav2loudness_with_info(ts_path, "all", lenient=True)
+        assert len(loudness) == 20 * 384
+        assert info.corruption is None
@@ -0,0 +1,61 @@
+import math
+
+import pytest
+
+import get_loudness_from_wav
+from tests.wav_files import write_wav
+
+
+SAMPLES = [16384] * 5 + [0] * 20 + [-8192] * 10 + [0] * 5
+
+
+class TestWavView:
+    def test_loudness(self, tmp_path):
+        wav_path = write_wav(tmp_path / "a.wav", SAMPLES)
+        view = get_loudness_from_wav.WavView(wav_path)
+        assert len(view) == view.frames == view.info.frames == 40
+        expected = list(get_loudness_from_wav.wav2loudness(wav_path))
+        assert list(view.loudness(0)) == expected
+        assert list(view.loudness(20, 30)) == expected[20:30]
+        assert list(view.loudness(40)) == []
+
+    def test_samples(self, tmp_path):
+        wav_path = write_wav(tmp_path / "stereo.wav", [16384, 0, 0, -8192, 0, 0], channels=2)
+        view = get_loudness_from_wav.WavView(wav_path)
+        assert list(view.samples(0)) == [0.5, 0, 0, -0.25, 0, 0]
+        assert list(view.samples(1, 2)) == [0, -0.25]
+        assert list(view.loudness(0, channel_mode=1)) == [0, 0.25, 0]
+
+    def test_stats(self, tmp_path):
+        view = get_loudness_from_wav.WavView(write_wav(tmp_path / "a.wav", SAMPLES))
+        stats = view.stats(0, 25)
+        assert stats.count == 25
+        assert stats.peak == 0.5
+        assert stats.peak_dbfs == pytest.approx(-6.0206, abs=1e-4)
+        assert stats.rms == pytest.approx(math.sqrt(0.25 * 5 / 25))
+        assert stats.mean_abs == pytest.approx(0.1)
+        assert stats.zero_count == 20
+        silent = view.stats(5, 25)
+        assert silent.rms_dbfs == -math.inf
+
+    def test_envelope(self, tmp_path):
+        wav_path = write_wav(tmp_path / "a.wav", SAMPLES * 10)
+        view = get_loudness_from_wav.WavView(wav_path)
+        expected = get_loudness_from_wav.wav2envelope(wav_path, 0.001, kind="peak")
+        envelope = view.envelope(80, 240, 0.001, kind="peak")
+        assert envelope.window_frames == 8
+        assert list(envelope.values) == list(expected.values)[10:30]
+
+    def test_invalid_range(self, tmp_path):
+        view = get_loudness_from_wav.WavView(write_wav(tmp_path / "a.wav", SAMPLES))
+        for start, end in [(10, 5), (0, 41), (41, None)]:
+            with pytest.raises(ValueError) as e:
+                view.stats(start, end)
+            assert "Frame range out of the data:" in str(e.value)
+
+    def test_truncated(self, tmp_path):
+        wav_path = write_wav(tmp_path / "a.wav", SAMPLES)
+        data = open(wav_path, "rb").read()
+        open(wav_path, "wb").write(data[:-10])
+        with pytest.raises(get_loudness_from_wav.TruncatedWavError):
+            get_loudness_from_wav.WavView(wav_path)
@@ -0,0 +1,12 @@
+import struct
+import wave
+
+
+def write_wav(path, samples, sample_width=2, channels=1, frame_rate=8000):
+    formats = {1: "B", 2: "h", 4: "i"}
+    with wave.open(str(path), "wb") as wav_file:
+        wav_file.setnchannels(channels)
+        wav_file.setsampwidth(sample_width)
+        wav_file.setframerate(frame_rate)
+        wav_file.writeframes(struct.pack(f"<{len(samples)}{formats[sample_width]}", *samples))
+    return str(path)